@@ -1,28 +1,55 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, FilterContext, FilterFragment, FilterGraph};
+use crate::actions::ab_replace::{ab_fragment, AbMode};
 
-// Note: Advanced AB replace - placeholder implementation
 pub struct AbAdvancedReplaceAction;
 
+const AB_FIELD: ParamSpec = ParamSpec {
+    name: "ab_field",
+    label: "B视频场",
+    kind: ParamKind::Choice {
+        options: &[
+            ("bottom", "下场 (奇数行)"),
+            ("top", "上场 (偶数行)"),
+            ("alternate", "逐帧交替"),
+        ],
+        default: "bottom",
+    },
+    unit: "",
+    description: "隔行取 B 视频的画面，逐帧交替时上下场每帧互换",
+};
+
 impl VideoAction for AbAdvancedReplaceAction {
     fn id(&self) -> &'static str {
         "ab_advanced_replace"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "高级AB替换",
+            name_en: "Advanced AB replace",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[Material::AbVideo],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[AB_FIELD]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_adv")?;
-        
-        // Placeholder implementation
-        let vf = "eq=contrast=1.08:brightness=0.02";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mode = match config.text(&AB_FIELD)?.as_str() {
+            "top" => AbMode::Field { bottom: false, alternate: false },
+            "alternate" => AbMode::Field { bottom: true, alternate: true },
+            _ => AbMode::Field { bottom: true, alternate: false },
+        };
+        ab_fragment(self.id(), config, mode).map(Some)
     }
 }
@@ -1,6 +1,6 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct AbBlendAction;
 
@@ -9,19 +9,25 @@ impl VideoAction for AbBlendAction {
         "ab_blend"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AB混合模式",
+            name_en: "AB blend",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_blend")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
         // Simple blend effect using overlay
-        let filter_complex = "[0:v]split=2[a][b];[a][b]blend=all_mode=overlay:all_opacity=0.5";
+        let graph = "[in]split=2[a][b];[a][b]blend=all_mode=overlay:all_opacity=0.5";
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter_complex", filter_complex,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Graph(graph.to_string()))))
     }
 }
@@ -1,6 +1,6 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct AbChromaAction;
 
@@ -9,20 +9,24 @@ impl VideoAction for AbChromaAction {
         "ab_chroma"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AB色度偏移",
+            name_en: "AB chroma shift",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_chroma")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
         // Chromatic aberration effect using chromashift (works on YUV, efficient and robust)
         // cb/cr shift values create the color fringe
-        let filter_complex = "chromashift=cb=4:cr=-4:edge=smear";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter_complex", filter_complex,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain("chromashift=cb=4:cr=-4:edge=smear".to_string()))))
     }
 }
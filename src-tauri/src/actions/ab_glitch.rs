@@ -1,6 +1,6 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct AbGlitchAction;
 
@@ -9,19 +9,23 @@ impl VideoAction for AbGlitchAction {
         "ab_glitch"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AB故障效果",
+            name_en: "AB glitch",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_glitch")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
         // Glitch effect using noise and color shift
-        let vf = "noise=alls=20:allf=t,hue=s=0.8";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain("noise=alls=20:allf=t,hue=s=0.8".to_string()))))
     }
 }
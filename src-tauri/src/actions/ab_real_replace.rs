@@ -1,28 +1,48 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, FilterContext, FilterFragment, FilterGraph};
+use crate::actions::ab_replace::{ab_fragment, AbMode};
 
-// Note: Real AB replace - placeholder implementation
 pub struct AbRealReplaceAction;
 
+const AB_OPACITY: ParamSpec = ParamSpec {
+    name: "ab_opacity",
+    label: "B视频透明度",
+    kind: ParamKind::Float { min: 0.01, max: 0.5, default: 0.08 },
+    unit: "",
+    description: "B 视频叠在每一帧上的不透明度，0.08 代表 8%，越低越难被肉眼察觉",
+};
+
 impl VideoAction for AbRealReplaceAction {
     fn id(&self) -> &'static str {
         "ab_real_replace"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "真实AB替换",
+            name_en: "Real AB replace",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[Material::AbVideo],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[AB_OPACITY]
+    }
+
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[AB_OPACITY]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_real")?;
-        
-        // Placeholder implementation
-        let vf = "eq=saturation=1.1";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let opacity = config.float(&AB_OPACITY)?;
+        ab_fragment(self.id(), config, AbMode::Blend(opacity)).map(Some)
     }
 }
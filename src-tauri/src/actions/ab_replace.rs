@@ -1,30 +1,137 @@
-use std::path::Path;
-use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, bail};
+use rand::seq::SliceRandom;
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
+use crate::core::engine::scan_videos;
 
-// Note: This action requires a replacement video B as input
-// For now, it's a placeholder that applies a simple effect
 pub struct AbReplaceAction;
 
+const AB_INTERVAL: ParamSpec = ParamSpec {
+    name: "ab_interval",
+    label: "替换间隔",
+    kind: ParamKind::Int { min: 2, max: 120, default: 5 },
+    unit: "帧",
+    description: "每 N 帧中的最后一帧换成 B 视频的画面",
+};
+
+/// How the frames of the B video are mixed into the main (A) video
+pub(crate) enum AbMode {
+    /// Every `n`th frame comes from B
+    EveryNth(i64),
+    /// B is laid over every frame at this opacity
+    Blend(f64),
+    /// Every other line comes from B; `alternate` swaps the field on each frame
+    Field { bottom: bool, alternate: bool },
+}
+
+impl AbMode {
+    /// `blend` expression picking each pixel from A or B
+    fn expr(&self) -> String {
+        match *self {
+            AbMode::EveryNth(n) => format!("if(eq(mod(N,{}),{}),B,A)", n, n - 1),
+            AbMode::Blend(opacity) => format!("A*{:.3}+B*{:.3}", 1.0 - opacity, opacity),
+            AbMode::Field { bottom, alternate: false } => format!("if(eq(mod(Y,2),{}),B,A)", bottom as u8),
+            AbMode::Field { bottom, alternate: true } => format!("if(eq(mod(Y+N,2),{}),B,A)", bottom as u8),
+        }
+    }
+}
+
+/// Mix the configured B video into the main one; the audio stays A's. B is scaled to the main
+/// video and looped when shorter. A directory is a pool: each file gets one of its videos.
+/// Without a B video the stage is a stream copy.
+pub(crate) fn ab_fragment(action_id: &str, config: &ActionConfig, mode: AbMode) -> Result<FilterFragment> {
+    let Some(path) = Material::AbVideo.path(config) else {
+        return Ok(FilterFragment::default());
+    };
+    let b = pick_b_video(Path::new(path), config, action_id)?;
+
+    // blend needs both inputs at the same size, pixel format and SAR
+    let graph = format!(
+        "movie='{}':loop=0[b];[b][in]scale2ref[b_scaled][main];\
+         [main]format=yuv420p,setsar=1[a_fmt];[b_scaled]format=yuv420p,setsar=1[b_fmt];\
+         [a_fmt][b_fmt]blend=all_expr='{}':shortest=1",
+        FFUtils::escape_path(&b.to_string_lossy()), mode.expr());
+    let name = b.file_name().unwrap_or_default().to_string_lossy().to_string();
+    Ok(FilterFragment::video(Filter::Graph(graph)).choice("b", name))
+}
+
+/// The B video itself, or one picked at random from a directory of them
+fn pick_b_video(path: &Path, config: &ActionConfig, action_id: &str) -> Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    match scan_videos(path).choose(&mut config.rng(action_id)) {
+        Some(video) => Ok(video.clone()),
+        None => bail!("B视频目录中没有视频文件: {}", path.display()),
+    }
+}
+
 impl VideoAction for AbReplaceAction {
     fn id(&self) -> &'static str {
         "ab_replace"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AB视频替换",
+            name_en: "AB frame replace",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[Material::AbVideo],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[AB_INTERVAL]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_replace")?;
-        
-        // Placeholder: Just copy with slight modification
-        // In full implementation, this would blend with video B
-        let vf = "eq=contrast=1.05";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let interval = config.int(&AB_INTERVAL)?;
+        ab_fragment(self.id(), config, AbMode::EveryNth(interval)).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn blend_expressions() {
+        assert_eq!(AbMode::EveryNth(5).expr(), "if(eq(mod(N,5),4),B,A)");
+        assert_eq!(AbMode::Blend(0.25).expr(), "A*0.750+B*0.250");
+        assert_eq!(AbMode::Field { bottom: true, alternate: false }.expr(), "if(eq(mod(Y,2),1),B,A)");
+        assert_eq!(AbMode::Field { bottom: false, alternate: true }.expr(), "if(eq(mod(Y+N,2),0),B,A)");
+    }
+
+    #[test]
+    fn without_b_video_the_stage_is_empty() {
+        let fragment = ab_fragment("ab_replace", &ActionConfig::default(), AbMode::Blend(0.5)).unwrap();
+        assert!(fragment.video.is_none() && fragment.choices.is_empty());
+    }
+
+    #[test]
+    fn pool_pick_follows_the_seed() {
+        let dir = std::env::temp_dir().join(format!("vmp_ab_{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = ActionConfig { seed: Some(3), ..Default::default() };
+        assert!(pick_b_video(&dir, &config, "ab_replace").is_err());
+
+        for name in ["b1.mp4", "b2.mp4", "b3.mov", "notes.txt"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let picked = pick_b_video(&dir, &config, "ab_replace").unwrap();
+        assert_eq!(pick_b_video(&dir, &config, "ab_replace").unwrap(), picked);
+        assert!(picked.extension().is_some_and(|e| e != "txt"));
+
+        let config = ActionConfig { ab_video_path: Some(dir.to_string_lossy().to_string()), ..config };
+        let fragment = ab_fragment("ab_replace", &config, AbMode::EveryNth(3)).unwrap();
+        assert_eq!(fragment.choices, [format!("b={}", picked.file_name().unwrap().to_string_lossy())]);
+        let _ = fs::remove_dir_all(&dir);
     }
 }
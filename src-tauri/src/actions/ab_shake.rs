@@ -1,6 +1,6 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct AbShakeAction;
 
@@ -9,19 +9,23 @@ impl VideoAction for AbShakeAction {
         "ab_shake"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AB抖动效果",
+            name_en: "AB shake",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_shake")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
         // Shake effect using crop with sine wave movement
-        let vf = "crop=iw:ih:5*sin(t*10):5*cos(t*10)";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain("crop=iw:ih:5*sin(t*10):5*cos(t*10)".to_string()))))
     }
 }
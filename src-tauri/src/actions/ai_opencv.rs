@@ -1,35 +1,339 @@
+use std::fs;
 use std::path::Path;
-use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use std::time::Instant;
+use anyhow::{Result, anyhow, bail};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, params};
+use crate::core::effects::{Echo, Posterize, RgbSplit, Sketch};
+use crate::core::face::{self, Face, FaceDetector, FaceReport, FaceTracker, FrameFaces};
+use crate::core::frames::{self, Frame, FrameReader, Image, Rect};
+use crate::core::track::{Keyframe, TemplateTracker, Track};
 
-// Placeholder implementations for AI/OpenCV actions
 pub struct FaceDetectionAction;
 pub struct ObjectTrackingAction;
 pub struct OpencvFilterAction;
 
+const FACE_MODE: ParamSpec = ParamSpec {
+    name: "face_mode",
+    label: "处理方式",
+    kind: ParamKind::Choice {
+        options: &[
+            ("blur", "模糊 (隐私保护)"),
+            ("pixelate", "马赛克 (隐私保护)"),
+            ("boxes", "画框 (调试)"),
+            ("json", "仅导出检测结果"),
+        ],
+        default: "blur",
+    },
+    unit: "",
+    description: "每种方式都会在输出旁写入逐帧的人脸框 (*.faces.json)，仅导出时不生成新视频",
+};
+
+const FACE_MIN_SIZE: ParamSpec = ParamSpec {
+    name: "face_min_size",
+    label: "最小人脸",
+    kind: ParamKind::Float { min: 0.02, max: 0.5, default: 0.06 },
+    unit: "",
+    description: "能检测到的最小人脸高度占画面高度的比例",
+};
+
+const FACE_THRESHOLD: ParamSpec = ParamSpec {
+    name: "face_threshold",
+    label: "判定阈值",
+    kind: ParamKind::Float { min: 0.2, max: 0.95, default: 0.7 },
+    unit: "",
+    description: "模型置信度达到该值才算人脸，越低漏检越少但误检越多",
+};
+
+const FACE_MARGIN: ParamSpec = ParamSpec {
+    name: "face_margin",
+    label: "外扩比例",
+    kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.2 },
+    unit: "",
+    description: "模糊或马赛克区域向四周扩大的比例，覆盖头发和耳朵",
+};
+
+/// Frames a face stays covered after the detector last saw it, about half a second
+const FACE_HOLD_FRAMES: usize = 15;
+
 impl VideoAction for FaceDetectionAction {
     fn id(&self) -> &'static str { "face_detection" }
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "人脸检测",
+            name_en: "Face detection",
+            category: ActionCategory::OpenCv,
+            touches: Touches::VIDEO,
+            materials: &[Material::FaceModel],
+        }
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        &[FACE_MODE, FACE_MIN_SIZE, FACE_THRESHOLD, FACE_MARGIN]
+    }
+    /// A missing model must stop the batch before any video goes out unblurred
+    fn validate(&self, config: &ActionConfig) -> Result<()> {
+        params::check_all(self.params(), config)?;
+        face::model_path(config).map(|_| ())
+    }
+    /// Runs on decoded frames, so it takes its own pass instead of a filter fragment
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
         let dst = FFUtils::get_dst(src, out_dir, "face")?;
-        // Placeholder: requires OpenCV integration
-        FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+        let detector = FaceDetector::load(&face::model_path(config)?, config.float(&FACE_MIN_SIZE)?, config.float(&FACE_THRESHOLD)?)?;
+        let margin = config.float(&FACE_MARGIN)?;
+        let mode = config.text(&FACE_MODE)?;
+
+        let mut tracker = FaceTracker::new(FACE_HOLD_FRAMES);
+        let source = config.source.clone().unwrap_or_else(|| src.to_path_buf());
+        let mut report = FaceReport { source, width: 0, height: 0, frames: Vec::new() };
+        let mut track = |frame: &Frame| -> Result<Vec<Face>> {
+            let faces = tracker.update(detector.detect(frame)?);
+            (report.width, report.height) = (frame.width, frame.height);
+            report.frames.push(FrameFaces { frame: frame.index, time: frame.time, faces: faces.clone() });
+            Ok(faces)
+        };
+
+        if mode == "json" {
+            let mut reader = FrameReader::open(src)?;
+            while let Some(frame) = reader.read()? {
+                track(&frame)?;
+            }
+            reader.finish()?;
+        } else {
+            frames::process(src, &dst, |frame: &mut Frame| {
+                for face in track(frame)? {
+                    let rect = face.rect.expand(margin);
+                    match mode.as_str() {
+                        "pixelate" => frame.pixelate(&rect, (rect.height / 8.0).ceil() as usize),
+                        "boxes" => frame.draw_rect(&face.rect, [0, 255, 0], (frame.height / 270).max(2)),
+                        _ => frame.blur(&rect, (rect.height / 6.0).ceil() as usize),
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        let json = dst.with_extension("faces.json");
+        fs::write(&json, serde_json::to_string_pretty(&report)?)
+            .map_err(|e| anyhow!("无法写入人脸检测结果 {}: {}", json.display(), e))?;
+        // Exporting leaves the video alone, so there is no new one to report
+        let video = if mode == "json" { src.to_path_buf() } else { dst };
+        let mut output = ActionOutput::new(video, started.elapsed());
+        output.side_outputs.push(json);
+        Ok(output)
     }
 }
 
+const TRACK_X: ParamSpec = ParamSpec {
+    name: "track_x",
+    label: "区域左边",
+    kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.4 },
+    unit: "",
+    description: "首帧上追踪区域左边缘的位置，占画面宽度的比例，可在预览页框选",
+};
+
+const TRACK_Y: ParamSpec = ParamSpec {
+    name: "track_y",
+    label: "区域上边",
+    kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.4 },
+    unit: "",
+    description: "首帧上追踪区域上边缘的位置，占画面高度的比例",
+};
+
+const TRACK_W: ParamSpec = ParamSpec {
+    name: "track_w",
+    label: "区域宽度",
+    kind: ParamKind::Float { min: 0.01, max: 1.0, default: 0.2 },
+    unit: "",
+    description: "追踪区域宽度占画面宽度的比例",
+};
+
+const TRACK_H: ParamSpec = ParamSpec {
+    name: "track_h",
+    label: "区域高度",
+    kind: ParamKind::Float { min: 0.01, max: 1.0, default: 0.2 },
+    unit: "",
+    description: "追踪区域高度占画面高度的比例",
+};
+
+const TRACK_MODE: ParamSpec = ParamSpec {
+    name: "track_mode",
+    label: "跟随效果",
+    kind: ParamKind::Choice {
+        options: &[
+            ("mosaic", "马赛克"),
+            ("sticker", "贴纸"),
+            ("zoom", "跟随放大"),
+            ("boxes", "画框 (调试)"),
+        ],
+        default: "mosaic",
+    },
+    unit: "",
+    description: "贴纸使用素材中的贴纸图片；轨迹会写入输出旁的 *.track.json，可作为追踪数据素材复用",
+};
+
+const TRACK_ZOOM: ParamSpec = ParamSpec {
+    name: "track_zoom",
+    label: "放大倍数",
+    kind: ParamKind::Float { min: 1.1, max: 4.0, default: 1.5 },
+    unit: "x",
+    description: "跟随放大时画面放大的倍数",
+};
+
 impl VideoAction for ObjectTrackingAction {
     fn id(&self) -> &'static str { "object_tracking" }
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "物体追踪",
+            name_en: "Object tracking",
+            category: ActionCategory::OpenCv,
+            touches: Touches::VIDEO,
+            materials: &[Material::Sticker, Material::Track],
+        }
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        &[TRACK_X, TRACK_Y, TRACK_W, TRACK_H, TRACK_MODE, TRACK_ZOOM]
+    }
+    /// Without a sticker image the sticker mode would quietly fall back to the mosaic
+    fn validate(&self, config: &ActionConfig) -> Result<()> {
+        params::check_all(self.params(), config)?;
+        if config.text(&TRACK_MODE)? == "sticker" && Material::Sticker.path(config).is_none() {
+            bail!("跟随效果为贴纸时，请在素材中选择贴纸图片");
+        }
+        Ok(())
+    }
+    /// Tracks the region on decoded frames (or follows an earlier track) and draws the effect on them
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
         let dst = FFUtils::get_dst(src, out_dir, "track")?;
-        // Placeholder: requires OpenCV integration
-        FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+        let region = Rect { x: config.float(&TRACK_X)?, y: config.float(&TRACK_Y)?, width: config.float(&TRACK_W)?, height: config.float(&TRACK_H)? };
+        let mode = config.text(&TRACK_MODE)?;
+        let zoom = config.float(&TRACK_ZOOM)?;
+        let earlier = Material::Track.path(config).map(|p| Track::load(Path::new(p))).transpose()?;
+        let sticker_path = Material::Sticker.path(config).map(Path::new);
+
+        let mut tracker: Option<TemplateTracker> = None;
+        let mut sticker: Option<Image> = None;
+        let source = config.source.clone().unwrap_or_else(|| src.to_path_buf());
+        let mut track = Track { source, width: 0, height: 0, keyframes: Vec::new() };
+        frames::process(src, &dst, |frame: &mut Frame| {
+            let (rect, confidence) = match (&earlier, &mut tracker) {
+                (Some(earlier), _) => {
+                    let rect = earlier.rect_at(frame.time, frame.width, frame.height).ok_or_else(|| anyhow!("追踪数据中没有关键帧"))?;
+                    (rect, 1.0)
+                }
+                (None, Some(tracker)) => tracker.update(frame),
+                (None, None) => {
+                    let rect = region.scale(frame.width as f64, frame.height as f64);
+                    tracker = Some(TemplateTracker::new(frame, &rect)?);
+                    (rect, 1.0)
+                }
+            };
+            (track.width, track.height) = (frame.width, frame.height);
+            track.keyframes.push(Keyframe { frame: frame.index, time: frame.time, rect, confidence });
+
+            match (mode.as_str(), sticker_path) {
+                ("sticker", Some(path)) => {
+                    let image = match &sticker {
+                        Some(image) => image,
+                        None => sticker.insert(Image::load(path, rect.width.round() as usize, rect.height.round() as usize)?),
+                    };
+                    frame.draw_image(image, rect.x.round() as isize, rect.y.round() as isize);
+                }
+                ("zoom", _) => frame.zoom_to(&zoom_window(&rect, frame, zoom)),
+                ("boxes", _) => frame.draw_rect(&rect, [255, 200, 0], (frame.height / 270).max(2)),
+                _ => frame.pixelate(&rect, (rect.width.min(rect.height) / 8.0).ceil() as usize),
+            }
+            Ok(())
+        })?;
+
+        track.thin(1.0);
+        let json = dst.with_extension("track.json");
+        track.save(&json)?;
+        let mut output = ActionOutput::new(dst, started.elapsed());
+        output.side_outputs.push(json);
+        Ok(output)
     }
 }
 
+/// Part of `frame` shown when zooming in `zoom` times on `rect`, kept inside the frame
+fn zoom_window(rect: &Rect, frame: &Frame, zoom: f64) -> Rect {
+    let (width, height) = (frame.width as f64 / zoom, frame.height as f64 / zoom);
+    let (cx, cy) = rect.center();
+    Rect {
+        x: (cx - width / 2.0).clamp(0.0, frame.width as f64 - width),
+        y: (cy - height / 2.0).clamp(0.0, frame.height as f64 - height),
+        width,
+        height,
+    }
+}
+
+const CV_EFFECT: ParamSpec = ParamSpec {
+    name: "cv_effect",
+    label: "滤镜效果",
+    kind: ParamKind::Choice {
+        options: &[
+            ("sketch", "素描"),
+            ("posterize", "色调分离"),
+            ("rgb_split", "色差错位"),
+            ("echo", "运动残影"),
+        ],
+        default: "sketch",
+    },
+    unit: "",
+    description: "在解码后的画面上逐帧处理，原音轨直接复制",
+};
+
+const CV_STRENGTH: ParamSpec = ParamSpec {
+    name: "cv_strength",
+    label: "滤镜强度",
+    kind: ParamKind::Float { min: 0.05, max: 1.0, default: 0.3 },
+    unit: "",
+    description: "素描的叠加比例、色调分离的档位、色差的错位距离或残影的保留比例",
+};
+
 impl VideoAction for OpencvFilterAction {
     fn id(&self) -> &'static str { "opencv_filter" }
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "OpenCV滤镜",
+            name_en: "OpenCV filter",
+            category: ActionCategory::OpenCv,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        &[CV_EFFECT, CV_STRENGTH]
+    }
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[CV_STRENGTH]
+    }
+    /// Runs the effect in Rust on decoded frames, so it takes its own pass
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
         let dst = FFUtils::get_dst(src, out_dir, "opencv")?;
-        // Placeholder: requires OpenCV integration
-        FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+        let strength = config.float(&CV_STRENGTH)?;
+        match config.text(&CV_EFFECT)?.as_str() {
+            "posterize" => frames::process(src, &dst, Posterize { levels: (32.0 - strength * 30.0).round() as u32 })?,
+            "rgb_split" => frames::process(src, &dst, RgbSplit { shift: strength * 0.02 })?,
+            "echo" => frames::process(src, &dst, Echo::new(strength * 0.9))?,
+            _ => frames::process(src, &dst, Sketch { strength })?,
+        }
+        Ok(ActionOutput::new(dst, started.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sticker_tracking_needs_a_sticker() {
+        let sticker = ActionConfig { params: json!({ "track_mode": "sticker" }), ..Default::default() };
+        assert!(ObjectTrackingAction.validate(&sticker).is_err());
+        let with_image = ActionConfig { sticker_path: Some("/stickers/star.png".into()), ..sticker };
+        assert!(ObjectTrackingAction.validate(&with_image).is_ok());
+        assert!(ObjectTrackingAction.validate(&ActionConfig::default()).is_ok());
     }
 }
@@ -1,29 +1,52 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, FilterContext, FilterFragment, FilterGraph};
 
 pub struct BitrateAction;
 
+const TARGET_BITRATE: ParamSpec = ParamSpec {
+    name: "target_bitrate",
+    label: "目标码率",
+    kind: ParamKind::Bitrate { default: "15M" },
+    unit: "",
+    description: "例如: 10M, 15M, 5000k",
+};
+
 impl VideoAction for BitrateAction {
     fn id(&self) -> &'static str {
         "bitrate_hq"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "高码率 (15Mbps)",
+            name_en: "High bitrate (15 Mbps)",
+            category: ActionCategory::Basic,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[TARGET_BITRATE]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "hq")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let bitrate = config.text(&TARGET_BITRATE)?;
+        // Simple bufsize calculation (2x bitrate, same unit) - this is a rough approximation
+        let digits = bitrate.trim_end_matches(['k', 'K', 'm', 'M']);
+        let unit = &bitrate[digits.len()..];
+        let bufsize = format!("{}{}", digits.parse::<f64>()? * 2.0, unit);
         
-        let bitrate = config.params.get("target_bitrate").and_then(|v| v.as_str()).unwrap_or("15M");
-        // Simple bufsize calculation (2x bitrate) - this is a rough approximation
-        let bufsize = format!("{}M", bitrate.trim_end_matches('M').parse::<f32>().unwrap_or(15.0) * 2.0);
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-b:v", bitrate,
-            "-minrate", bitrate,
+        Ok(Some(FilterFragment::output_args(&[
+            "-b:v", &bitrate,
+            "-minrate", &bitrate,
             "-bufsize", &bufsize,
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        ])))
     }
 }
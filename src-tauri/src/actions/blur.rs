@@ -1,27 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct BlurAction;
 
+const BLUR_STRENGTH: ParamSpec = ParamSpec {
+    name: "blur_strength",
+    label: "强度 (Sigma)",
+    kind: ParamKind::Float { min: 0.1, max: 10.0, default: 0.5 },
+    unit: "",
+    description: "高斯模糊的 sigma",
+};
+
 impl VideoAction for BlurAction {
     fn id(&self) -> &'static str {
         "blur"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "智能柔焦",
+            name_en: "Soft blur",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[BLUR_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "blur")?;
-        
-        let sigma = config.params.get("blur_strength").and_then(|v| v.as_f64()).unwrap_or(0.5);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let sigma = config.float(&BLUR_STRENGTH)?;
         let vf = format!("gblur=sigma={}", sigma);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
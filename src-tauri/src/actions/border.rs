@@ -1,31 +1,49 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct BorderAction;
 
+const BORDER_WIDTH: ParamSpec = ParamSpec {
+    name: "border_width",
+    label: "宽度",
+    kind: ParamKind::Int { min: 0, max: 500, default: 20 },
+    unit: "像素",
+    description: "仅在使用默认模糊边框时有效",
+};
+
 impl VideoAction for BorderAction {
     fn id(&self) -> &'static str {
         "border"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "智能补边",
+            name_en: "Border padding",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[Material::Border],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[BORDER_WIDTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "border")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.border_path {
             // Use custom border image - overlay it on top
-            let vf = format!("movie='{}'[border];[in][border]overlay=0:0", path);
-            FFUtils::run(&[
-                "-y",
-                "-i", src.to_str().unwrap(),
-                "-vf", &vf,
-                "-c:a", "copy",
-                "-loglevel", "error",
-                dst.to_str().unwrap()
-            ])
+            let graph = format!("movie='{}'[border];[in][border]overlay=0:0", FFUtils::escape_path(path));
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Default: blur border effect
-            let width = config.params.get("border_width").and_then(|v| v.as_i64()).unwrap_or(20) as f64;
+            let width = config.int(&BORDER_WIDTH)?;
             
             // Calculate scale factor: (W - 2*width) / W
             // Since we don't know W here easily without probing, we use a relative approach or assume standard width
@@ -37,19 +55,12 @@ impl VideoAction for BorderAction {
             // We use an expression for scale: iw-2*{width}:ih-2*{width}
             // Note: This might fail if width is too large.
             
-            let filter_complex = format!(
-                "[0:v]split=2[bg][fg];[bg]scale=iw:ih,boxblur=20[bg_b];[fg]scale=iw-2*{0}:ih-2*{0}[fg_s];[bg_b][fg_s]overlay={0}:{0}",
+            let graph = format!(
+                "[in]split=2[bg][fg];[bg]scale=iw:ih,boxblur=20[bg_b];[fg]scale=iw-2*{0}:ih-2*{0}[fg_s];[bg_b][fg_s]overlay={0}:{0}",
                 width
             );
             
-            FFUtils::run(&[
-                "-y",
-                "-i", src.to_str().unwrap(),
-                "-filter_complex", &filter_complex,
-                "-c:a", "copy",
-                "-loglevel", "error",
-                dst.to_str().unwrap()
-            ])
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         }
     }
 }
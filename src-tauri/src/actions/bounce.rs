@@ -1,26 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct BounceAction;
 
+const BOUNCE_AMPLITUDE: ParamSpec = ParamSpec {
+    name: "bounce_amplitude",
+    label: "弹跳幅度",
+    kind: ParamKind::Float { min: 5.0, max: 100.0, default: 20.0 },
+    unit: "像素",
+    description: "水平摆动幅度，垂直方向为其一半",
+};
+
 impl VideoAction for BounceAction {
     fn id(&self) -> &'static str {
         "bounce"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "弹跳效果",
+            name_en: "Bounce",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[BOUNCE_AMPLITUDE]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "bounce")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let amplitude = config.float(&BOUNCE_AMPLITUDE)?;
+        let graph = format!("[in]split=2[bg][fg];[bg]scale=iw:ih,boxblur=20[bg_blur];[fg]scale=iw*0.85:ih*0.85[fg_s];[bg_blur][fg_s]overlay=x='(W-w)/2+{}*sin(t)':y='(H-h)/2+{}*cos(t*1.5)'", amplitude, amplitude / 2.0);
         
-        let filter_complex = "[0:v]split=2[bg][fg];[bg]scale=iw:ih,boxblur=20[bg_blur];[fg]scale=iw*0.85:ih*0.85[fg_s];[bg_blur][fg_s]overlay=x='(W-w)/2+20*sin(t)':y='(H-h)/2+10*cos(t*1.5)'";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter_complex", filter_complex,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Graph(graph))))
     }
 }
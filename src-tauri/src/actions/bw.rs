@@ -1,6 +1,6 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct BwAction;
 
@@ -9,16 +9,22 @@ impl VideoAction for BwAction {
         "bw"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "黑白怀旧",
+            name_en: "Black and white",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "bw")?;
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", "hue=s=0",
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        Ok(Some(FilterFragment::video(Filter::Chain("hue=s=0".to_string()))))
     }
 }
@@ -1,34 +1,65 @@
 use std::path::Path;
 use anyhow::Result;
 use rand::Rng;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct ColorAction;
 
+const COLOR_TEMP_RANGE: ParamSpec = ParamSpec {
+    name: "color_temp_range",
+    label: "色温范围",
+    kind: ParamKind::Int { min: 100, max: 2000, default: 500 },
+    unit: "K",
+    description: "色温调整范围（开尔文），越大偏色越明显",
+};
+
 impl VideoAction for ColorAction {
     fn id(&self) -> &'static str {
         "color"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "随机色温",
+            name_en: "Random color temperature",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[COLOR_TEMP_RANGE]
+    }
+
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[COLOR_TEMP_RANGE]
+    }
+
+    fn per_scene(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "color")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
+        // 500K corresponds to a gamma shift of up to 0.12
+        let max_shift = 0.12 * config.int(&COLOR_TEMP_RANGE)? as f64 / 500.0;
+        let val: f64 = rng.gen_range((max_shift * 0.4)..=max_shift);
         
-        let mut rng = rand::thread_rng();
-        let val: f64 = rng.gen_range(0.05..0.12);
-        
-        let vf = if rng.gen_bool(0.5) {
+        let warm = rng.gen_bool(0.5);
+        let vf = if warm {
             format!("eq=gamma_r={:.4}:gamma_b={:.4}:saturation=1.1", 1.0+val, 1.0-val)
         } else {
             format!("eq=gamma_r={:.4}:gamma_b={:.4}:saturation=1.05", 1.0-val, 1.0+val)
         };
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))
+            .choice("shift", format!("{:.4}", val))
+            .choice("tone", if warm { "warm" } else { "cool" })))
     }
 }
@@ -1,26 +1,46 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct CornerAction;
 
+const CORNER_BLUR: ParamSpec = ParamSpec {
+    name: "corner_blur",
+    label: "模糊半径",
+    kind: ParamKind::Int { min: 1, max: 50, default: 10 },
+    unit: "像素",
+    description: "",
+};
+
 impl VideoAction for CornerAction {
     fn id(&self) -> &'static str {
         "corner"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "边角模糊",
+            name_en: "Corner blur",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[CORNER_BLUR]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "corner")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        // Each split output can only be consumed once, so every corner gets its own copy
+        let radius = config.int(&CORNER_BLUR)?;
+        let graph = format!("[in]split=5[main][c1][c2][c3][c4];[c1]crop=iw/4:ih/4:0:0,boxblur={0}[blur1];[c2]crop=iw/4:ih/4:iw*3/4:0,boxblur={0}[blur2];[c3]crop=iw/4:ih/4:0:ih*3/4,boxblur={0}[blur3];[c4]crop=iw/4:ih/4:iw*3/4:ih*3/4,boxblur={0}[blur4];[main][blur1]overlay=0:0[tmp1];[tmp1][blur2]overlay=W*3/4:0[tmp2];[tmp2][blur3]overlay=0:H*3/4[tmp3];[tmp3][blur4]overlay=W*3/4:H*3/4", radius);
         
-        let filter_complex = "[0:v]split=2[main][blur];[blur]crop=iw/4:ih/4:0:0,boxblur=10[blur1];[blur]crop=iw/4:ih/4:iw*3/4:0,boxblur=10[blur2];[blur]crop=iw/4:ih/4:0:ih*3/4,boxblur=10[blur3];[blur]crop=iw/4:ih/4:iw*3/4:ih*3/4,boxblur=10[blur4];[main][blur1]overlay=0:0[tmp1];[tmp1][blur2]overlay=iw*3/4:0[tmp2];[tmp2][blur3]overlay=0:ih*3/4[tmp3];[tmp3][blur4]overlay=iw*3/4:ih*3/4";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter_complex", filter_complex,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Graph(graph))))
     }
 }
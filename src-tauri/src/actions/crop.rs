@@ -1,37 +1,75 @@
 use std::path::Path;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use rand::Rng;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct CropAction;
 
+const CROP_MIN: ParamSpec = ParamSpec {
+    name: "crop_min",
+    label: "最小比例",
+    kind: ParamKind::Float { min: 0.0, max: 0.5, default: 0.01 },
+    unit: "",
+    description: "每次至少裁掉的画面比例，0.05 代表 5%",
+};
+
+const CROP_MAX: ParamSpec = ParamSpec {
+    name: "crop_max",
+    label: "最大比例",
+    kind: ParamKind::Float { min: 0.0, max: 0.5, default: 0.05 },
+    unit: "",
+    description: "每次最多裁掉的画面比例",
+};
+
 impl VideoAction for CropAction {
     fn id(&self) -> &'static str {
         "crop"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "随机微裁剪 (1-5%)",
+            name_en: "Random micro crop (1-5%)",
+            category: ActionCategory::Basic,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[CROP_MIN, CROP_MAX]
+    }
+
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[CROP_MIN, CROP_MAX]
+    }
+
+    fn per_scene(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "crop")?;
-        
-        let mut rng = rand::thread_rng();
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
         
         // Get parameters from config or use defaults
-        let min_crop = config.params.get("crop_min").and_then(|v| v.as_f64()).unwrap_or(0.01);
-        let max_crop = config.params.get("crop_max").and_then(|v| v.as_f64()).unwrap_or(0.05);
+        let min_crop = config.float(&CROP_MIN)?;
+        let max_crop = config.float(&CROP_MAX)?;
+        
+        if min_crop > max_crop {
+            return Err(anyhow!("参数 crop_min ({}) 不能大于 crop_max ({})", min_crop, max_crop));
+        }
         
         // Calculate keep ratio (e.g., crop 5% means keep 95%)
-        let crop_amount = rng.gen_range(min_crop..max_crop);
+        let crop_amount = rng.gen_range(min_crop..=max_crop);
         let ratio = 1.0 - crop_amount;
         
         let vf = format!("crop=iw*{:.3}:ih*{:.3}:(iw-ow)/2:(ih-oh)/2", ratio, ratio);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf)).choice("amount", format!("{:.3}", crop_amount))))
     }
 }
@@ -1,34 +1,520 @@
+use std::fs;
 use std::path::Path;
+use std::time::Instant;
 use anyhow::{Result, anyhow};
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use rand::Rng;
+use uuid::Uuid;
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
+use crate::core::probe::{self, probe};
 
 pub struct CutAction;
 
+const CUT_MODE: ParamSpec = ParamSpec {
+    name: "cut_mode",
+    label: "剪切方式",
+    kind: ParamKind::Choice {
+        options: &[
+            ("accurate", "精确 (重新编码)"),
+            ("smart", "智能 (只重新编码切点附近)"),
+            ("copy", "快速 (按关键帧，不重新编码)"),
+        ],
+        default: "copy",
+    },
+    unit: "",
+    description: "快速方式只能切在关键帧上，开头可能出现定格或花屏；智能方式只重新编码切点到最近关键帧之间的画面，仅支持 H.264/H.265，重新编码的参数与原视频不一致时改用精确方式",
+};
+
+const CUT_SECONDS: ParamSpec = ParamSpec {
+    name: "cut_seconds",
+    label: "首尾各去除",
+    kind: ParamKind::Float { min: 0.0, max: 30.0, default: 0.0 },
+    unit: "秒",
+    description: "从开头和结尾各去除固定的秒数，代替下面的最少/最多设置；0 表示不使用",
+};
+
+const CUT_HEAD_MIN: ParamSpec = ParamSpec {
+    name: "cut_head_min",
+    label: "开头最少",
+    kind: ParamKind::Float { min: 0.0, max: 30.0, default: 1.0 },
+    unit: "秒",
+    description: "从视频开头至少去除的秒数",
+};
+
+const CUT_HEAD_MAX: ParamSpec = ParamSpec {
+    name: "cut_head_max",
+    label: "开头最多",
+    kind: ParamKind::Float { min: 0.0, max: 30.0, default: 1.0 },
+    unit: "秒",
+    description: "从视频开头最多去除的秒数，每个视频在最少和最多之间随机",
+};
+
+const CUT_TAIL_MIN: ParamSpec = ParamSpec {
+    name: "cut_tail_min",
+    label: "结尾最少",
+    kind: ParamKind::Float { min: 0.0, max: 30.0, default: 1.0 },
+    unit: "秒",
+    description: "从视频结尾至少去除的秒数",
+};
+
+const CUT_TAIL_MAX: ParamSpec = ParamSpec {
+    name: "cut_tail_max",
+    label: "结尾最多",
+    kind: ParamKind::Float { min: 0.0, max: 30.0, default: 1.0 },
+    unit: "秒",
+    description: "从视频结尾最多去除的秒数；首尾合计超过视频时长一半时按比例缩小",
+};
+
+/// Share of a video that head and tail may take together; shorter videos are cut proportionally
+/// less, logged as the choice `scaled`
+const MAX_CUT_SHARE: f64 = 0.5;
+
+/// Margin around keyframe times, which ffprobe prints rounded; far below one frame
+const EDGE: f64 = 0.0005;
+
+/// Seconds cut from either end
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Trim {
+    head: f64,
+    tail: f64,
+    /// Factor the drawn amounts were shrunk by to stay within `MAX_CUT_SHARE`, 1.0 when they fit
+    scale: f64,
+}
+
+impl Trim {
+    /// Logged with the output; a shrunk cut says so, as the amounts are below the configured ones
+    fn choices(&self) -> Vec<(&'static str, String)> {
+        let mut choices = vec![("head", format!("{:.3}", self.head)), ("tail", format!("{:.3}", self.tail))];
+        if self.scale < 1.0 {
+            choices.push(("scaled", format!("{:.3}", self.scale)));
+        }
+        choices
+    }
+}
+
+impl CutAction {
+    fn trim(&self, config: &ActionConfig, duration: f64) -> Result<Trim> {
+        let mut rng = config.rng(self.id());
+        let mut draw = |min: &ParamSpec, max: &ParamSpec| -> Result<f64> {
+            let (low, high) = (config.float(min)?, config.float(max)?);
+            if low > high {
+                return Err(anyhow!("参数 {} ({}) 不能大于 {} ({})", min.name, low, max.name, high));
+            }
+            Ok(rng.gen_range(low..=high))
+        };
+        let both = config.float(&CUT_SECONDS)?;
+        let (mut head, mut tail) = if both > 0.0 {
+            (both, both)
+        } else {
+            (draw(&CUT_HEAD_MIN, &CUT_HEAD_MAX)?, draw(&CUT_TAIL_MIN, &CUT_TAIL_MAX)?)
+        };
+
+        let limit = duration * MAX_CUT_SHARE;
+        let mut scale = 1.0;
+        if head + tail > limit {
+            scale = limit / (head + tail);
+            head *= scale;
+            tail *= scale;
+        }
+        Ok(Trim { head, tail, scale })
+    }
+
+    /// Frame-accurate cut of `start..end` in the merged filtergraph
+    fn fragment(trim: Trim, duration: f64) -> FilterFragment {
+        let end = duration - trim.tail;
+        let fragment = FilterFragment {
+            video: Some(Filter::Chain(format!("trim=start={:.6}:end={:.6},setpts=PTS-STARTPTS", trim.head, end))),
+            audio: Some(Filter::Chain(format!("atrim=start={:.6}:end={:.6},asetpts=PTS-STARTPTS", trim.head, end))),
+            time_scale: (duration > 0.0).then(|| (end - trim.head) / duration),
+            ..Default::default()
+        };
+        trim.choices().into_iter().fold(fragment, |fragment, (name, value)| fragment.choice(name, value))
+    }
+}
+
 impl VideoAction for CutAction {
     fn id(&self) -> &'static str {
         "cut_head_tail"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "首尾去秒",
+            name_en: "Trim head and tail",
+            category: ActionCategory::Basic,
+            touches: Touches::AV,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[CUT_MODE, CUT_SECONDS, CUT_HEAD_MIN, CUT_HEAD_MAX, CUT_TAIL_MIN, CUT_TAIL_MAX]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
         let dst = FFUtils::get_dst(src, out_dir, "cut")?;
-        
-        let cut_secs = config.params.get("cut_seconds").and_then(|v| v.as_f64()).unwrap_or(1.0);
+        let mode = config.text(&CUT_MODE)?;
+        if mode == "accurate" {
+            return FilterGraph::apply(self, src, &dst, config);
+        }
+
         let duration = FFUtils::get_duration(src)?;
-        
-        if duration < cut_secs * 2.0 + 1.0 {
-            return Err(anyhow!("Video too short for cutting"));
-        }
-
-        let new_duration = duration - cut_secs * 2.0;
-        
-        FFUtils::run(&[
-            "-y",
-            "-ss", &cut_secs.to_string(),
-            "-t", &new_duration.to_string(),
-            "-i", src.to_str().unwrap(),
-            "-c", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        let trim = self.trim(config, duration)?;
+        let (start, end) = (trim.head, duration - trim.tail);
+        let smart = mode == "smart";
+        let rendered = if smart { smart_cut(src, &dst, start, end)? } else { copy_cut(src, &dst, start, end)?; true };
+        if !rendered {
+            // Not a codec smart rendering can copy, or no keyframe inside the cut
+            let mut graph = FilterGraph::new();
+            graph.push(Self::fragment(trim, duration));
+            graph.run(src, &dst)?;
+        }
+
+        let mut output = ActionOutput::new(dst, started.elapsed());
+        output.choices.extend(trim.choices().into_iter().map(|(name, value)| format!("{}: {}={}", self.id(), name, value)));
+        if smart && !rendered {
+            output.choices.push(format!("{}: mode=accurate", self.id()));
+        }
+        Ok(output)
+    }
+
+    fn filter(&self, ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        // Copying and smart rendering work on the encoded stream and run on their own
+        if config.text(&CUT_MODE)? != "accurate" {
+            return Ok(None);
+        }
+        let duration = ctx.duration()?;
+        let trim = self.trim(config, duration)?;
+        Ok(Some(Self::fragment(trim, duration)))
+    }
+}
+
+/// Stream copy starting at the keyframe before `start`
+fn copy_cut(src: &Path, dst: &Path, start: f64, end: f64) -> Result<()> {
+    let src_str = src.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+    let dst_str = dst.to_str().ok_or_else(|| anyhow!("Invalid output path encoding"))?;
+    FFUtils::run(&[
+        "-ss", &format!("{:.6}", start),
+        "-t", &format!("{:.6}", end - start),
+        "-i", src_str,
+        "-c", "copy",
+        "-avoid_negative_ts", "make_zero",
+        "-loglevel", "error",
+        dst_str,
+    ])?;
+    Ok(())
+}
+
+/// Cut `start..end` re-encoding only the frames between each cut and the nearest keyframe
+/// inside it; the video between those keyframes is copied and the audio encoded in one piece.
+/// Returns false, writing nothing, when the video cannot be cut this way.
+fn smart_cut(src: &Path, dst: &Path, start: f64, end: f64) -> Result<bool> {
+    let info = probe(src)?;
+    let Some((stream, video)) = info.video() else { return Ok(false) };
+    let (encoder, format) = match stream.codec.as_str() {
+        "h264" => ("libx264", "h264"),
+        "hevc" => ("libx265", "hevc"),
+        _ => return Ok(false),
+    };
+    // MPEG-TS segments lose the rotation, which a re-encode would bake into the picture
+    if video.rotation != 0 {
+        return Ok(false);
+    }
+
+    let keys = probe::keyframes(src)?;
+    let first = keys.iter().copied().find(|&k| k >= start - EDGE);
+    let last = keys.iter().copied().rev().find(|&k| k <= end + EDGE);
+    let (first, last) = match (first, last) {
+        (Some(first), Some(last)) if last > first => (first, last),
+        _ => return Ok(false),
+    };
+
+    let mut encode = vec!["-c:v", encoder, "-preset", "medium", "-crf", "18"];
+    if let Some(pix_fmt) = video.pix_fmt.as_deref() {
+        encode.extend(["-pix_fmt", pix_fmt]);
+    }
+    // Copied and re-encoded parts share one decoder configuration only with a matching profile
+    let profile = match (encoder, stream.profile.as_deref()) {
+        ("libx264", Some("Constrained Baseline" | "Baseline")) => Some("baseline"),
+        ("libx264", Some("Main")) => Some("main"),
+        ("libx264", Some("High")) => Some("high"),
+        _ => None,
+    };
+    if let Some(profile) = profile {
+        encode.extend(["-profile:v", profile]);
+    }
+    if encoder == "libx264" {
+        // Keeps the picture parameter set independent of the rate control
+        encode.extend(["-x264-params", "stitchable=1"]);
+    }
+
+    let out_dir = dst.parent().ok_or_else(|| anyhow!("Invalid output path"))?;
+    let work_dir = out_dir.join(format!(".cut_{}", Uuid::new_v4().simple()));
+    fs::create_dir_all(&work_dir)?;
+    let result = smart_cut_in(src, dst, &work_dir, &parts((start, end), (first, last)), &encode, format);
+    let _ = fs::remove_dir_all(&work_dir);
+    probe::forget(&work_dir);
+    result
+}
+
+/// Piece of a smart render: `length` seconds of video from `seek`, copied or re-encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Part {
+    seek: f64,
+    length: f64,
+    copy: bool,
+}
+
+/// Pieces of the cut `start..end` with the keyframes `first` and `last` just inside it. The
+/// copied middle starts on the keyframe `first` and stops before the one at `last`; the frames
+/// before `first` and from `last` on are re-encoded.
+fn parts((start, end): (f64, f64), (first, last): (f64, f64)) -> Vec<Part> {
+    let mut parts = Vec::new();
+    if first - start > EDGE {
+        parts.push(Part { seek: start, length: first - start - EDGE, copy: false });
+    }
+    parts.push(Part { seek: first + EDGE, length: last - first - 2.0 * EDGE, copy: true });
+    if end - last > EDGE {
+        parts.push(Part { seek: last - EDGE, length: end - last + EDGE, copy: false });
+    }
+    parts
+}
+
+/// Smart render of `parts` with the audio of the same span. An MP4 carries one decoder
+/// configuration for the whole stream, so the parts are only joined when the re-encoded ones
+/// come out with the parameter sets of the copied one; returns false otherwise, as soon as a
+/// one frame probe of the encoder settings shows they differ.
+fn smart_cut_in(src: &Path, dst: &Path, work_dir: &Path, parts: &[Part], encode: &[&str], format: &str) -> Result<bool> {
+    let src_str = src.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+    let dst_str = dst.to_str().ok_or_else(|| anyhow!("Invalid output path encoding"))?;
+    let seconds = |time: f64| format!("{:.6}", time);
+
+    let copied = parts.iter().find(|p| p.copy).ok_or_else(|| anyhow!("Smart render without a copied part"))?;
+    let source = first_parameter_sets(&["-noautorotate", "-ss", &seconds(copied.seek), "-i", src_str, "-c:v", "copy"], &work_dir.join("source"), format)?;
+    if source.is_empty() {
+        return Ok(false);
+    }
+    if let Some(part) = parts.iter().find(|p| !p.copy) {
+        let seek = seconds(part.seek);
+        let mut args = vec!["-noautorotate", "-ss", &seek, "-i", src_str];
+        args.extend(encode);
+        if first_parameter_sets(&args, &work_dir.join("probe"), format)? != source {
+            return Ok(false);
+        }
+    }
+
+    let mut list = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        let path = work_dir.join(format!("part{}.ts", i));
+        let path_str = path.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+        let (seek, length) = (seconds(part.seek), seconds(part.length));
+        let mut args = vec!["-noautorotate", "-ss", &seek, "-i", src_str, "-t", &length, "-map", "0:v:0"];
+        if part.copy {
+            args.extend(["-c:v", "copy"]);
+        } else {
+            args.extend(encode);
+        }
+        args.extend(["-f", "mpegts", "-loglevel", "error", path_str]);
+        FFUtils::run(&args)?;
+        // The probe frame matching does not promise every part does
+        if first_parameter_sets(&["-i", path_str, "-c:v", "copy"], &path, format)? != source {
+            return Ok(false);
+        }
+        list.push_str(&format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''")));
+    }
+
+    let list_path = work_dir.join("parts.txt");
+    fs::write(&list_path, list)?;
+    let list_str = list_path.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+    let video = work_dir.join("video.ts");
+    let video_str = video.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+    FFUtils::run(&["-f", "concat", "-safe", "0", "-i", list_str, "-map", "0:v", "-c:v", "copy", "-f", "mpegts", "-loglevel", "error", video_str])?;
+
+    // The audio covers what the video ended up covering: a copied first part starts on its keyframe
+    let audio_start = if parts[0].copy { parts[0].seek - EDGE } else { parts[0].seek };
+    let (start, length) = (format!("{:.6}", audio_start), format!("{:.6}", FFUtils::get_duration(&video)?));
+    FFUtils::run(&[
+        "-i", video_str,
+        "-ss", &start, "-t", &length, "-i", src_str,
+        "-map", "0:v", "-map", "1:a?",
+        "-c:v", "copy",
+        "-loglevel", "error",
+        dst_str,
+    ])?;
+    Ok(true)
+}
+
+/// Parameter sets of the first video frame ffmpeg writes with the input and codec options
+/// `args`, kept beside `work` as a raw stream
+fn first_parameter_sets(args: &[&str], work: &Path, format: &str) -> Result<Vec<Vec<u8>>> {
+    let raw = work.with_extension(format);
+    let raw_str = raw.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+    let mut args = args.to_vec();
+    args.extend(["-map", "0:v:0", "-frames:v", "1", "-f", format, "-loglevel", "error", raw_str]);
+    FFUtils::run(&args)?;
+    let stream = fs::read(&raw)?;
+    Ok(parameter_sets(&stream, format == "hevc").into_iter().map(<[u8]>::to_vec).collect())
+}
+
+/// Parameter set NAL units (SPS, PPS and for HEVC the VPS) of an Annex B stream, sorted
+fn parameter_sets(stream: &[u8], hevc: bool) -> Vec<&[u8]> {
+    let starts: Vec<usize> = stream.windows(3).enumerate()
+        .filter(|(_, code)| *code == [0, 0, 1])
+        .map(|(i, _)| i + 3)
+        .collect();
+    let mut units: Vec<&[u8]> = starts.iter().enumerate()
+        .map(|(i, &start)| {
+            let mut unit = &stream[start..starts.get(i + 1).map_or(stream.len(), |&next| next - 3)];
+            // The leading zero of a four byte start code
+            while let [rest @ .., 0] = unit {
+                unit = rest;
+            }
+            unit
+        })
+        .filter(|unit| match unit.first() {
+            Some(&header) if hevc => (32..=34).contains(&((header >> 1) & 0x3f)),
+            Some(&header) => matches!(header & 0x1f, 7 | 8),
+            None => false,
+        })
+        .collect();
+    units.sort();
+    units.dedup();
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(params: serde_json::Value) -> ActionConfig {
+        ActionConfig { seed: Some(7), params, ..Default::default() }
+    }
+
+    #[test]
+    fn trim_draws_within_the_ranges() {
+        let ranges = config(json!({ "cut_head_min": 0.5, "cut_head_max": 2.0, "cut_tail_min": 1.0, "cut_tail_max": 1.0 }));
+        let trim = CutAction.trim(&ranges, 60.0).unwrap();
+        assert!((0.5..=2.0).contains(&trim.head), "{:?}", trim);
+        assert_eq!((trim.tail, trim.scale), (1.0, 1.0));
+        // Seeded: the same file gets the same amounts
+        assert_eq!(CutAction.trim(&ranges, 60.0).unwrap(), trim);
+        assert_eq!(trim.choices().len(), 2);
+
+        let reversed = config(json!({ "cut_head_min": 3.0, "cut_head_max": 2.0 }));
+        assert!(CutAction.trim(&reversed, 60.0).is_err());
+    }
+
+    #[test]
+    fn cut_seconds_sets_both_ends() {
+        let fixed = config(json!({ "cut_seconds": 2.5, "cut_head_min": 0.0, "cut_head_max": 9.0 }));
+        let trim = CutAction.trim(&fixed, 60.0).unwrap();
+        assert_eq!((trim.head, trim.tail), (2.5, 2.5));
+        // 0 leaves the ranges in charge
+        let trim = CutAction.trim(&config(json!({ "cut_seconds": 0.0 })), 60.0).unwrap();
+        assert_eq!((trim.head, trim.tail), (1.0, 1.0));
+    }
+
+    #[test]
+    fn short_videos_are_cut_less_and_say_so() {
+        let trim = CutAction.trim(&config(json!({ "cut_head_min": 3.0, "cut_head_max": 3.0 })), 4.0).unwrap();
+        // 3 + 1 seconds of a 4 second video: shrunk to half of it
+        assert_eq!((trim.head, trim.tail, trim.scale), (1.5, 0.5, 0.5));
+        assert_eq!(trim.choices().last().unwrap(), &("scaled", "0.500".to_string()));
+
+        let fragment = CutAction::fragment(trim, 4.0);
+        assert_eq!(fragment.choices, ["head=1.500", "tail=0.500", "scaled=0.500"]);
+        assert_eq!(fragment.time_scale, Some(0.5));
+        assert!(matches!(fragment.video, Some(Filter::Chain(ref chain)) if chain == "trim=start=1.500000:end=3.500000,setpts=PTS-STARTPTS"));
+    }
+
+    #[test]
+    fn parts_copy_between_the_keyframes() {
+        // Keyframes at 2 and 6 inside a 1.5..7 cut
+        let pieces = parts((1.5, 7.0), (2.0, 6.0));
+        assert_eq!(pieces.len(), 3);
+        assert_eq!((pieces[0].seek, pieces[0].copy), (1.5, false));
+        assert!((pieces[0].length - (0.5 - EDGE)).abs() < 1e-9);
+        assert_eq!((pieces[1].seek, pieces[1].copy), (2.0 + EDGE, true));
+        assert!((pieces[1].length - (4.0 - 2.0 * EDGE)).abs() < 1e-9);
+        assert_eq!((pieces[2].seek, pieces[2].copy), (6.0 - EDGE, false));
+        assert!((pieces[2].seek + pieces[2].length - 7.0).abs() < 1e-9);
+
+        // Cuts on keyframes (as ffprobe rounds them) need no re-encoding at that end
+        let pieces = parts((2.0002, 6.0), (2.0, 6.0));
+        assert_eq!(pieces.iter().map(|p| p.copy).collect::<Vec<_>>(), [true]);
+        let pieces = parts((2.0, 6.5), (2.0, 6.0));
+        assert_eq!(pieces.iter().map(|p| p.copy).collect::<Vec<_>>(), [true, false]);
+
+        // Together the parts span the cut
+        let pieces = parts((0.3, 9.7), (2.0, 8.0));
+        let covered: f64 = pieces.iter().map(|p| p.length).sum();
+        assert!((covered - 9.4).abs() < 3.0 * EDGE, "{}", covered);
+    }
+
+    #[test]
+    fn parameter_sets_are_picked_from_the_stream() {
+        // AUD, SPS, PPS after a four byte start code, SEI, IDR slice
+        let h264 = [
+            &[0, 0, 0, 1, 0x09, 0xf0][..],
+            &[0, 0, 0, 1, 0x67, 0x64, 0x00, 0x1f, 0xac],
+            &[0, 0, 1, 0x68, 0xeb, 0xe3, 0xcb],
+            &[0, 0, 1, 0x06, 0x05, 0x11],
+            &[0, 0, 1, 0x65, 0x88, 0x84, 0x00],
+        ].concat();
+        let sets = parameter_sets(&h264, false);
+        assert_eq!(sets, [&[0x67, 0x64, 0x00, 0x1f, 0xac][..], &[0x68, 0xeb, 0xe3, 0xcb]]);
+        // Repeated before the next keyframe: still one of each
+        assert_eq!(parameter_sets(&[&h264[..], &h264[..]].concat(), false), sets);
+
+        // HEVC: VPS 32, SPS 33, PPS 34 in the six bits after the forbidden zero bit
+        let hevc = [
+            &[0, 0, 0, 1, 0x40, 0x01, 0x0c][..],
+            &[0, 0, 1, 0x42, 0x01, 0x01],
+            &[0, 0, 1, 0x44, 0x01, 0xc1],
+            &[0, 0, 1, 0x26, 0x01, 0xaf],
+        ].concat();
+        assert_eq!(parameter_sets(&hevc, true).len(), 3);
+        assert!(parameter_sets(&[0x67, 0x64], false).is_empty());
+    }
+
+    /// Runs ffmpeg with libx264 from the PATH: `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn smart_render_decodes_in_sync_or_is_refused() {
+        let dir = std::env::temp_dir().join(format!("vmp_cut_{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = |name: &str, x264: &[&str]| {
+            let path = dir.join(name);
+            let mut args = vec![
+                "-f", "lavfi", "-i", "testsrc2=size=320x240:rate=25:duration=8",
+                "-f", "lavfi", "-i", "sine=frequency=440:duration=8",
+                // Forced keyframes leave the keyframe interval, and with it the SPS, at the default
+                "-c:v", "libx264", "-pix_fmt", "yuv420p", "-force_key_frames", "expr:gte(t,n_forced*2)", "-sc_threshold", "0",
+            ];
+            args.extend(x264);
+            args.extend(["-c:a", "aac", "-shortest", path.to_str().unwrap()]);
+            FFUtils::run(&args).unwrap();
+            path
+        };
+
+        // Encoded with the settings of the re-encoded parts, keyframes every two seconds: joined,
+        // decodes and keeps the sound in sync
+        let src = source("matching.mp4", &["-preset", "medium", "-crf", "18", "-profile:v", "high", "-x264-params", "stitchable=1"]);
+        let dst = dir.join("matching_cut.mp4");
+        assert!(smart_cut(&src, &dst, 0.52, 7.28).unwrap(), "matching source was refused");
+        FFUtils::run(&["-xerror", "-i", dst.to_str().unwrap(), "-f", "null", "-loglevel", "error", "-"]).unwrap();
+        let info = probe::probe(&dst).unwrap();
+        let video = info.video().and_then(|(s, _)| s.duration).unwrap();
+        let audio = info.audio().and_then(|(s, _)| s.duration).unwrap();
+        assert!((video - 6.76).abs() < 0.1, "video {}", video);
+        assert!((video - audio).abs() < 0.05, "video {} audio {}", video, audio);
+
+        // Without CABAC the parameter sets cannot match: refused, nothing written
+        let src = source("other.mp4", &["-preset", "ultrafast"]);
+        let dst = dir.join("other_cut.mp4");
+        assert!(!smart_cut(&src, &dst, 0.52, 7.28).unwrap());
+        assert!(!dst.exists());
+        let _ = fs::remove_dir_all(&dir);
     }
 }
@@ -1,29 +1,47 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct DenoiseAction;
 
+const DENOISE_STRENGTH: ParamSpec = ParamSpec {
+    name: "denoise_strength",
+    label: "强度",
+    kind: ParamKind::Float { min: 0.0, max: 20.0, default: 5.0 },
+    unit: "",
+    description: "hqdn3d 时域强度，空域强度为其 0.3 倍",
+};
+
 impl VideoAction for DenoiseAction {
     fn id(&self) -> &'static str {
         "denoise"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "智能降噪",
+            name_en: "Denoise",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[DENOISE_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "denoise")?;
-        
-        let strength = config.params.get("denoise_strength").and_then(|v| v.as_f64()).unwrap_or(5.0);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let strength = config.float(&DENOISE_STRENGTH)?;
         // hqdn3d=luma_spatial:chroma_spatial:luma_tmp:chroma_tmp
         // We scale all parameters based on strength
         let vf = format!("hqdn3d={0}:{0}:{1}:{1}", strength * 0.3, strength);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
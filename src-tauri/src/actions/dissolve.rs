@@ -1,27 +1,46 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct DissolveAction;
 
+const DISSOLVE_STRENGTH: ParamSpec = ParamSpec {
+    name: "dissolve_strength",
+    label: "溶解强度",
+    kind: ParamKind::Float { min: 0.1, max: 1.0, default: 0.5 },
+    unit: "",
+    description: "淡入淡出时长 = 强度 × 2 秒",
+};
+
 impl VideoAction for DissolveAction {
     fn id(&self) -> &'static str {
         "dissolve"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AI移动溶解",
+            name_en: "Moving dissolve",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[DISSOLVE_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ai_dis")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let duration = ctx.duration()?;
+        let fade = config.float(&DISSOLVE_STRENGTH)? * 2.0;
+        let vf = format!("fade=t=in:st=0:d={0},fade=t=out:st={1}:d={0}", fade, (duration - fade).max(0.0));
         
-        let duration = FFUtils::get_duration(src)?;
-        let vf = format!("fade=t=in:st=0:d=1,fade=t=out:st={}:d=1", duration - 1.0);
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
@@ -1,7 +1,7 @@
 use std::path::Path;
 use anyhow::Result;
 use rand::Rng;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, FilterContext, FilterFragment, FilterGraph};
 
 pub struct EncodeAction;
 
@@ -10,24 +10,32 @@ impl VideoAction for EncodeAction {
         "encode"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "修改编码参数",
+            name_en: "Re-encode (x264)",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "encode")?;
-        
-        let mut rng = rand::thread_rng();
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
         let crf = rng.gen_range(18..=28);
         
         let presets = ["ultrafast", "superfast", "veryfast", "faster", "fast", "medium"];
         let preset = presets[rng.gen_range(0..presets.len())];
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
+        Ok(Some(FilterFragment::output_args(&[
             "-c:v", "libx264",
             "-crf", &crf.to_string(),
             "-preset", preset,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        ]).choice("crf", crf).choice("preset", preset)))
     }
 }
@@ -1,26 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct FlashAction;
 
+const FLASH_STRENGTH: ParamSpec = ParamSpec {
+    name: "flash_strength",
+    label: "闪白强度",
+    kind: ParamKind::Float { min: 0.1, max: 1.0, default: 0.3 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for FlashAction {
     fn id(&self) -> &'static str {
         "flash"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "3D闪白",
+            name_en: "3D white flash",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[FLASH_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "flash")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let amplitude = config.float(&FLASH_STRENGTH)? / 3.0;
+        let vf = format!("eq=brightness='{:.3}*sin(10*t)'", amplitude);
         
-        let vf = "eq=brightness='0.1*sin(10*t)'";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
@@ -1,25 +1,44 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, FilterContext, FilterFragment, FilterGraph};
 
 pub struct FpsAction;
 
+const TARGET_FPS: ParamSpec = ParamSpec {
+    name: "target_fps",
+    label: "目标帧率",
+    kind: ParamKind::Int { min: 24, max: 120, default: 60 },
+    unit: "FPS",
+    description: "",
+};
+
 impl VideoAction for FpsAction {
     fn id(&self) -> &'static str {
         "fps_60"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "强制60帧",
+            name_en: "Force 60 fps",
+            category: ActionCategory::Basic,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[TARGET_FPS]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "fps")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let fps = config.int(&TARGET_FPS)?.to_string();
         
-        let fps = config.params.get("target_fps").and_then(|v| v.as_u64()).unwrap_or(60).to_string();
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-r", &fps,
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::output_args(&["-r", &fps])))
     }
 }
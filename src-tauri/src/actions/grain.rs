@@ -1,29 +1,47 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct GrainAction;
 
+const GRAIN_STRENGTH: ParamSpec = ParamSpec {
+    name: "grain_strength",
+    label: "强度",
+    kind: ParamKind::Float { min: 0.0, max: 0.5, default: 0.1 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for GrainAction {
     fn id(&self) -> &'static str {
         "grain"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "胶片颗粒",
+            name_en: "Film grain",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[GRAIN_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "grain")?;
-        
-        let strength = config.params.get("grain_strength").and_then(|v| v.as_f64()).unwrap_or(0.1);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let strength = config.float(&GRAIN_STRENGTH)?;
         // Scale 0.0-0.5 to 0-50 for noise filter
         let noise_val = (strength * 100.0) as i32;
         let vf = format!("noise=alls={}:allf=t+u", noise_val);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
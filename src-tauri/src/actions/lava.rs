@@ -1,26 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct LavaAction;
 
+const LAVA_STRENGTH: ParamSpec = ParamSpec {
+    name: "lava_strength",
+    label: "效果强度",
+    kind: ParamKind::Float { min: 0.1, max: 1.0, default: 0.5 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for LavaAction {
     fn id(&self) -> &'static str {
         "lava"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "岩浆AB模式",
+            name_en: "Lava AB mode",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[LAVA_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_lava")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let amplitude = 0.6 * config.float(&LAVA_STRENGTH)?;
+        let vf = format!("eq=contrast='1+{:.3}*sin(t)':saturation='1.5'", amplitude);
         
-        let vf = "eq=contrast='1+0.3*sin(t)':saturation='1.5'";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
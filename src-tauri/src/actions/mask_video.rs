@@ -1,17 +1,60 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct MaskVideoAction;
 
+const MASK_VIDEO_OPACITY: ParamSpec = ParamSpec {
+    name: "mask_video_opacity",
+    label: "透明度",
+    kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.8 },
+    unit: "",
+    description: "",
+};
+
+const MASK_VIDEO_BLEND_MODE: ParamSpec = ParamSpec {
+    name: "mask_video_blend_mode",
+    label: "混合模式",
+    kind: ParamKind::Choice {
+        options: &[
+            ("multiply", "正片叠底 (Multiply)"),
+            ("screen", "滤色 (Screen)"),
+            ("overlay", "叠加 (Overlay)"),
+            ("addition", "相加 (Add)"),
+            ("subtract", "相减 (Subtract)"),
+            ("difference", "差值 (Difference)"),
+        ],
+        default: "multiply",
+    },
+    unit: "",
+    description: "正片叠底适合暗色蒙版，滤色适合亮色蒙版，叠加效果较平衡，相加会增强亮度",
+};
+
 impl VideoAction for MaskVideoAction {
     fn id(&self) -> &'static str {
         "mask_video"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "蒙版视频叠加",
+            name_en: "Mask video overlay",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[Material::MaskVideo],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[MASK_VIDEO_OPACITY, MASK_VIDEO_BLEND_MODE]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "mask_video")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.mask_video_path {
             let escaped_path = FFUtils::escape_path(path);
             
@@ -20,21 +63,14 @@ impl VideoAction for MaskVideoAction {
             // 2. scale2ref -> Scales the mask video (first input) to match the main video dimensions (second input)
             // 3. blend -> Applies the blend effect
             // 4. shortest=1 -> Ensures output stops when the main video ends (important since mask is now infinite)
-            let vf = format!("movie='{}':loop=0[mask];[mask][in]scale2ref[mask_scaled][in_main];[in_main][mask_scaled]blend=all_mode=multiply:shortest=1", escaped_path);
+            let opacity = config.float(&MASK_VIDEO_OPACITY)?;
+            let mode = config.text(&MASK_VIDEO_BLEND_MODE)?;
+            let graph = format!("movie='{}':loop=0[mask];[mask][in]scale2ref[mask_scaled][in_main];[in_main][mask_scaled]blend=all_mode={}:all_opacity={}:shortest=1", escaped_path, mode, opacity);
             
-            FFUtils::run(&[
-                "-i", src.to_str().unwrap(),
-                "-vf", &vf,
-                "-c:a", "copy",
-                dst.to_str().unwrap()
-            ])
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback: copy
-            FFUtils::run(&[
-                "-i", src.to_str().unwrap(),
-                "-c", "copy",
-                dst.to_str().unwrap()
-            ])
+            Ok(Some(FilterFragment::default()))
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, Material, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 // Placeholder implementations for material overlay actions
 pub struct StickerAction;
@@ -12,100 +12,165 @@ pub struct GoodsTemplateAction;
 
 impl VideoAction for StickerAction {
     fn id(&self) -> &'static str { "sticker" }
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "添加贴纸",
+            name_en: "Sticker",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[Material::Sticker],
+        }
+    }
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "sticker")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.sticker_path {
             // Sticker centered
             let escaped_path = FFUtils::escape_path(path);
-            let vf = format!("movie='{}'[s];[in][s]overlay=(W-w)/2:(H-h)/2", escaped_path);
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", &vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            let graph = format!("movie='{}'[s];[in][s]overlay=(W-w)/2:(H-h)/2", escaped_path);
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            Ok(Some(FilterFragment::default()))
         }
     }
 }
 
 impl VideoAction for MaskAction {
     fn id(&self) -> &'static str { "mask" }
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "蒙版叠加",
+            name_en: "Mask overlay",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[Material::Mask],
+        }
+    }
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "mask")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.mask_path {
             // Mask overlay (full stretch or centered) - here we assume overlay
             let escaped_path = FFUtils::escape_path(path);
-            let vf = format!("movie='{}'[m];[in][m]overlay=0:0", escaped_path);
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", &vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            let graph = format!("movie='{}'[m];[in][m]overlay=0:0", escaped_path);
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            Ok(Some(FilterFragment::default()))
         }
     }
 }
 
 impl VideoAction for PipAction {
     fn id(&self) -> &'static str { "pip" }
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "画中画",
+            name_en: "Picture in picture",
+            category: ActionCategory::Material,
+            touches: Touches::VIDEO,
+            materials: &[Material::Pip],
+        }
+    }
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "pip")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.pip_path {
             // Picture-in-Picture: robust scaling relative to main video
             // 1. scale2ref=w=iw/4:h=-1 -> Scale PIP to 1/4th of main video width, maintain aspect ratio
             // 2. overlay -> Place in bottom-right with padding
             let escaped_path = FFUtils::escape_path(path);
-            let vf = format!("movie='{}'[pip];[pip][in]scale2ref=w=iw/4:h=ow/mdar[pip_scaled][in_main];[in_main][pip_scaled]overlay=W-w-20:H-h-20", escaped_path);
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", &vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            let graph = format!("movie='{}'[pip];[pip][in]scale2ref=w=iw/4:h=ow/mdar[pip_scaled][in_main];[in_main][pip_scaled]overlay=W-w-20:H-h-20", escaped_path);
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            Ok(Some(FilterFragment::default()))
         }
     }
 }
 
 impl VideoAction for EdgeEffectAction {
     fn id(&self) -> &'static str { "edge_effect" }
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "边缘效果",
+            name_en: "Edge effect",
+            category: ActionCategory::Material,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "edge")?;
-        let vf = "edgedetect=mode=colormix";
-        FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+        FilterGraph::apply(self, src, &dst, config)
+    }
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        Ok(Some(FilterFragment::video(Filter::Chain("edgedetect=mode=colormix".to_string()))))
     }
 }
 
 impl VideoAction for LightEffectAction {
     fn id(&self) -> &'static str { "light_effect" }
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "光效叠加",
+            name_en: "Light effect overlay",
+            category: ActionCategory::Material,
+            touches: Touches::VIDEO,
+            materials: &[Material::LightEffect],
+        }
+    }
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "light")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.light_effect_path {
             // Light effect: auto-loop and auto-scale to fill screen
             let escaped_path = FFUtils::escape_path(path);
             // Use screen blending for light effects (better for black background overlays), or simple overlay if transparent
             // Here we use scale2ref to fill screen and 'shortest=1' to match main video duration
-            let vf = format!("movie='{}':loop=0[light];[light][in]scale2ref[light_scaled][in_main];[in_main][light_scaled]blend=all_mode=screen:shortest=1", escaped_path);
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", &vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            let graph = format!("movie='{}':loop=0[light];[light][in]scale2ref[light_scaled][in_main];[in_main][light_scaled]blend=all_mode=screen:shortest=1", escaped_path);
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback: add brightness/glow effect
-            let vf = "eq=brightness=0.1:contrast=1.1";
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            Ok(Some(FilterFragment::video(Filter::Chain("eq=brightness=0.1:contrast=1.1".to_string()))))
         }
     }
 }
 
 impl VideoAction for GoodsTemplateAction {
     fn id(&self) -> &'static str { "goods_template" }
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "带货模板",
+            name_en: "Goods template",
+            category: ActionCategory::Material,
+            touches: Touches::VIDEO,
+            materials: &[Material::Goods],
+        }
+    }
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "goods")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.goods_path {
             // Goods template: auto-scale to fit screen
             let escaped_path = FFUtils::escape_path(path);
             // using scale2ref to ensure template matches video size exactly
-            let vf = format!("movie='{}'[template];[template][in]scale2ref[template_scaled][in_main];[in_main][template_scaled]overlay=0:0:shortest=1", escaped_path);
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-vf", &vf, "-c:a", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            let graph = format!("movie='{}'[template];[template][in]scale2ref[template_scaled][in_main];[in_main][template_scaled]overlay=0:0:shortest=1", escaped_path);
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback
-            FFUtils::run(&["-y", "-i", src.to_str().unwrap(), "-c", "copy", "-loglevel", "error", dst.to_str().unwrap()])
+            Ok(Some(FilterFragment::default()))
         }
     }
 }
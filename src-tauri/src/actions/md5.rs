@@ -1,7 +1,9 @@
 use std::path::Path;
+use std::time::Instant;
 use anyhow::Result;
+use rand::Rng;
 use uuid::Uuid;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils};
 
 pub struct Md5Action;
 
@@ -10,9 +12,20 @@ impl VideoAction for Md5Action {
         "md5"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "一键MD5 (Remux)",
+            name_en: "MD5 refresh (remux)",
+            category: ActionCategory::Basic,
+            touches: Touches::CONTAINER,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
         let dst = FFUtils::get_dst(src, out_dir, "md5")?;
-        let uid = Uuid::new_v4().to_string();
+        let uid = Uuid::from_bytes(config.rng(self.id()).gen()).to_string();
         
         FFUtils::run(&[
             "-y",
@@ -22,6 +35,10 @@ impl VideoAction for Md5Action {
             "-metadata", &format!("comment={}", uid),
             "-loglevel", "error",
             dst.to_str().unwrap()
-        ])
+        ])?;
+        
+        let mut output = ActionOutput::new(dst, started.elapsed());
+        output.choices.push(format!("{}: comment={}", self.id(), uid));
+        Ok(output)
     }
 }
@@ -1,31 +1,52 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct MirrorAction;
 
+const MIRROR_DIRECTION: ParamSpec = ParamSpec {
+    name: "mirror_direction",
+    label: "翻转方向",
+    kind: ParamKind::Choice {
+        options: &[("horizontal", "水平"), ("vertical", "垂直"), ("both", "双向")],
+        default: "horizontal",
+    },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for MirrorAction {
     fn id(&self) -> &'static str {
         "mirror"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "镜像翻转",
+            name_en: "Mirror flip",
+            category: ActionCategory::Basic,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[MIRROR_DIRECTION]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "flip")?;
-        
-        let direction = config.params.get("mirror_direction").and_then(|v| v.as_str()).unwrap_or("horizontal");
-        let vf = match direction {
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let direction = config.text(&MIRROR_DIRECTION)?;
+        let vf = match direction.as_str() {
             "vertical" => "vflip",
             "both" => "hflip,vflip",
             _ => "hflip"
         };
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf.to_string()))))
     }
 }
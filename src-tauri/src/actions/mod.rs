@@ -1,91 +1,64 @@
-pub mod crop;
-pub mod speed;
-pub mod rotate;
-pub mod mirror;
-pub mod fps;
-pub mod bitrate;
-pub mod cut;
-pub mod md5;
-pub mod grain;
-pub mod blur;
-pub mod sharpen;
-pub mod color;
-pub mod vignette;
-pub mod border;
-pub mod denoise;
-pub mod bw;
-pub mod mute;
-pub mod noise;
-pub mod pitch;
-pub mod pull;
-pub mod corner;
-pub mod scan;
-pub mod zoom;
-pub mod dissolve;
-pub mod flash;
-pub mod bounce;
-pub mod lava;
-pub mod progressive;
-pub mod trifold;
-pub mod touch;
-pub mod clean;
-pub mod portrait;
-pub mod strong_crop;
-pub mod encode;
-pub mod ab_blend;
-pub mod ab_glitch;
-pub mod ab_shake;
-pub mod ab_chroma;
-pub mod ab_replace;
-pub mod ab_advanced_replace;
-pub mod ab_real_replace;
-pub mod watermark;
-pub mod material_overlay;
-pub mod mask_video;
-pub mod ai_opencv;
+use crate::core::ActionFactory;
 
-pub use crop::CropAction;
-pub use speed::SpeedAction;
-pub use rotate::RotateAction;
-pub use mirror::MirrorAction;
-pub use fps::FpsAction;
-pub use bitrate::BitrateAction;
-pub use cut::CutAction;
-pub use md5::Md5Action;
-pub use grain::GrainAction;
-pub use blur::BlurAction;
-pub use sharpen::SharpenAction;
-pub use color::ColorAction;
-pub use vignette::VignetteAction;
-pub use border::BorderAction;
-pub use denoise::DenoiseAction;
-pub use bw::BwAction;
-pub use mute::MuteAction;
-pub use noise::AudioNoiseAction;
-pub use pitch::PitchAction;
-pub use pull::PullAction;
-pub use corner::CornerAction;
-pub use scan::ScanAction;
-pub use zoom::ZoomAction;
-pub use dissolve::DissolveAction;
-pub use flash::FlashAction;
-pub use bounce::BounceAction;
-pub use lava::LavaAction;
-pub use progressive::ProgressiveAction;
-pub use trifold::TrifoldAction;
-pub use touch::TouchAction;
-pub use clean::CleanAction;
-pub use portrait::PortraitAction;
-pub use strong_crop::StrongCropAction;
-pub use encode::EncodeAction;
-pub use ab_blend::AbBlendAction;
-pub use ab_glitch::AbGlitchAction;
-pub use ab_shake::AbShakeAction;
-pub use ab_chroma::AbChromaAction;
-pub use ab_replace::AbReplaceAction;
-pub use ab_advanced_replace::AbAdvancedReplaceAction;
-pub use ab_real_replace::AbRealReplaceAction;
-pub use watermark::WatermarkAction;
-pub use mask_video::MaskVideoAction;
-pub use material_overlay::{StickerAction, MaskAction, PipAction, EdgeEffectAction, LightEffectAction, GoodsTemplateAction};
-pub use ai_opencv::{FaceDetectionAction, ObjectTrackingAction, OpencvFilterAction};
+/// Declares the action modules, re-exports their actions and registers them with the factory.
+/// Within a category, actions are listed in the UI in the order they appear here.
+macro_rules! actions {
+    ($($module:ident => [$($action:ident),+ $(,)?],)+) => {
+        $(pub mod $module;)+
+        $(pub use $module::{$($action),+};)+
+
+        /// Register every built-in action
+        pub fn register_all(factory: &mut ActionFactory) {
+            $($(factory.register($action);)+)+
+        }
+    };
+}
+
+actions! {
+    md5 => [Md5Action],
+    crop => [CropAction],
+    cut => [CutAction],
+    rotate => [RotateAction],
+    speed => [SpeedAction],
+    mirror => [MirrorAction],
+    fps => [FpsAction],
+    bitrate => [BitrateAction],
+    sharpen => [SharpenAction],
+    portrait => [PortraitAction],
+    denoise => [DenoiseAction],
+    clean => [CleanAction],
+    grain => [GrainAction],
+    blur => [BlurAction],
+    color => [ColorAction],
+    vignette => [VignetteAction],
+    bw => [BwAction],
+    border => [BorderAction],
+    pull => [PullAction],
+    corner => [CornerAction],
+    zoom => [ZoomAction],
+    dissolve => [DissolveAction],
+    scan => [ScanAction],
+    bounce => [BounceAction],
+    trifold => [TrifoldAction],
+    lava => [LavaAction],
+    flash => [FlashAction],
+    progressive => [ProgressiveAction],
+    ab_blend => [AbBlendAction],
+    ab_glitch => [AbGlitchAction],
+    ab_shake => [AbShakeAction],
+    ab_chroma => [AbChromaAction],
+    ab_replace => [AbReplaceAction],
+    ab_advanced_replace => [AbAdvancedReplaceAction],
+    mute => [MuteAction],
+    noise => [AudioNoiseAction],
+    pitch => [PitchAction],
+    silent => [SilentAudioAction],
+    touch => [TouchAction],
+    strong_crop => [StrongCropAction],
+    watermark => [WatermarkAction],
+    encode => [EncodeAction],
+    material_overlay => [StickerAction, MaskAction, LightEffectAction, PipAction, EdgeEffectAction, GoodsTemplateAction],
+    mask_video => [MaskVideoAction],
+    ab_real_replace => [AbRealReplaceAction],
+    ai_opencv => [FaceDetectionAction, ObjectTrackingAction, OpencvFilterAction],
+}
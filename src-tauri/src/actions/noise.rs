@@ -1,28 +1,50 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct AudioNoiseAction;
 
+const NOISE_STRENGTH: ParamSpec = ParamSpec {
+    name: "noise_strength",
+    label: "噪音强度",
+    kind: ParamKind::Float { min: 0.001, max: 0.1, default: 0.01 },
+    unit: "",
+    description: "强度越大，噪音越明显",
+};
+
 impl VideoAction for AudioNoiseAction {
     fn id(&self) -> &'static str {
         "audio_noise"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "混入弱白噪音",
+            name_en: "Mix in faint white noise",
+            category: ActionCategory::Audio,
+            touches: Touches::AUDIO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[NOISE_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "anoise")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        if !ctx.has_audio() {
+            // Nothing to change in a silent video; the rest of the chain still runs
+            return Ok(Some(FilterFragment::default()));
+        }
+        let strength = config.float(&NOISE_STRENGTH)?;
+        // aevalsrc=-2+random(0):d=50[n];[n]volume={strength}[vn];[in][vn]amix=inputs=2:duration=first
+        let graph = format!("aevalsrc=-2+random(0):d=50[n];[n]volume={}[vn];[in][vn]amix=inputs=2:duration=first", strength);
         
-        let strength = config.params.get("noise_strength").and_then(|v| v.as_f64()).unwrap_or(0.01);
-        // aevalsrc=-2+random(0):d=50[n];[n]volume={strength}[vn];[0:a][vn]amix=inputs=2:duration=first
-        let filter_complex = format!("aevalsrc=-2+random(0):d=50[n];[n]volume={}[vn];[0:a][vn]amix=inputs=2:duration=first", strength);
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter_complex", &filter_complex,
-            "-c:v", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::audio(Filter::Graph(graph))))
     }
 }
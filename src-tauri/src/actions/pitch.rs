@@ -1,20 +1,49 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use rand::Rng;
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct PitchAction;
 
+const PITCH_RANGE: ParamSpec = ParamSpec {
+    name: "pitch_range",
+    label: "变调范围",
+    kind: ParamKind::Float { min: 0.1, max: 12.0, default: 0.5 },
+    unit: "半音",
+    description: "例如 2 代表 -2 到 +2 半音",
+};
+
 impl VideoAction for PitchAction {
     fn id(&self) -> &'static str {
         "pitch"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "音频变调",
+            name_en: "Audio pitch shift",
+            category: ActionCategory::Audio,
+            touches: Touches::AUDIO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[PITCH_RANGE]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "pitch")?;
-        
-        let range = config.params.get("pitch_range").and_then(|v| v.as_f64()).unwrap_or(0.5);
-        let mut rng = rand::thread_rng();
-        use rand::Rng;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        if !ctx.has_audio() {
+            // Nothing to change in a silent video; the rest of the chain still runs
+            return Ok(Some(FilterFragment::default()));
+        }
+        let range = config.float(&PITCH_RANGE)?;
+        let mut rng = config.rng(self.id());
         let semitones = rng.gen_range(-range..range);
         
         // Convert semitones to rate multiplier: 2^(semitones/12)
@@ -23,13 +52,6 @@ impl VideoAction for PitchAction {
         
         let af = format!("asetrate={},aresample=44100", new_rate);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-af", &af,
-            "-c:v", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::audio(Filter::Chain(af)).choice("semitones", format!("{:.3}", semitones))))
     }
 }
@@ -1,27 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct PortraitAction;
 
+const PORTRAIT_STRENGTH: ParamSpec = ParamSpec {
+    name: "portrait_strength",
+    label: "柔焦强度",
+    kind: ParamKind::Float { min: 0.5, max: 10.0, default: 2.0 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for PortraitAction {
     fn id(&self) -> &'static str {
         "portrait"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "智能锐化 (人像)",
+            name_en: "Portrait soft focus",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[PORTRAIT_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "portrait")?;
-        
-        let strength = config.params.get("portrait_strength").and_then(|v| v.as_f64()).unwrap_or(2.0);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let strength = config.float(&PORTRAIT_STRENGTH)?;
         let vf = format!("unsharp=7:7:{}:7:7:0.0,eq=contrast=1.1:brightness=0.02", strength);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
@@ -1,27 +1,46 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct ProgressiveAction;
 
+const PROGRESSIVE_RATIO: ParamSpec = ParamSpec {
+    name: "progressive_ratio",
+    label: "对比度波动",
+    kind: ParamKind::Float { min: 0.05, max: 0.5, default: 0.1 },
+    unit: "",
+    description: "对比度随时间起伏的幅度",
+};
+
 impl VideoAction for ProgressiveAction {
     fn id(&self) -> &'static str {
         "progressive"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "渐进处理",
+            name_en: "Progressive frame drop",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[PROGRESSIVE_RATIO]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "prog")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let duration = ctx.duration()?;
+        let ratio = config.float(&PROGRESSIVE_RATIO)?;
+        let vf = format!("fade=t=in:st=0:d=0.5,fade=t=out:st={}:d=0.5,eq=contrast='1+{}*sin(2*PI*t/2)'", duration - 0.5, ratio);
         
-        let duration = FFUtils::get_duration(src)?;
-        let vf = format!("fade=t=in:st=0:d=0.5,fade=t=out:st={}:d=0.5,eq=contrast='1+0.1*sin(2*PI*t/2)'", duration - 0.5);
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
@@ -1,27 +1,51 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct PullAction;
 
+const PULL_INTERVAL: ParamSpec = ParamSpec {
+    name: "pull_interval",
+    label: "抽帧间隔",
+    kind: ParamKind::Int { min: 2, max: 120, default: 30 },
+    unit: "帧",
+    description: "每 N 帧保留 1 帧",
+};
+
 impl VideoAction for PullAction {
     fn id(&self) -> &'static str {
         "pull"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "智能抽帧",
+            name_en: "Frame pulling",
+            category: ActionCategory::Visual,
+            touches: Touches::AV,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[PULL_INTERVAL]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "pull")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let interval = config.int(&PULL_INTERVAL)?;
         // select='not(mod(n,30))',setpts=N/FRAME_RATE/TB
-        let vf = "select='not(mod(n,30))',setpts=N/FRAME_RATE/TB";
+        let vf = format!("select='not(mod(n,{}))',setpts=N/FRAME_RATE/TB", interval);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-an",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment {
+            video: Some(Filter::Chain(vf)),
+            drop_audio: true,
+            time_scale: Some(1.0 / interval as f64),
+            ..Default::default()
+        }))
     }
 }
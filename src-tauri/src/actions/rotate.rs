@@ -1,32 +1,58 @@
 use std::path::Path;
 use anyhow::Result;
 use rand::Rng;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct RotateAction;
 
+const ROTATE_ANGLE: ParamSpec = ParamSpec {
+    name: "rotate_angle",
+    label: "最大角度",
+    kind: ParamKind::Float { min: 0.1, max: 10.0, default: 1.5 },
+    unit: "度",
+    description: "视频将在正负该角度内随机旋转",
+};
+
 impl VideoAction for RotateAction {
     fn id(&self) -> &'static str {
         "rotate"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "微旋转 (±1.5°)",
+            name_en: "Micro rotation",
+            category: ActionCategory::Basic,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[ROTATE_ANGLE]
+    }
+
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[ROTATE_ANGLE]
+    }
+
+    fn per_scene(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "rot")?;
-        
-        let mut rng = rand::thread_rng();
-        let max_angle = config.params.get("rotate_angle").and_then(|v| v.as_f64()).unwrap_or(1.5);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
+        let max_angle = config.float(&ROTATE_ANGLE)?;
         let degree: f64 = rng.gen_range(-max_angle..max_angle);
         
         // rotate={degree}*PI/180,scale=1.02*iw:-1
         let vf = format!("rotate={}*PI/180,scale=1.02*iw:-1", degree);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf)).choice("degree", degree)))
     }
 }
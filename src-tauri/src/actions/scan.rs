@@ -1,26 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct ScanAction;
 
+const SCAN_STRENGTH: ParamSpec = ParamSpec {
+    name: "scan_strength",
+    label: "光扫强度",
+    kind: ParamKind::Float { min: 0.1, max: 1.0, default: 0.5 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for ScanAction {
     fn id(&self) -> &'static str {
         "scan"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AI随机光扫",
+            name_en: "Random light scan",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[SCAN_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "scan")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let amplitude = 0.16 * config.float(&SCAN_STRENGTH)?;
+        let vf = format!("eq=brightness='{:.3}*sin(2*PI*t/3)'", amplitude);
         
-        let vf = "eq=brightness='0.08*sin(2*PI*t/3)'";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
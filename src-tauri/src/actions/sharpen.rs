@@ -1,27 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct SharpenAction;
 
+const SHARPEN_STRENGTH: ParamSpec = ParamSpec {
+    name: "sharpen_strength",
+    label: "强度",
+    kind: ParamKind::Float { min: 0.0, max: 5.0, default: 1.0 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for SharpenAction {
     fn id(&self) -> &'static str {
         "sharpen"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "智能锐化",
+            name_en: "Sharpen",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[SHARPEN_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "sharp")?;
-        
-        let strength = config.params.get("sharpen_strength").and_then(|v| v.as_f64()).unwrap_or(1.0);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let strength = config.float(&SHARPEN_STRENGTH)?;
         let vf = format!("unsharp=5:5:{}:5:5:0.0", strength);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
@@ -0,0 +1,31 @@
+use std::path::Path;
+use anyhow::Result;
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils, FilterContext, FilterFragment, FilterGraph};
+
+/// For platforms that reject uploads without an audio track. Files that have audio keep it.
+pub struct SilentAudioAction;
+
+impl VideoAction for SilentAudioAction {
+    fn id(&self) -> &'static str {
+        "silent_audio"
+    }
+
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "补充静音音轨",
+            name_en: "Add silent track if missing",
+            category: ActionCategory::Audio,
+            touches: Touches::AUDIO,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let dst = FFUtils::get_dst(src, out_dir, "silent")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        Ok(Some(FilterFragment { silent_audio: true, ..Default::default() }))
+    }
+}
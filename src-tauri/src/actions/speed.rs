@@ -1,32 +1,63 @@
 use std::path::Path;
 use anyhow::Result;
 use rand::Rng;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct SpeedAction;
 
+const SPEED_RANGE: ParamSpec = ParamSpec {
+    name: "speed_range",
+    label: "变速范围",
+    kind: ParamKind::Float { min: 0.01, max: 0.5, default: 0.05 },
+    unit: "",
+    description: "例如 0.1 代表速度在 0.9x 到 1.1x 之间随机",
+};
+
 impl VideoAction for SpeedAction {
     fn id(&self) -> &'static str {
         "speed"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "非线性变速 (0.95-1.05x)",
+            name_en: "Random speed change",
+            category: ActionCategory::Basic,
+            touches: Touches::AV,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[SPEED_RANGE]
+    }
+
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[SPEED_RANGE]
+    }
+
+    fn per_scene(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "spd")?;
-        
-        let mut rng = rand::thread_rng();
-        let range = config.params.get("speed_range").and_then(|v| v.as_f64()).unwrap_or(0.05);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
+        let range = config.float(&SPEED_RANGE)?;
         let speed: f64 = rng.gen_range((1.0 - range)..(1.0 + range));
         
         let setpts = format!("setpts={:.4}*PTS", 1.0/speed);
         let atempo = format!("atempo={:.4}", speed);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter:v", &setpts,
-            "-filter:a", &atempo,
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment {
+            video: Some(Filter::Chain(setpts)),
+            audio: Some(Filter::Chain(atempo)),
+            time_scale: Some(1.0 / speed),
+            ..Default::default()
+        }.choice("speed", format!("{:.4}", speed))))
     }
 }
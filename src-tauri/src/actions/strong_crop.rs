@@ -1,20 +1,45 @@
 use std::path::Path;
 use anyhow::Result;
 use rand::Rng;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct StrongCropAction;
 
+const STRONG_CROP_RATIO: ParamSpec = ParamSpec {
+    name: "strong_crop_ratio",
+    label: "裁剪比例",
+    kind: ParamKind::Float { min: 0.05, max: 0.3, default: 0.1 },
+    unit: "",
+    description: "裁剪比例越大，去重效果越强",
+};
+
 impl VideoAction for StrongCropAction {
     fn id(&self) -> &'static str {
         "strong_crop"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "强力裁剪 (8-12%)",
+            name_en: "Strong crop (8-12%)",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[STRONG_CROP_RATIO]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "strong_crop")?;
-        
-        let mut rng = rand::thread_rng();
-        let crop_ratio = config.params.get("strong_crop_ratio").and_then(|v| v.as_f64()).unwrap_or(0.1);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
+        let crop_ratio = config.float(&STRONG_CROP_RATIO)?;
         // Randomly vary slightly around the target ratio (±1%)
         let min_keep = 1.0 - (crop_ratio + 0.01);
         let max_keep = 1.0 - (crop_ratio - 0.01);
@@ -22,13 +47,6 @@ impl VideoAction for StrongCropAction {
         
         let vf = format!("crop=iw*{:.3}:ih*{:.3}:(iw-ow)/2:(ih-oh)/2", ratio, ratio);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf)).choice("keep", format!("{:.3}", ratio))))
     }
 }
@@ -1,8 +1,8 @@
 use std::path::Path;
 use std::fs;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, FFUtils};
 
 pub struct TouchAction;
 
@@ -11,7 +11,18 @@ impl VideoAction for TouchAction {
         "touch"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "仅修改时间戳",
+            name_en: "Touch file timestamps",
+            category: ActionCategory::Audio,
+            touches: Touches::CONTAINER,
+            materials: &[],
+        }
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
         let dst = FFUtils::get_dst(src, out_dir, "touch")?;
         
         // Copy file
@@ -21,6 +32,6 @@ impl VideoAction for TouchAction {
         let now = SystemTime::now();
         filetime::set_file_mtime(&dst, filetime::FileTime::from_system_time(now))?;
         
-        Ok(())
+        Ok(ActionOutput::new(dst, started.elapsed()))
     }
 }
@@ -1,26 +1,50 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct TrifoldAction;
 
+const TRIFOLD_SPACING: ParamSpec = ParamSpec {
+    name: "trifold_spacing",
+    label: "屏幕间距",
+    kind: ParamKind::Int { min: 0, max: 50, default: 0 },
+    unit: "像素",
+    description: "",
+};
+
 impl VideoAction for TrifoldAction {
     fn id(&self) -> &'static str {
         "trifold"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "三联屏效果",
+            name_en: "Triptych",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[TRIFOLD_SPACING]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ab_tri")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let spacing = config.int(&TRIFOLD_SPACING)?;
+        let graph = if spacing > 0 {
+            // Pad the left and middle panels on the right to leave a black gap
+            format!("[in]split=3[a][b][c];[a]pad=iw+{0}:ih[a_pad];[b]hflip,pad=iw+{0}:ih[b_flip];[a_pad][b_flip][c]hstack=inputs=3,scale=iw:ih", spacing)
+        } else {
+            "[in]split=3[a][b][c];[b]hflip[b_flip];[a][b_flip][c]hstack=inputs=3,scale=iw:ih".to_string()
+        };
         
-        let filter_complex = "[0:v]split=3[a][b][c];[b]hflip[b_flip];[a][b_flip][c]hstack=inputs=3,scale=iw:ih";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-filter_complex", filter_complex,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Graph(graph))))
     }
 }
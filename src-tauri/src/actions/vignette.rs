@@ -1,29 +1,47 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct VignetteAction;
 
+const VIGNETTE_STRENGTH: ParamSpec = ParamSpec {
+    name: "vignette_strength",
+    label: "强度",
+    kind: ParamKind::Float { min: 0.1, max: 1.0, default: 0.2 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for VignetteAction {
     fn id(&self) -> &'static str {
         "vignette"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "电影暗角",
+            name_en: "Cinematic vignette",
+            category: ActionCategory::Visual,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[VIGNETTE_STRENGTH]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "vig")?;
-        
-        let strength = config.params.get("vignette_strength").and_then(|v| v.as_f64()).unwrap_or(0.2);
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let strength = config.float(&VIGNETTE_STRENGTH)?;
         // strength 0.0-1.0 maps to angle 0 to PI/2
         let angle = strength * std::f64::consts::PI / 2.0;
         let vf = format!("vignette={:.3}", angle);
         
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", &vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf))))
     }
 }
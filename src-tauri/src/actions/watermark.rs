@@ -1,25 +1,61 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, Material, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 // Note: Watermark requires external image file - placeholder implementation
 pub struct WatermarkAction;
 
+const WATERMARK_POSITION: ParamSpec = ParamSpec {
+    name: "watermark_position",
+    label: "位置",
+    kind: ParamKind::Choice {
+        options: &[("top_left", "左上"), ("top_right", "右上"), ("bottom_left", "左下"), ("bottom_right", "右下"), ("center", "居中")],
+        default: "top_right",
+    },
+    unit: "",
+    description: "仅对图片水印有效",
+};
+
+const WATERMARK_OPACITY: ParamSpec = ParamSpec {
+    name: "watermark_opacity",
+    label: "不透明度",
+    kind: ParamKind::Float { min: 0.1, max: 1.0, default: 0.5 },
+    unit: "",
+    description: "",
+};
+
 impl VideoAction for WatermarkAction {
     fn id(&self) -> &'static str {
         "watermark"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "添加水印",
+            name_en: "Watermark",
+            category: ActionCategory::Strong,
+            touches: Touches::VIDEO,
+            materials: &[Material::Watermark],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[WATERMARK_POSITION, WATERMARK_OPACITY]
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "watermark")?;
-        
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
         if let Some(path) = &config.watermark_path {
             // Get parameters
-            let position = config.params.get("watermark_position").and_then(|v| v.as_str()).unwrap_or("top_right");
-            let opacity = config.params.get("watermark_opacity").and_then(|v| v.as_f64()).unwrap_or(0.5);
+            let position = config.text(&WATERMARK_POSITION)?;
+            let opacity = config.float(&WATERMARK_OPACITY)?;
             
             // Calculate overlay coordinates
-            let coord = match position {
+            let coord = match position.as_str() {
                 "top_left" => "10:10",
                 "top_right" => "W-w-10:10",
                 "bottom_left" => "10:H-h-10",
@@ -31,28 +67,14 @@ impl VideoAction for WatermarkAction {
             // Apply opacity and overlay
             // [wm]format=rgba,colorchannelmixer=aa={opacity}[wm_t];[in][wm_t]overlay={coord}
             let escaped_path = FFUtils::escape_path(path);
-            let vf = format!("movie='{}',format=rgba,colorchannelmixer=aa={}[wm];[in][wm]overlay={}", escaped_path, opacity, coord);
+            let graph = format!("movie='{}',format=rgba,colorchannelmixer=aa={}[wm];[in][wm]overlay={}", escaped_path, opacity, coord);
             
-            FFUtils::run(&[
-                "-y",
-                "-i", src.to_str().unwrap(),
-                "-vf", &vf,
-                "-c:a", "copy",
-                "-loglevel", "error",
-                dst.to_str().unwrap()
-            ])
+            Ok(Some(FilterFragment::video(Filter::Graph(graph))))
         } else {
             // Fallback: Text Watermark
             let vf = "drawtext=text='Processed':fontsize=24:fontcolor=white@0.5:x=10:y=10";
             
-            FFUtils::run(&[
-                "-y",
-                "-i", src.to_str().unwrap(),
-                "-vf", vf,
-                "-c:a", "copy",
-                "-loglevel", "error",
-                dst.to_str().unwrap()
-            ])
+            Ok(Some(FilterFragment::video(Filter::Chain(vf.to_string()))))
         }
     }
 }
@@ -1,26 +1,57 @@
 use std::path::Path;
 use anyhow::Result;
-use crate::core::{VideoAction, ActionConfig, FFUtils};
+use rand::Rng;
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionMeta, ActionCategory, Touches, ParamSpec, ParamKind, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
 
 pub struct ZoomAction;
 
+const ZOOM_RANGE: ParamSpec = ParamSpec {
+    name: "zoom_range",
+    label: "缩放范围",
+    kind: ParamKind::Float { min: 0.01, max: 0.5, default: 0.2 },
+    unit: "",
+    description: "最大放大比例，0.2 代表随机放大到 1.1x 至 1.2x",
+};
+
 impl VideoAction for ZoomAction {
     fn id(&self) -> &'static str {
         "zoom"
     }
 
-    fn execute(&self, src: &Path, out_dir: &Path, _config: &ActionConfig) -> Result<()> {
+    fn meta(&self) -> ActionMeta {
+        ActionMeta {
+            name_zh: "AI随机缩放",
+            name_en: "Random zoom",
+            category: ActionCategory::AiAb,
+            touches: Touches::VIDEO,
+            materials: &[],
+        }
+    }
+
+    fn params(&self) -> &'static [ParamSpec] {
+        &[ZOOM_RANGE]
+    }
+
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[ZOOM_RANGE]
+    }
+
+    fn per_scene(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput> {
         let dst = FFUtils::get_dst(src, out_dir, "ai_zoom")?;
+        FilterGraph::apply(self, src, &dst, config)
+    }
+
+    fn filter(&self, _ctx: &FilterContext, config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        let mut rng = config.rng(self.id());
+        let range = config.float(&ZOOM_RANGE)?;
+        // At least half of the range, so every draw still zooms noticeably
+        let max_zoom = 1.0 + rng.gen_range((range * 0.5)..=range);
+        let vf = format!("zoompan=z='min(zoom+0.0015,{:.4})':d=700:x='iw/2-(iw/zoom/2)':y='ih/2-(ih/zoom/2)'", max_zoom);
         
-        let vf = "zoompan=z='min(zoom+0.0015,1.2)':d=700:x='iw/2-(iw/zoom/2)':y='ih/2-(ih/zoom/2)'";
-        
-        FFUtils::run(&[
-            "-y",
-            "-i", src.to_str().unwrap(),
-            "-vf", vf,
-            "-c:a", "copy",
-            "-loglevel", "error",
-            dst.to_str().unwrap()
-        ])
+        Ok(Some(FilterFragment::video(Filter::Chain(vf)).choice("zoom", format!("{:.4}", max_zoom))))
     }
 }
@@ -0,0 +1,484 @@
+//! Headless front end for build servers: `vmp run | job | list-actions | probe | preview`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+use anyhow::{Result, anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
+use video_matrix_pro::core::{ActionConfig, ActionFactory, ActionCategory, Material, FFUtils, SceneOptions};
+use video_matrix_pro::core::engine::{self, BatchJob, CancelToken, EngineEvent, ProcessMode};
+use video_matrix_pro::core::job::JobSpec;
+use video_matrix_pro::core::probe;
+use video_matrix_pro::core::scan::ScanOptions;
+use video_matrix_pro::core::search::SimilarityTarget;
+use video_matrix_pro::core::similarity;
+use video_matrix_pro::core::watch::{self, WatchOptions};
+
+#[derive(Parser)]
+#[command(name = "vmp", version, about = "Video Matrix Pro command-line tool")]
+struct Cli {
+    /// Print progress as JSON lines instead of plain text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // parsed once, boxing only adds noise
+enum Command {
+    /// Process every video in a directory
+    Run {
+        #[command(flatten)]
+        job: JobArgs,
+
+        /// Input directory or video file; may be repeated
+        #[arg(short, long, required = true)]
+        input: Vec<PathBuf>,
+
+        /// Apply all actions to one output per file, or write one output per action
+        #[arg(long, value_enum, default_value_t = ModeArg::Stacked)]
+        mode: ModeArg,
+
+        /// Output file name template using {stem} and {action}, without extension
+        #[arg(long)]
+        naming: Option<String>,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+
+        /// Files processed at the same time (default: from core count and resolution)
+        #[arg(short = 'j', long)]
+        workers: Option<usize>,
+
+        /// Threads for each ffmpeg (default: from core count and resolution)
+        #[arg(long)]
+        threads: Option<usize>,
+
+        /// Process every file again, ignoring the journal of earlier runs in the output directory
+        #[arg(long)]
+        restart: bool,
+
+        /// Compare each output with its source by perceptual hashes and write a similarity report
+        #[arg(long)]
+        similarity: bool,
+
+        #[command(flatten)]
+        target: TargetArgs,
+
+        /// Write the job as a JSON or TOML manifest instead of running it
+        #[arg(long, value_name = "FILE")]
+        save_job: Option<PathBuf>,
+    },
+    /// Run a JSON or TOML job manifest
+    Job {
+        manifest: PathBuf,
+
+        /// Process every file again, ignoring the journal of earlier runs in the output directory
+        #[arg(long)]
+        restart: bool,
+    },
+    /// Watch folders and run a job manifest on every new video once it has finished copying
+    Watch {
+        /// Job manifest giving the actions and settings
+        manifest: PathBuf,
+
+        /// Folder to watch instead of the manifest inputs; may be repeated
+        #[arg(short, long)]
+        input: Vec<PathBuf>,
+
+        /// Output directory instead of the manifest's
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Move originals here once all their tasks succeeded
+        #[arg(long, value_name = "DIR")]
+        archive: Option<PathBuf>,
+
+        /// Seconds between two scans
+        #[arg(long, default_value_t = 5.0)]
+        interval: f64,
+
+        /// Seconds a file's size and modification time must stay unchanged before it is taken
+        #[arg(long, default_value_t = 10.0)]
+        settle: f64,
+    },
+    /// List the available actions and their parameters
+    ListActions,
+    /// Show stream and format information of a media file
+    Probe {
+        file: PathBuf,
+
+        /// Print ffprobe's own JSON instead of the parsed summary
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Score how similar a processed video still is to its source
+    Compare {
+        source: PathBuf,
+        output: PathBuf,
+    },
+    /// Render a short stacked preview of the first video
+    Preview {
+        #[command(flatten)]
+        job: JobArgs,
+
+        /// Input file, or a directory to take the first video from
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Length of the preview clip in seconds
+        #[arg(long, default_value_t = 5.0)]
+        seconds: f64,
+    },
+}
+
+#[derive(clap::Args)]
+struct JobArgs {
+    /// Output directory (default: <input>/output)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Comma separated action ids, in order
+    #[arg(short, long, value_delimiter = ',', required = true)]
+    actions: Vec<String>,
+
+    /// Action parameter as name=value (value is parsed as JSON, falling back to a string)
+    #[arg(short, long = "param", value_name = "NAME=VALUE")]
+    params: Vec<String>,
+
+    /// Material file as kind=path, e.g. watermark=logo.png
+    #[arg(short, long = "material", value_name = "KIND=PATH")]
+    materials: Vec<String>,
+
+    /// Seed for the randomized actions; the same seed reproduces the same outputs
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Split videos at scene cuts and give crop, rotate, speed, color and zoom fresh random
+    /// values in every scene
+    #[arg(long)]
+    scenes: bool,
+
+    /// With --scenes: scdet threshold from 0 to 100, lower finds more cuts
+    #[arg(long, requires = "scenes", default_value_t = SceneOptions::default().threshold)]
+    scene_threshold: f64,
+
+    /// With --scenes: shortest scene
+    #[arg(long, value_name = "SECS", requires = "scenes", default_value_t = SceneOptions::default().min_length)]
+    min_scene: f64,
+}
+
+#[derive(clap::Args)]
+struct ScanArgs {
+    /// Also scan sub-directories of input directories
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Glob on the path relative to the input directory; replaces the video extension filter
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Glob of files or directories to leave out
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    #[arg(long, value_name = "MB")]
+    min_size: Option<f64>,
+
+    #[arg(long, value_name = "MB")]
+    max_size: Option<f64>,
+
+    #[arg(long, value_name = "SECS")]
+    min_duration: Option<f64>,
+
+    #[arg(long, value_name = "SECS")]
+    max_duration: Option<f64>,
+
+    /// Include files and directories whose name starts with a dot
+    #[arg(long)]
+    hidden: bool,
+
+    /// Follow symbolic links instead of skipping them
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Recreate the input directory tree under the output directory
+    #[arg(long)]
+    mirror: bool,
+}
+
+impl From<ScanArgs> for ScanOptions {
+    fn from(args: ScanArgs) -> Self {
+        ScanOptions {
+            recursive: args.recursive,
+            include: args.include,
+            exclude: args.exclude,
+            min_size_mb: args.min_size,
+            max_size_mb: args.max_size,
+            min_duration: args.min_duration,
+            max_duration: args.max_duration,
+            hidden: args.hidden,
+            follow_symlinks: args.follow_symlinks,
+            mirror_tree: args.mirror,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct TargetArgs {
+    /// Re-render each task with other random values and stronger parameters until its
+    /// perceptual similarity is below this, e.g. 0.85
+    #[arg(long = "target", value_name = "SIMILARITY")]
+    max_similarity: Option<f64>,
+
+    /// With --target: keep at least this SSIM
+    #[arg(long, requires = "max_similarity")]
+    min_ssim: Option<f64>,
+
+    /// With --target: also bring the audio fingerprint similarity below this
+    #[arg(long, requires = "max_similarity")]
+    max_audio: Option<f64>,
+
+    /// With --target: renders per task at most
+    #[arg(long, requires = "max_similarity", default_value_t = SimilarityTarget::default().attempts)]
+    attempts: usize,
+}
+
+impl From<TargetArgs> for Option<SimilarityTarget> {
+    fn from(args: TargetArgs) -> Self {
+        Some(SimilarityTarget {
+            max_similarity: args.max_similarity?,
+            min_ssim: args.min_ssim,
+            max_audio: args.max_audio,
+            attempts: args.attempts,
+        })
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ModeArg {
+    Stacked,
+    Independent,
+}
+
+impl JobArgs {
+    fn config(&self) -> Result<ActionConfig> {
+        let scenes = self.scenes.then_some(SceneOptions { threshold: self.scene_threshold, min_length: self.min_scene });
+        let mut config = ActionConfig { seed: self.seed, scenes, ..Default::default() };
+
+        for param in &self.params {
+            let (name, raw) = param.split_once('=')
+                .ok_or_else(|| anyhow!("Invalid --param \"{}\", expected NAME=VALUE", param))?;
+            let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+            if ActionFactory::global().param(name).is_none() {
+                bail!("Unknown parameter: {}", name);
+            }
+            config.params[name] = value;
+        }
+
+        for material in &self.materials {
+            let (kind, path) = material.split_once('=')
+                .ok_or_else(|| anyhow!("Invalid --material \"{}\", expected KIND=PATH", material))?;
+            let kind: Material = serde_json::from_value(json!(kind))
+                .map_err(|_| anyhow!("Unknown material kind: {}", kind))?;
+            kind.set_path(&mut config, path.to_string());
+        }
+
+        Ok(config)
+    }
+
+    fn output_dir(&self, input: &Path) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            let base = if input.is_dir() { input } else { input.parent().unwrap_or(Path::new(".")) };
+            base.join("output")
+        })
+    }
+}
+
+/// Prints engine events either as log lines or as JSON lines
+struct Reporter {
+    json: bool,
+    // Keeps lines from parallel workers from interleaving. Also holds the last 10% step printed
+    // for each running task, so plain output shows ffmpeg progress without flooding.
+    lock: Mutex<HashMap<String, u32>>,
+}
+
+impl Reporter {
+    fn event(&self, event: &EngineEvent) {
+        let mut printed = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        if self.json {
+            println!("{}", json!(event));
+            return;
+        }
+        if let EngineEvent::FileProgress { file, action, fraction, .. } = event {
+            let step = (fraction.unwrap_or(0.0) * 10.0) as u32;
+            if printed.insert(format!("{}|{}", file.display(), action), step) == Some(step) {
+                return;
+            }
+        }
+        println!("{}", event);
+    }
+
+    fn value(&self, value: Value, plain: impl FnOnce() -> String) {
+        if self.json {
+            println!("{}", value);
+        } else {
+            println!("{}", plain());
+        }
+    }
+
+    fn error(&self, error: &anyhow::Error) {
+        if self.json {
+            println!("{}", json!({ "event": "error", "error": error.to_string() }));
+        } else {
+            eprintln!("error: {}", error);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let reporter = Reporter { json: cli.json, lock: Mutex::new(HashMap::new()) };
+
+    match run(cli.command, &reporter) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            reporter.error(&e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Returns Ok(false) when the command ran but some tasks failed
+fn run(command: Command, reporter: &Reporter) -> Result<bool> {
+    match command {
+        Command::Run { job, input, mode, naming, scan, workers, threads, restart, similarity, target, save_job } => {
+            let batch = BatchJob {
+                output_dir: job.output_dir(&input[0]),
+                inputs: input,
+                actions: job.actions.clone(),
+                mode: match mode {
+                    ModeArg::Stacked => ProcessMode::Stacked,
+                    ModeArg::Independent => ProcessMode::Independent,
+                },
+                naming,
+                scan: scan.into(),
+                workers,
+                ffmpeg_threads: threads,
+                restart,
+                similarity,
+                target: target.into(),
+                config: job.config()?,
+            };
+
+            match save_job {
+                Some(path) => {
+                    JobSpec::from_batch(&batch).save(&path)?;
+                    reporter.value(json!({ "event": "saved", "path": path }), || format!("Job written to {}", path.display()));
+                    Ok(true)
+                }
+                None => run_batch(&batch, reporter),
+            }
+        }
+        Command::Job { manifest, restart } => {
+            let base_dir = manifest.parent().unwrap_or(Path::new("."));
+            let batch = BatchJob { restart, ..JobSpec::load(&manifest)?.to_batch(base_dir)? };
+            run_batch(&batch, reporter)
+        }
+        Command::Watch { manifest, input, output, archive, interval, settle } => {
+            let base_dir = manifest.parent().unwrap_or(Path::new("."));
+            let mut batch = JobSpec::load(&manifest)?.to_batch(base_dir)?;
+            if !input.is_empty() {
+                batch.inputs = input;
+            }
+            if let Some(output) = output {
+                batch.output_dir = output;
+            }
+            let options = WatchOptions { interval_secs: interval, settle_secs: settle, archive_dir: archive };
+            let summary = watch::watch(&batch, &options, &CancelToken::default(), &|event| reporter.event(&event))?;
+            Ok(summary.failed == 0)
+        }
+        Command::ListActions => {
+            list_actions(reporter);
+            Ok(true)
+        }
+        Command::Probe { file, raw } => {
+            if raw {
+                let info = FFUtils::probe(&file)?;
+                reporter.value(info.clone(), || serde_json::to_string_pretty(&info).unwrap_or_default());
+            } else {
+                let info = probe::probe(&file)?;
+                reporter.value(json!(*info), || info.to_string());
+            }
+            Ok(true)
+        }
+        Command::Compare { source, output } => {
+            let similarity = similarity::compare(&source, &output)?;
+            reporter.value(json!(similarity), || {
+                let verdict = if similarity.likely_duplicate { "likely flagged as duplicate" } else { "likely passes" };
+                let mut line = format!("similarity {:.1}% (pHash {:.1}%, dHash {:.1}%, SSIM {:.3}, {} frames): {}",
+                    similarity.score * 100.0, similarity.phash * 100.0, similarity.dhash * 100.0, similarity.ssim, similarity.frames, verdict);
+                match similarity.audio {
+                    Some(audio) => {
+                        let verdict = if similarity.audio_match { "still matches" } else { "changed enough" };
+                        line.push_str(&format!("\naudio fingerprint {:.1}%: {}", audio * 100.0, verdict));
+                    }
+                    None => line.push_str("\naudio fingerprint: no audio to compare"),
+                }
+                line
+            });
+            Ok(true)
+        }
+        Command::Preview { job, input, seconds } => {
+            let src = if input.is_dir() {
+                engine::scan_videos(&input).into_iter().next()
+                    .ok_or_else(|| anyhow!("No video files found in {}", input.display()))?
+            } else {
+                input.clone()
+            };
+
+            let output = engine::preview(&src, &job.output_dir(&input), &job.actions, &job.config()?, seconds, &|event| reporter.event(&event))?;
+            reporter.value(json!({ "event": "preview", "output": output.path }), || {
+                format!("Preview written to {}", output.path.display())
+            });
+            Ok(true)
+        }
+    }
+}
+
+fn run_batch(batch: &BatchJob, reporter: &Reporter) -> Result<bool> {
+    let summary = engine::run_batch(batch, &CancelToken::default(), &|event| reporter.event(&event))?;
+    reporter.value(json!({ "event": "summary", "summary": summary }), || {
+        let resumed = if summary.resumed > 0 { format!(" ({} done by earlier runs)", summary.resumed) } else { String::new() };
+        format!("{} / {} tasks succeeded{}, {} failed", summary.succeeded, summary.total, resumed, summary.failed)
+    });
+    Ok(summary.failed == 0)
+}
+
+fn list_actions(reporter: &Reporter) {
+    let factory = ActionFactory::global();
+
+    if reporter.json {
+        for action in factory.actions() {
+            println!("{}", json!({ "id": action.id(), "meta": action.meta(), "params": action.params() }));
+        }
+        return;
+    }
+
+    for category in ActionCategory::ALL {
+        println!("{}", category.name_en());
+        for action in factory.by_category(category) {
+            let meta = action.meta();
+            println!("  {:<22} {} / {}", action.id(), meta.name_zh, meta.name_en);
+            for spec in action.params() {
+                let line = format!("      {}: {} (default {}) {}", spec.name, spec.range_text(), spec.default_value(), spec.unit);
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::Serialize;
+use tauri::{Manager, State, Window};
+use video_matrix_pro::core::{ActionConfig, ActionFactory, ActionMeta, ParamSpec};
+use video_matrix_pro::core::engine::{self, BatchJob, CancelToken};
+
+/// Batch currently running in the webview, if any
+#[derive(Default)]
+pub struct BatchState {
+    running: Mutex<Option<CancelToken>>,
+    /// Output directory of the last job started from the webview; `delete_file` only removes
+    /// files inside it
+    output_dir: Mutex<Option<PathBuf>>,
+}
+
+impl BatchState {
+    fn set_output_dir(&self, dir: &Path) {
+        *self.output_dir.lock().unwrap_or_else(|e| e.into_inner()) = Some(dir.to_path_buf());
+    }
+}
+
+#[derive(Serialize)]
+pub struct ActionInfo {
+    id: &'static str,
+    meta: ActionMeta,
+    params: &'static [ParamSpec],
+}
+
+/// Every registered action with its metadata and parameter schema
+#[tauri::command]
+pub fn list_actions() -> Vec<ActionInfo> {
+    ActionFactory::global().actions()
+        .map(|action| ActionInfo { id: action.id(), meta: action.meta(), params: action.params() })
+        .collect()
+}
+
+/// Run one action on one file and return the path of the produced output
+#[tauri::command]
+pub async fn process_video(action_id: String, src_path: String, out_dir: String, config: Option<ActionConfig>) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let action = ActionFactory::global().get(&action_id)
+            .ok_or_else(|| format!("Unknown action: {}", action_id))?;
+        let config = config.unwrap_or_default();
+        action.validate(&config).map_err(|e| e.to_string())?;
+
+        fs::create_dir_all(&out_dir).map_err(|e| format!("Failed to create output directory: {}", e))?;
+        let output = action.execute(&PathBuf::from(src_path), &PathBuf::from(out_dir), &config)
+            .map_err(|e| e.to_string())?;
+        Ok(output.path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Delete an output of the current job. Paths are resolved first, so neither `..` nor a
+/// symbolic link reaches a file outside the job's output directory.
+#[tauri::command]
+pub fn delete_file(state: State<'_, BatchState>, path: String) -> Result<(), String> {
+    let out_dir = state.output_dir.lock().unwrap_or_else(|e| e.into_inner()).clone()
+        .ok_or_else(|| "没有正在处理的任务，无法删除文件".to_string())?;
+    let root = fs::canonicalize(&out_dir).map_err(|e| format!("无法读取输出目录 {}: {}", out_dir.display(), e))?;
+    let file = fs::canonicalize(&path).map_err(|e| format!("删除失败 {}: {}", path, e))?;
+    if !file.starts_with(&root) || !file.is_file() {
+        return Err(format!("只能删除输出目录 {} 中的文件: {}", out_dir.display(), path));
+    }
+    fs::remove_file(&file).map_err(|e| format!("删除失败 {}: {}", path, e))
+}
+
+/// Start a batch in the background. Progress is emitted to the window as `batch-event`
+/// (an `EngineEvent`), followed by `batch-finished` (a `BatchSummary`) or `batch-error`.
+#[tauri::command]
+pub fn start_batch(window: Window, state: State<'_, BatchState>, job: BatchJob) -> Result<(), String> {
+    let mut running = state.running.lock().unwrap_or_else(|e| e.into_inner());
+    if running.is_some() {
+        return Err("已有任务正在处理中".to_string());
+    }
+
+    let cancel = CancelToken::default();
+    *running = Some(cancel.clone());
+    state.set_output_dir(&job.output_dir);
+
+    std::thread::spawn(move || {
+        let result = engine::run_batch(&job, &cancel, &|event| {
+            let _ = window.emit("batch-event", event);
+        });
+
+        *window.state::<BatchState>().running.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        let _ = match result {
+            Ok(summary) => window.emit("batch-finished", summary),
+            Err(e) => window.emit("batch-error", e.to_string()),
+        };
+    });
+
+    Ok(())
+}
+
+/// Stop the running batch: running ffmpeg processes are killed and their partial outputs removed
+#[tauri::command]
+pub fn cancel_batch(state: State<'_, BatchState>) -> Result<bool, String> {
+    let running = state.running.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(match running.as_ref() {
+        Some(cancel) => {
+            cancel.cancel();
+            true
+        }
+        None => false,
+    })
+}
+
+/// Run the webview frontend from `src/` instead of the egui app
+pub fn run_tauri_app() -> tauri::Result<()> {
+    tauri::Builder::default()
+        .manage(BatchState::default())
+        .invoke_handler(tauri::generate_handler![
+            list_actions,
+            process_video,
+            delete_file,
+            start_batch,
+            cancel_batch,
+        ])
+        .run(tauri::generate_context!())
+}
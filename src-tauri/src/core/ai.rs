@@ -1,6 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use crate::core::{ActionFactory, ActionCategory};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AIResponse {
@@ -49,9 +50,7 @@ impl AIService {
         let system_prompt = r#"
 You are a video processing AI assistant. Your goal is to analyze the user's requirement and suggest a list of video processing actions and their parameters.
 The available actions are:
-- Basic: cut (cut seconds), mirror (flip), rotate (rotate angle), speed (speed range), fps (target fps), bitrate (target bitrate)
-- Visual: sharpen, denoise, blur, grain, vignette, border, portrait (soft focus), color (temp), pull (border), progressive (frame drop), corner (blur)
-- AI/Effects: zoom, dissolve, scan, bounce, trifold, flash, lava, noise, pitch
+{actions}
 
 Output MUST be a valid JSON object with the following structure:
 {
@@ -63,33 +62,11 @@ Output MUST be a valid JSON object with the following structure:
     "explanation": "Brief explanation of why these actions were chosen."
 }
 
-Example params:
-- cut_seconds: 1.0-10.0
-- rotate_angle: 0.1-10.0
-- speed_range: 0.01-0.5
-- sharpen_strength: 0.0-5.0
-- denoise_strength: 0.0-10.0
-- blur_strength: 0.0-10.0
-- grain_strength: 0.0-1.0
-- vignette_strength: 0.0-1.0
-- border_width: 10-100
-- portrait_strength: 0.0-10.0
-- color_temp_range: 100-2000
-- pull_width: 10-200
-- progressive_ratio: 0.0-0.5
-- corner_radius: 10-200
-- zoom_range: 0.0-0.5
-- dissolve_strength: 0.0-1.0
-- scan_strength: 0.0-1.0
-- bounce_amplitude: 0.0-100.0
-- trifold_spacing: 0-50
-- flash_strength: 0.0-1.0
-- lava_strength: 0.0-1.0
-- noise_strength: 0.0-0.1
-- pitch_range: 0.0-12.0
+Available params (name: range, default):
+{params}
 
 Do not include markdown formatting (like ```json). Just return the raw JSON string.
-"#;
+"#.replace("{actions}", &Self::actions_prompt()).replace("{params}", &Self::params_prompt());
 
         let response = client.post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -141,14 +118,20 @@ Rules:
 3. If bitrate is low, suggest denoising and sharpening.
 4. If duration is long, suggest simple cuts or speed ups.
 
-The available actions and output format MUST be exactly the same as the standard requirements:
+The available actions are:
+{actions}
+
+Available params (name: range, default):
+{params}
+
+The output format MUST be exactly the same as the standard requirements:
 {
     "suggested_actions": ["action_id1", "action_id2"],
     "params": { ... },
     "explanation": "Brief explanation focused on the video characteristics."
 }
 Do not include markdown formatting.
-"#;
+"#.replace("{actions}", &Self::actions_prompt()).replace("{params}", &Self::params_prompt());
 
         let response = client.post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
@@ -185,4 +168,38 @@ Do not include markdown formatting.
 
         Ok(ai_response)
     }
+
+    /// One line per parameter with its range and default
+    fn params_prompt() -> String {
+        ActionFactory::global().actions()
+            .flat_map(|action| action.params())
+            .map(|spec| {
+                let unit = if spec.unit.is_empty() { String::new() } else { format!(" {}", spec.unit) };
+                format!("- {}: {}{} (default {})", spec.name, spec.range_text(), unit, spec.default_value())
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// One line per category listing the registered action ids with their English names
+    fn actions_prompt() -> String {
+        let factory = ActionFactory::global();
+        ActionCategory::ALL.iter()
+            .map(|&category| {
+                let actions: Vec<String> = factory.by_category(category)
+                    .map(|action| {
+                        let meta = action.meta();
+                        let materials: Vec<&str> = meta.materials.iter().map(|m| m.name_en()).collect();
+                        if materials.is_empty() {
+                            format!("{} ({})", action.id(), meta.name_en)
+                        } else {
+                            format!("{} ({}, uses {})", action.id(), meta.name_en, materials.join(", "))
+                        }
+                    })
+                    .collect();
+                format!("- {}: {}", category.name_en(), actions.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
@@ -0,0 +1,57 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::Result;
+
+/// Shared flag to stop a running job. Work running inside `CancelToken::scope` sees it through
+/// `cancel::check`, and `FFUtils::run` kills its ffmpeg child when it is set.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+/// Error returned by work that stopped because its job was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "已取消")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Run `f` on this thread with this token as the current one
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|c| c.replace(Some(self.clone())));
+        let result = f();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+        result
+    }
+}
+
+/// Whether the job running on this thread has been cancelled
+pub fn is_cancelled() -> bool {
+    CURRENT.with(|c| c.borrow().as_ref().is_some_and(|t| t.is_cancelled()))
+}
+
+/// Fail with `Cancelled` if the job running on this thread has been cancelled
+pub fn check() -> Result<()> {
+    if is_cancelled() {
+        return Err(Cancelled.into());
+    }
+    Ok(())
+}
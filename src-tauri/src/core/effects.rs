@@ -0,0 +1,167 @@
+use anyhow::Result;
+use crate::core::frames::{Frame, FrameProcessor};
+
+/// Pencil sketch: dark Sobel edges on white, mixed over the picture by `strength` (0-1)
+pub struct Sketch {
+    pub strength: f64,
+}
+
+impl FrameProcessor for Sketch {
+    fn process(&mut self, frame: &mut Frame) -> Result<()> {
+        let (w, h) = (frame.width, frame.height);
+        let luma: Vec<f64> = frame.data.chunks_exact(3)
+            .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+            .collect();
+        let at = |x: isize, y: isize| luma[y.clamp(0, h as isize - 1) as usize * w + x.clamp(0, w as isize - 1) as usize];
+        for y in 0..h as isize {
+            for x in 0..w as isize {
+                let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1) - at(x - 1, y - 1) - 2.0 * at(x - 1, y) - at(x - 1, y + 1);
+                let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1) - at(x - 1, y - 1) - 2.0 * at(x, y - 1) - at(x + 1, y - 1);
+                let paper = 255.0 - (gx * gx + gy * gy).sqrt().min(255.0);
+                let (x, y) = (x as usize, y as usize);
+                let rgb = frame.pixel(x, y).map(|c| (c as f64 * (1.0 - self.strength) + paper * self.strength).round() as u8);
+                frame.set_pixel(x, y, rgb);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cut every channel down to `levels` steps
+pub struct Posterize {
+    pub levels: u32,
+}
+
+impl FrameProcessor for Posterize {
+    fn process(&mut self, frame: &mut Frame) -> Result<()> {
+        let steps = self.levels.max(2) as f64 - 1.0;
+        for c in frame.data.iter_mut() {
+            *c = ((*c as f64 / 255.0 * steps).round() / steps * 255.0).round() as u8;
+        }
+        Ok(())
+    }
+}
+
+/// Chromatic aberration: red shifted to the left and blue to the right by `shift` times the
+/// frame width, at least one pixel
+pub struct RgbSplit {
+    pub shift: f64,
+}
+
+impl FrameProcessor for RgbSplit {
+    fn process(&mut self, frame: &mut Frame) -> Result<()> {
+        let source = frame.clone();
+        let last = frame.width - 1;
+        let offset = (frame.width as f64 * self.shift).round().max(1.0) as usize;
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let red = source.pixel((x + offset).min(last), y)[0];
+                let blue = source.pixel(x.saturating_sub(offset), y)[2];
+                let green = source.pixel(x, y)[1];
+                frame.set_pixel(x, y, [red, green, blue]);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Motion trail: every frame keeps `decay` (0-1) of the frames before it
+pub struct Echo {
+    pub decay: f64,
+    trail: Vec<f32>,
+}
+
+impl Echo {
+    pub fn new(decay: f64) -> Self {
+        Self { decay, trail: Vec::new() }
+    }
+}
+
+impl FrameProcessor for Echo {
+    fn process(&mut self, frame: &mut Frame) -> Result<()> {
+        if self.trail.len() != frame.data.len() {
+            self.trail = frame.data.iter().map(|&c| c as f32).collect();
+            return Ok(());
+        }
+        let decay = self.decay as f32;
+        for (t, c) in self.trail.iter_mut().zip(frame.data.iter_mut()) {
+            *t = *t * decay + *c as f32 * (1.0 - decay);
+            *c = t.round() as u8;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: usize, height: usize, rgb: impl Fn(usize, usize) -> [u8; 3]) -> Frame {
+        let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| rgb(x, y)).collect();
+        Frame { index: 0, time: 0.0, width, height, data }
+    }
+
+    #[test]
+    fn sketch_draws_edges_dark_on_white() {
+        // Black left half, white right half
+        let halves = frame(8, 4, |x, _| if x < 4 { [0; 3] } else { [255; 3] });
+        let mut sketch = halves.clone();
+        Sketch { strength: 1.0 }.process(&mut sketch).unwrap();
+        assert_eq!(sketch.pixel(0, 1), [255; 3]);
+        assert_eq!(sketch.pixel(3, 1), [0; 3]);
+        assert_eq!(sketch.pixel(4, 1), [0; 3]);
+        assert_eq!(sketch.pixel(7, 1), [255; 3]);
+
+        let mut untouched = halves.clone();
+        Sketch { strength: 0.0 }.process(&mut untouched).unwrap();
+        assert_eq!(untouched.data, halves.data);
+    }
+
+    #[test]
+    fn posterize_rounds_to_the_levels() {
+        let mut three = frame(1, 1, |_, _| [60, 100, 200]);
+        Posterize { levels: 3 }.process(&mut three).unwrap();
+        assert_eq!(three.pixel(0, 0), [0, 128, 255]);
+        // Fewer than two levels count as two
+        let mut two = frame(1, 1, |_, _| [60, 100, 200]);
+        Posterize { levels: 0 }.process(&mut two).unwrap();
+        assert_eq!(two.pixel(0, 0), [0, 0, 255]);
+    }
+
+    #[test]
+    fn rgb_split_moves_red_left_and_blue_right() {
+        let source = frame(10, 2, |x, _| [x as u8 * 10, 50, x as u8 * 20]);
+        let mut split = source.clone();
+        // Two pixels of a ten pixel wide frame
+        RgbSplit { shift: 0.2 }.process(&mut split).unwrap();
+        assert_eq!(split.pixel(3, 1), [50, 50, 20]);
+        // Clamped at the edges
+        assert_eq!(split.pixel(9, 0), [90, 50, 140]);
+        assert_eq!(split.pixel(0, 0), [20, 50, 0]);
+
+        // Never less than one pixel
+        let mut thin = source.clone();
+        RgbSplit { shift: 0.001 }.process(&mut thin).unwrap();
+        assert_eq!(thin.pixel(3, 0), [40, 50, 40]);
+    }
+
+    #[test]
+    fn echo_blends_each_frame_into_the_trail() {
+        let mut echo = Echo::new(0.5);
+        let mut first = frame(2, 2, |_, _| [100; 3]);
+        echo.process(&mut first).unwrap();
+        assert_eq!(first.pixel(1, 1), [100; 3]);
+
+        let mut second = frame(2, 2, |_, _| [200; 3]);
+        echo.process(&mut second).unwrap();
+        assert_eq!(second.pixel(1, 1), [150; 3]);
+        let mut third = frame(2, 2, |_, _| [200; 3]);
+        echo.process(&mut third).unwrap();
+        assert_eq!(third.pixel(0, 0), [175; 3]);
+
+        // A frame of another size starts a new trail
+        let mut other = frame(3, 1, |_, _| [20; 3]);
+        echo.process(&mut other).unwrap();
+        assert_eq!(other.pixel(2, 0), [20; 3]);
+    }
+}
@@ -0,0 +1,618 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use anyhow::{Result, anyhow, bail};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, ActionFactory, FFUtils};
+use crate::core::journal::Journal;
+use crate::core::pipeline::StackedChain;
+use crate::core::probe::probe;
+use crate::core::progress::{self, FfmpegProgress};
+use crate::core::scan::{scan, ScanOptions, ScannedFile};
+use crate::core::search::{self, SearchResult, SimilarityTarget};
+use crate::core::similarity::{self, Similarity, SimilarityRecord};
+pub use crate::core::cancel::CancelToken;
+
+pub const VIDEO_EXTENSIONS: [&str; 8] = ["mp4", "mov", "mkv", "avi", "wmv", "flv", "webm", "m4v"];
+
+/// How the selected actions are applied to each file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessMode {
+    /// All actions on one file, written as `{stem}_processed.{ext}`
+    #[default]
+    Stacked,
+    /// One output file per action
+    Independent,
+}
+
+/// A batch run over every video in `inputs`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchJob {
+    /// Directories (scanned for videos according to `scan`) or single files
+    pub inputs: Vec<PathBuf>,
+    pub output_dir: PathBuf,
+    pub actions: Vec<String>,
+    #[serde(default)]
+    pub mode: ProcessMode,
+    /// Output file name without extension. `{stem}` is the source name and `{action}` the action
+    /// id (the ids joined with `_` in stacked mode). Defaults to `{stem}_processed` when stacked and
+    /// to each action's own suffix when independent.
+    #[serde(default)]
+    pub naming: Option<String>,
+    #[serde(default)]
+    pub scan: ScanOptions,
+    /// Files processed at the same time; picked from the core count and resolution when unset
+    #[serde(default)]
+    pub workers: Option<usize>,
+    /// `-threads` given to each ffmpeg; picked with `workers` when unset
+    #[serde(default)]
+    pub ffmpeg_threads: Option<usize>,
+    /// Process every task again instead of skipping those the journal marks as done
+    #[serde(default)]
+    pub restart: bool,
+    /// Compare every output with its source by perceptual hashes and write
+    /// `similarity_report.json` into the output directory
+    #[serde(default)]
+    pub similarity: bool,
+    /// Render each task again with other random values and stronger parameters until its output
+    /// is different enough from the source, see `search`. Scores are reported as with `similarity`.
+    #[serde(default)]
+    pub target: Option<SimilarityTarget>,
+    #[serde(default)]
+    pub config: ActionConfig,
+}
+
+/// Progress reported by the engine. `Display` gives the log line shown in the UI and CLI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EngineEvent {
+    Log { message: String },
+    Progress { completed: usize, total: usize },
+    TaskStarted { file: PathBuf, action: String },
+    /// Position of the ffmpeg pass currently running for a task. In stacked mode each pass of
+    /// the chain starts again from 0.
+    FileProgress { file: PathBuf, action: String, fraction: Option<f64>, speed: Option<f64>, eta_secs: Option<f64> },
+    TaskDone { file: PathBuf, action: String, output: PathBuf, elapsed_secs: f64, choices: Vec<String> },
+    TaskFailed { file: PathBuf, action: String, error: String },
+    TaskCancelled { file: PathBuf, action: String },
+    /// Done by an earlier run of the same job, see `Journal`
+    TaskSkipped { file: PathBuf, action: String, output: PathBuf },
+    /// How close a finished output still is to its source, see `BatchJob::similarity`, and the
+    /// attempt kept when searching for a target
+    Similarity {
+        file: PathBuf,
+        action: String,
+        output: PathBuf,
+        similarity: Similarity,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        search: Option<SearchResult>,
+    },
+}
+
+impl EngineEvent {
+    pub(crate) fn log(message: impl Into<String>) -> Self {
+        EngineEvent::Log { message: message.into() }
+    }
+}
+
+impl fmt::Display for EngineEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |p: &Path| p.file_name().unwrap_or_default().to_string_lossy().to_string();
+        match self {
+            EngineEvent::Log { message } => write!(f, "{}", message),
+            EngineEvent::Progress { completed, total } => write!(f, "[{}/{}]", completed, total),
+            EngineEvent::TaskStarted { file, action } => write!(f, "  ⏳ Processing: {} [{}]...", name(file), action),
+            EngineEvent::FileProgress { file, action, fraction, speed, eta_secs } => {
+                write!(f, "    ⏱ {} [{}]", name(file), action)?;
+                if let Some(fraction) = fraction {
+                    write!(f, " {:.0}%", fraction * 100.0)?;
+                }
+                if let Some(speed) = speed {
+                    write!(f, " {:.2}x", speed)?;
+                }
+                if let Some(eta) = eta_secs {
+                    write!(f, " 剩余 {}", format_secs(*eta))?;
+                }
+                Ok(())
+            }
+            EngineEvent::TaskDone { file, action, output, elapsed_secs, choices } => {
+                write!(f, "  ✅ {} Completed ({}) → {} ({:.1}s)", action, name(file), name(output), elapsed_secs)?;
+                if !choices.is_empty() {
+                    write!(f, " 🎲 {}", choices.join(", "))?;
+                }
+                Ok(())
+            }
+            EngineEvent::TaskFailed { file, action, error } => write!(f, "  ❌ {} Failed ({}): {}", action, name(file), error),
+            EngineEvent::TaskCancelled { file, action } => write!(f, "  🛑 {} Cancelled ({})", action, name(file)),
+            EngineEvent::TaskSkipped { file, action, output } => write!(f, "  ⏭ {} 已完成，跳过 ({}) → {}", action, name(file), name(output)),
+            EngineEvent::Similarity { file, action, output, similarity, search } => {
+                write!(f, "  🔍 {} 相似度 ({}) → {}: {:.0}% (pHash {:.0}%, dHash {:.0}%)", action, name(file), name(output),
+                    similarity.score * 100.0, similarity.phash * 100.0, similarity.dhash * 100.0)?;
+                if similarity.likely_duplicate {
+                    write!(f, " ⚠️ 仍可能被判为重复")?;
+                }
+                if let Some(audio) = similarity.audio {
+                    write!(f, "，音频 {:.0}%", audio * 100.0)?;
+                    if similarity.audio_match {
+                        write!(f, " ⚠️ 音频指纹仍可匹配")?;
+                    }
+                }
+                if let Some(search) = search {
+                    write!(f, "，SSIM {:.2}，第 {}/{} 次尝试", similarity.ssim, search.attempt, search.attempts)?;
+                    if !search.target_met {
+                        write!(f, " ⚠️ 未达到目标")?;
+                    }
+                    if !search.params.is_empty() {
+                        let params: Vec<String> = search.params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                        write!(f, " ({})", params.join(", "))?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` past an hour
+pub fn format_secs(secs: f64) -> String {
+    let secs = secs.max(0.0).round() as u64;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Outcome of a batch run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Tasks skipped because the batch was cancelled
+    pub cancelled: usize,
+    /// Tasks skipped because an earlier run already did them; counted as succeeded
+    pub resumed: usize,
+}
+
+/// Video files directly inside `dir`
+pub fn scan_videos(dir: &Path) -> Vec<PathBuf> {
+    let mut video_files = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_video = path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+            if is_video && entry.metadata().is_ok_and(|m| m.is_file()) {
+                video_files.push(path);
+            }
+        }
+    }
+
+    video_files.sort();
+    video_files
+}
+
+/// Fill in a naming template. The extension is kept from the source.
+pub fn output_name(template: &str, src: &Path, action: &str) -> String {
+    let stem = src.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+    let ext = src.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    format!("{}.{}", template.replace("{stem}", stem).replace("{action}", action), ext)
+}
+
+/// Check that a naming template cannot make two outputs of the job collide
+pub fn check_naming(template: &str, mode: ProcessMode, action_count: usize) -> Result<()> {
+    if !template.contains("{stem}") {
+        bail!("命名模板必须包含 {{stem}}: {}", template);
+    }
+    if mode == ProcessMode::Independent && action_count > 1 && !template.contains("{action}") {
+        bail!("独立输出模式下命名模板必须包含 {{action}}: {}", template);
+    }
+    if template.contains(['/', '\\']) {
+        bail!("命名模板不能包含路径分隔符: {}", template);
+    }
+    Ok(())
+}
+
+/// Look up the actions by id, failing on the first unknown one
+pub fn resolve_actions(ids: &[String]) -> Result<Vec<&'static dyn VideoAction>> {
+    ids.iter()
+        .map(|id| ActionFactory::global().get(id).ok_or_else(|| anyhow!("Unknown action: {}", id)))
+        .collect()
+}
+
+/// The config with its seed fixed: the configured one, or a fresh random seed that is logged so
+/// the run can be repeated. Fresh seeds stay short enough to type and to store in TOML.
+fn seeded(config: &ActionConfig, on_event: &dyn Fn(EngineEvent)) -> ActionConfig {
+    let seed = config.seed.unwrap_or_else(|| rand::random::<u32>() as u64);
+    on_event(EngineEvent::log(format!("🎲 随机种子: {}", seed)));
+    ActionConfig { seed: Some(seed), ..config.clone() }
+}
+
+/// Check every action's parameters before any file is touched
+pub fn validate(actions: &[&dyn VideoAction], config: &ActionConfig) -> Result<()> {
+    for action in actions {
+        action.validate(config).map_err(|e| anyhow!("{}: {}", action.id(), e))?;
+    }
+    Ok(())
+}
+
+/// Apply all actions to one file, writing `{stem}_processed.{ext}` (or the `naming` template) into `out_dir`
+pub fn run_stacked(actions: &[&dyn VideoAction], src: &Path, out_dir: &Path, naming: Option<&str>, config: &ActionConfig, log: &dyn Fn(String)) -> Result<ActionOutput> {
+    let chain = actions.iter().map(|a| a.id()).collect::<Vec<_>>().join("_");
+    let dst = out_dir.join(output_name(naming.unwrap_or("{stem}_processed"), src, &chain));
+
+    StackedChain::new(actions.to_vec()).run(src, &dst, config, log)
+}
+
+/// Files processed at once and ffmpeg threads per file. Unset values are chosen from the core
+/// count and the largest resolution among the first inputs: bigger frames get more threads per
+/// encode and fewer encodes at once, which keeps memory use bounded on 4K sources.
+pub fn worker_budget(job: &BatchJob, files: &[ScannedFile]) -> (usize, usize) {
+    let cores = thread::available_parallelism().map_or(4, |n| n.get());
+    if let (Some(workers), Some(threads)) = (job.workers, job.ffmpeg_threads) {
+        return (workers.max(1), threads.max(1));
+    }
+
+    let pixels = files.iter().take(4).filter_map(|f| frame_pixels(&f.path)).max().unwrap_or(0);
+    let is_4k = pixels >= 3840 * 2160 * 3 / 4;
+    let threads = job.ffmpeg_threads.unwrap_or_else(|| match job.workers {
+        Some(workers) => cores / workers.max(1),
+        None if is_4k => 8,
+        None if pixels >= 1920 * 1080 * 3 / 4 || pixels == 0 => 4,
+        None => 2,
+    }.clamp(1, cores));
+    let workers = job.workers.unwrap_or_else(|| {
+        let workers = cores / threads.max(1);
+        if is_4k { workers.min(2) } else { workers }
+    });
+    (workers.max(1), threads.max(1))
+}
+
+/// Width × height of the first video stream
+fn frame_pixels(src: &Path) -> Option<u64> {
+    let (width, height) = probe(src).ok()?.display_size()?;
+    Some(width as u64 * height as u64)
+}
+
+/// Run one task with `cancel` as its token and `threads` as its ffmpeg thread budget, sending the
+/// progress of its ffmpeg passes to `progress`
+fn run_task<T>(cancel: &CancelToken, threads: usize, progress: &Sender<EngineEvent>, file: &Path, action: &str, task: impl FnOnce() -> T) -> T {
+    let (progress, file, action) = (progress.clone(), file.to_path_buf(), action.to_string());
+    let sink = move |p: FfmpegProgress| {
+        let _ = progress.send(EngineEvent::FileProgress {
+            file: file.clone(),
+            action: action.clone(),
+            fraction: p.fraction(),
+            speed: p.speed,
+            eta_secs: p.eta_secs(),
+        });
+    };
+    progress::scope(sink, || cancel.scope(|| FFUtils::with_threads(threads, task)))
+}
+
+/// Resolve the job's actions and check their parameters and the naming template
+pub fn check_job(job: &BatchJob) -> Result<Vec<&'static dyn VideoAction>> {
+    let actions = resolve_actions(&job.actions)?;
+    validate(&actions, &job.config)?;
+    if let Some(naming) = &job.naming {
+        check_naming(naming, job.mode, actions.len())?;
+    }
+    Ok(actions)
+}
+
+/// Process every video of the job in parallel. Per-file failures are reported as events and
+/// counted in the summary; only setup problems (no files, bad output dir) return an error.
+/// Cancelling `cancel` kills the running ffmpeg processes, removes their partial outputs and
+/// skips the tasks that have not started.
+pub fn run_batch(job: &BatchJob, cancel: &CancelToken, on_event: &(dyn Fn(EngineEvent) + Sync)) -> Result<BatchSummary> {
+    let actions = check_job(job)?;
+
+    for input in &job.inputs {
+        on_event(EngineEvent::log(format!("📂 Input: {}", input.display())));
+    }
+    on_event(EngineEvent::log(format!("📂 Output: {}", job.output_dir.display())));
+    on_event(EngineEvent::log(format!("✅ Selected {} features", actions.len())));
+    on_event(EngineEvent::log(format!("🎯 处理模式: {}", match job.mode {
+        ProcessMode::Stacked => "单个视频功能叠加",
+        ProcessMode::Independent => "每个功能独立输出",
+    })));
+
+    on_event(EngineEvent::log("🔍 Scanning for video files..."));
+    let video_files = scan(&job.inputs, &job.scan, &[&job.output_dir])?;
+    if video_files.is_empty() {
+        bail!("No video files found");
+    }
+
+    on_event(EngineEvent::log(format!("📹 Found {} video files", video_files.len())));
+    process_files(job, &video_files, cancel, on_event)
+}
+
+/// Run the job's actions on already scanned files; the part of `run_batch` after the scan.
+/// The job is expected to be validated.
+pub fn process_files(job: &BatchJob, video_files: &[ScannedFile], cancel: &CancelToken, on_event: &(dyn Fn(EngineEvent) + Sync)) -> Result<BatchSummary> {
+    Session::open(job, video_files, on_event)?.process(job, video_files, cancel, on_event)
+}
+
+/// What a run sets up before its first task: the actions, the config with its seed, the
+/// worker pool and the journal. A watch session opens one and runs every round in it.
+pub struct Session {
+    actions: Vec<&'static dyn VideoAction>,
+    config: ActionConfig,
+    pool: rayon::ThreadPool,
+    threads: usize,
+    journal: Journal,
+}
+
+impl Session {
+    /// Set up for `job`, sizing the workers by the first of `video_files`, and log the seed
+    /// and the worker budget
+    pub fn open(job: &BatchJob, video_files: &[ScannedFile], on_event: &(dyn Fn(EngineEvent) + Sync)) -> Result<Self> {
+        let actions = resolve_actions(&job.actions)?;
+        let config = seeded(&job.config, on_event);
+        let (workers, threads) = worker_budget(job, video_files);
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(workers).build()
+            .map_err(|e| anyhow!("Failed to start worker threads: {}", e))?;
+        on_event(EngineEvent::log(format!("🚀 并行处理 {} 个文件，每个 ffmpeg {} 线程{}", workers, threads,
+            if job.workers.is_none() || job.ffmpeg_threads.is_none() { " (自动)" } else { "" })));
+
+        fs::create_dir_all(&job.output_dir)
+            .map_err(|e| anyhow!("Failed to create output directory: {}", e))?;
+        let journal = Journal::open(&job.output_dir, Journal::params_hash(job))?;
+        if !job.restart && journal.done_count() > 0 {
+            on_event(EngineEvent::log(format!("📒 任务记录中有 {} 个已完成的任务，将跳过输出完好的部分", journal.done_count())));
+        }
+        if let Some(target) = &job.target {
+            on_event(EngineEvent::log(format!("🎯 相似度目标: {}", target)));
+        }
+        Ok(Self { actions, config, pool, threads, journal })
+    }
+
+    /// Run the job's actions on `video_files`
+    pub fn process(&self, job: &BatchJob, video_files: &[ScannedFile], cancel: &CancelToken, on_event: &(dyn Fn(EngineEvent) + Sync)) -> Result<BatchSummary> {
+        let Self { actions, config, pool, journal, .. } = self;
+        let threads = self.threads;
+        let total = match job.mode {
+            ProcessMode::Stacked => video_files.len(),
+            ProcessMode::Independent => video_files.len() * actions.len(),
+        };
+        let completed = AtomicUsize::new(0);
+        let failed = AtomicUsize::new(0);
+        let skipped = AtomicUsize::new(0);
+        let resumed = AtomicUsize::new(0);
+
+        on_event(EngineEvent::Progress { completed: 0, total });
+
+        // Skip a task an earlier run finished, or mark it as started
+        let begin = |file: &Path, action: &str| -> bool {
+            if let Some(output) = journal.completed(file, action).filter(|_| !job.restart) {
+                resumed.fetch_add(1, Ordering::SeqCst);
+                let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+                on_event(EngineEvent::Progress { completed: done, total });
+                on_event(EngineEvent::TaskSkipped { file: file.to_path_buf(), action: action.to_string(), output: output.to_path_buf() });
+                return false;
+            }
+            journal.started(file, action);
+            on_event(EngineEvent::TaskStarted { file: file.to_path_buf(), action: action.to_string() });
+            true
+        };
+
+        let similarities = Mutex::new(Vec::new());
+        let finish = |file: &Path, action: &str, result: Result<TaskOutput>| {
+            let done = completed.fetch_add(1, Ordering::SeqCst) + 1;
+            on_event(EngineEvent::Progress { completed: done, total });
+            match result {
+                Ok(TaskOutput { output, similarity: measured, search }) => {
+                    journal.done(file, action, &output.path);
+                    on_event(EngineEvent::TaskDone {
+                        file: file.to_path_buf(),
+                        action: action.to_string(),
+                        output: output.path.clone(),
+                        elapsed_secs: output.elapsed.as_secs_f64(),
+                        choices: output.choices,
+                    });
+                    let measured = match measured {
+                        Some(similarity) => Some(similarity),
+                        None if job.similarity && output.path != file && !cancel.is_cancelled() => match cancel.scope(|| similarity::compare(file, &output.path)) {
+                            Ok(similarity) => Some(similarity),
+                            Err(_) if cancel.is_cancelled() => None,
+                            Err(e) => {
+                                on_event(EngineEvent::log(format!("  ⚠️ 相似度计算失败 ({}): {}", output.path.display(), e)));
+                                None
+                            }
+                        },
+                        None => None,
+                    };
+                    if let Some(similarity) = measured {
+                        on_event(EngineEvent::Similarity {
+                            file: file.to_path_buf(),
+                            action: action.to_string(),
+                            output: output.path.clone(),
+                            similarity,
+                            search: search.clone(),
+                        });
+                        let record = SimilarityRecord { source: file.to_path_buf(), output: output.path, action: action.to_string(), similarity, search };
+                        similarities.lock().unwrap_or_else(|e| e.into_inner()).push(record);
+                    }
+                }
+                // Left as pending in the journal, so the next run retries it
+                Err(_) if cancel.is_cancelled() => {
+                    skipped.fetch_add(1, Ordering::SeqCst);
+                    on_event(EngineEvent::TaskCancelled { file: file.to_path_buf(), action: action.to_string() });
+                }
+                Err(e) => {
+                    failed.fetch_add(1, Ordering::SeqCst);
+                    journal.failed(file, action, &e.to_string());
+                    on_event(EngineEvent::TaskFailed { file: file.to_path_buf(), action: action.to_string(), error: e.to_string() });
+                }
+            }
+        };
+
+        // ffmpeg progress is reported from the worker threads through a channel and forwarded here
+        let (progress_tx, progress_rx) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(move || progress_rx.into_iter().for_each(on_event));
+
+            pool.install(|| {
+                video_files.par_iter().for_each(|video| {
+                    let video_path = &video.path;
+                    let config = config.for_file(video_path, &video.rel_dir);
+                    let out_dir = job.scan.out_dir(video, &job.output_dir);
+                    if let Err(e) = fs::create_dir_all(&out_dir) {
+                        on_event(EngineEvent::log(format!("❌ Failed to create output directory {}: {}", out_dir.display(), e)));
+                    }
+                    if cancel.is_cancelled() {
+                        let tasks = match job.mode {
+                            ProcessMode::Stacked => 1,
+                            ProcessMode::Independent => actions.len(),
+                        };
+                        skipped.fetch_add(tasks, Ordering::SeqCst);
+                        return;
+                    }
+
+                    match job.mode {
+                        ProcessMode::Stacked => {
+                            // 单个视频叠加模式：所有动作合并为一个滤镜图，尽量一次编码完成
+                            let chain = job.actions.join(" → ");
+                            let filename = video_path.file_name().unwrap_or_default().to_string_lossy();
+                            if !begin(video_path, &chain) {
+                                return;
+                            }
+
+                            let log = |msg: String| on_event(EngineEvent::log(format!("    [{}] {}", filename, msg)));
+                            let result = run_task(cancel, threads, &progress_tx, video_path, &chain, || {
+                                render_task(job, actions, video_path, &config, &log, |config| {
+                                    run_stacked(actions, video_path, &out_dir, job.naming.as_deref(), config, &log)
+                                })
+                            });
+                            finish(video_path, &chain, result);
+                        }
+                        ProcessMode::Independent => {
+                            // 原始模式：每个动作生成独立视频
+                            let filename = video_path.file_name().unwrap_or_default().to_string_lossy();
+                            let log = |msg: String| on_event(EngineEvent::log(format!("    [{}] {}", filename, msg)));
+                            for (i, action) in actions.iter().enumerate() {
+                                if cancel.is_cancelled() {
+                                    skipped.fetch_add(actions.len() - i, Ordering::SeqCst);
+                                    break;
+                                }
+                                if !begin(video_path, action.id()) {
+                                    continue;
+                                }
+                                let result = run_task(cancel, threads, &progress_tx, video_path, action.id(), || {
+                                    render_task(job, &[*action], video_path, &config, &log, |config| {
+                                        let output = action.execute(video_path, &out_dir, config)?;
+                                        match &job.naming {
+                                            // An action that only exported data hands back the source itself
+                                            Some(naming) if output.path != *video_path => rename_output(output, &output_name(naming, video_path, action.id())),
+                                            _ => Ok(output),
+                                        }
+                                    })
+                                });
+                                finish(video_path, action.id(), result);
+                            }
+                        }
+                    }
+                });
+            });
+            drop(progress_tx);
+        });
+
+        let failed = failed.into_inner();
+        let cancelled = skipped.into_inner();
+        let resumed = resumed.into_inner();
+        if resumed > 0 {
+            on_event(EngineEvent::log(format!("⏭ 跳过 {} 个之前已完成的任务", resumed)));
+        }
+        if cancelled > 0 {
+            on_event(EngineEvent::log(format!("🛑 已取消 {} 个任务", cancelled)));
+        }
+        report_similarity(&job.output_dir, &similarities.into_inner().unwrap_or_else(|e| e.into_inner()), on_event);
+        Ok(BatchSummary { total, succeeded: total - failed - cancelled, failed, cancelled, resumed })
+    }
+}
+
+/// What a task produced, with the scores already measured by a target search
+struct TaskOutput {
+    output: ActionOutput,
+    similarity: Option<Similarity>,
+    search: Option<SearchResult>,
+}
+
+/// Render a task once, or until its output meets the job's similarity target
+fn render_task(job: &BatchJob, actions: &[&dyn VideoAction], src: &Path, config: &ActionConfig, log: &dyn Fn(String), render: impl Fn(&ActionConfig) -> Result<ActionOutput>) -> Result<TaskOutput> {
+    match &job.target {
+        Some(target) => {
+            let (output, similarity, search) = search::search(target, actions, config, src, render, log)?;
+            Ok(TaskOutput { output, similarity: Some(similarity), search: Some(search) })
+        }
+        None => Ok(TaskOutput { output: render(config)?, similarity: None, search: None }),
+    }
+}
+
+/// Log the averages of the batch and merge its scores into the report of the output directory
+fn report_similarity(out_dir: &Path, records: &[SimilarityRecord], on_event: &dyn Fn(EngineEvent)) {
+    if records.is_empty() {
+        return;
+    }
+    let average = records.iter().map(|r| r.similarity.score).sum::<f64>() / records.len() as f64;
+    let duplicates = records.iter().filter(|r| r.similarity.likely_duplicate).count();
+    on_event(EngineEvent::log(format!("📊 平均相似度 {:.0}%，{} / {} 个输出仍可能被判为重复", average * 100.0, duplicates, records.len())));
+    match similarity::update_report(out_dir, records) {
+        Ok(path) => on_event(EngineEvent::log(format!("📊 相似度报告: {}", path.display()))),
+        Err(e) => on_event(EngineEvent::log(format!("⚠️ {}", e))),
+    }
+}
+
+/// Move an action's output to the name chosen by the job, keeping the extension the action picked
+fn rename_output(mut output: ActionOutput, name: &str) -> Result<ActionOutput> {
+    let mut dst = output.path.with_file_name(name);
+    if let Some(ext) = output.path.extension() {
+        dst.set_extension(ext);
+    }
+    fs::rename(&output.path, &dst).map_err(|e| anyhow!("Failed to rename {}: {}", output.path.display(), e))?;
+    output.path = dst;
+    Ok(output)
+}
+
+/// Render the first `seconds` of `src` with all actions stacked, into `out_dir/preview`.
+/// Random values are derived from the name of `src`, so they match a full run with the same seed
+/// when the file sits at the top of its input directory.
+pub fn preview(src: &Path, out_dir: &Path, action_ids: &[String], config: &ActionConfig, seconds: f64, on_event: &dyn Fn(EngineEvent)) -> Result<ActionOutput> {
+    let actions = resolve_actions(action_ids)?;
+    validate(&actions, config)?;
+
+    let preview_dir = out_dir.join("preview");
+    fs::create_dir_all(&preview_dir)?;
+    let preview_source = preview_dir.join("temp_source.mp4");
+
+    on_event(EngineEvent::log(format!("✂️ 正在截取前 {} 秒...", seconds)));
+    let src_str = src.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+    let source_str = preview_source.to_str().ok_or_else(|| anyhow!("Invalid output path encoding"))?;
+    FFUtils::run(&[
+        "-ss", "0", "-t", &seconds.to_string(),
+        "-i", src_str,
+        "-c:v", "libx264", "-preset", "ultrafast", // Re-encode to ensure clean cut and compatibility
+        "-c:a", "aac",
+        "-loglevel", "error",
+        source_str,
+    ]).map_err(|e| anyhow!("截取失败: {}", e))?;
+
+    on_event(EngineEvent::log("🚀 正在叠加应用所有效果..."));
+    let config = seeded(config, on_event).for_file(src, Path::new(""));
+    let output = run_stacked(&actions, &preview_source, &preview_dir, None, &config, &|msg| {
+        on_event(EngineEvent::log(format!("  {}", msg)));
+    })?;
+    if !output.choices.is_empty() {
+        on_event(EngineEvent::log(format!("  🎲 {}", output.choices.join(", "))));
+    }
+    Ok(output)
+}
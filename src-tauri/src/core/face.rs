@@ -0,0 +1,358 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tract_onnx::prelude::*;
+use crate::core::{ActionConfig, Material};
+use crate::core::frames::{Frame, Rect};
+
+/// Model used when none is configured, shipped in the `models` bundle resource
+pub const MODEL_FILE: &str = "version-RFB-320.onnx";
+
+/// `package.productName`, which names the resource directory of Linux packages
+const PRODUCT_NAME: &str = "video-matrix-pro-zwm";
+
+/// Input size of models that leave it open, the one UltraFace was trained at
+const DEFAULT_INPUT: (usize, usize) = (320, 240);
+
+/// Overlap above which two detections are taken as the same face
+const NMS_IOU: f64 = 0.3;
+
+/// A face found in one frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub rect: Rect,
+    /// Model confidence, 0-1
+    pub score: f64,
+}
+
+/// Finds faces with an UltraFace ONNX model (`version-RFB-320.onnx` or `version-slim-320.onnx`
+/// of the ONNX model zoo), run on the CPU by tract. The model takes the RGB frame stretched to
+/// its input size and gives a softmaxed (background, face) score and a box in corners relative
+/// to the picture for each of its priors.
+pub struct FaceDetector {
+    model: TypedRunnableModel<TypedModel>,
+    /// Model input width and height
+    input: (usize, usize),
+    /// Smallest face height as a share of the frame height
+    pub min_size: f64,
+    /// Lowest confidence a detection needs to count as a face
+    pub threshold: f64,
+}
+
+impl FaceDetector {
+    pub fn load(path: &Path, min_size: f64, threshold: f64) -> Result<Self> {
+        let model = tract_onnx::onnx().model_for_path(path)
+            .map_err(|e| anyhow!("无法加载人脸检测模型 {}: {}", path.display(), e))?;
+        Self::from_model(model, min_size, threshold)
+    }
+
+    fn from_model(model: InferenceModel, min_size: f64, threshold: f64) -> Result<Self> {
+        let dims = model.input_fact(0)?.shape.as_concrete_finite()?;
+        let input = match dims.as_deref() {
+            Some(&[_, 3, height, width]) => (width, height),
+            _ => DEFAULT_INPUT,
+        };
+        let model = model
+            .with_input_fact(0, f32::fact([1, 3, input.1, input.0]).into())?
+            .into_optimized()?
+            .into_runnable()?;
+        Ok(Self { model, input, min_size, threshold })
+    }
+
+    pub fn detect(&self, frame: &Frame) -> Result<Vec<Detection>> {
+        let outputs = self.model.run(tvec!(input_tensor(frame, self.input).into()))?;
+        // Told apart by shape: [1, priors, 2] scores and [1, priors, 4] boxes
+        let output = |size: usize| -> Result<&[f32]> {
+            let tensor = outputs.iter().find(|t| t.shape().last() == Some(&size))
+                .ok_or_else(|| anyhow!("人脸检测模型的输出不是 UltraFace 格式"))?;
+            tensor.as_slice::<f32>()
+        };
+        let (scores, boxes) = (output(2)?, output(4)?);
+        if scores.len() / 2 != boxes.len() / 4 {
+            bail!("人脸检测模型的输出不是 UltraFace 格式");
+        }
+
+        let (width, height) = (frame.width as f64, frame.height as f64);
+        let candidates = scores.chunks_exact(2).zip(boxes.chunks_exact(4))
+            .filter(|(score, _)| score[1] as f64 >= self.threshold)
+            .map(|(score, corners)| {
+                let [x0, y0, x1, y1] = [0, 1, 2, 3].map(|i| (corners[i] as f64).clamp(0.0, 1.0));
+                Detection {
+                    rect: Rect { x: x0 * width, y: y0 * height, width: (x1 - x0) * width, height: (y1 - y0) * height },
+                    score: score[1] as f64,
+                }
+            })
+            .filter(|d| d.rect.width > 0.0 && d.rect.height >= self.min_size * height)
+            .collect();
+        Ok(suppress(candidates))
+    }
+}
+
+/// The configured model, or the one shipped with the application
+pub fn model_path(config: &ActionConfig) -> Result<PathBuf> {
+    if let Some(path) = Material::FaceModel.path(config) {
+        return Ok(PathBuf::from(path));
+    }
+    let exe_dir = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf));
+    exe_dir.iter()
+        .flat_map(|dir| resource_dirs(dir))
+        .flat_map(|dir| [dir.join("models").join(MODEL_FILE), dir.join(MODEL_FILE)])
+        .find(|path| path.is_file())
+        .ok_or_else(|| anyhow!("未找到人脸检测模型 {}，请在素材中选择 UltraFace 的 ONNX 模型", MODEL_FILE))
+}
+
+/// Where the bundler puts resources relative to the executable: beside it on Windows and
+/// in `tauri dev`, `Contents/Resources` in a macOS app and `/usr/lib/<product>` in Linux
+/// packages and AppImages
+fn resource_dirs(exe_dir: &Path) -> [PathBuf; 3] {
+    let parent = exe_dir.parent().unwrap_or(exe_dir);
+    [exe_dir.to_path_buf(), parent.join("Resources"), parent.join("lib").join(PRODUCT_NAME)]
+}
+
+/// `frame` stretched to the model input, as normalised NCHW floats
+fn input_tensor(frame: &Frame, (width, height): (usize, usize)) -> Tensor {
+    // Each input pixel averages the frame pixels it covers
+    let span = |i: usize, size: usize, full: usize| (i * full / size, ((i + 1) * full / size).max(i * full / size + 1).min(full));
+    let mut pixels = vec![[0.0f32; 3]; width * height];
+    for y in 0..height {
+        let (y0, y1) = span(y, height, frame.height);
+        for x in 0..width {
+            let (x0, x1) = span(x, width, frame.width);
+            let mut sum = [0u32; 3];
+            for (fx, fy) in (y0..y1).flat_map(|fy| (x0..x1).map(move |fx| (fx, fy))) {
+                let rgb = frame.pixel(fx, fy);
+                (0..3).for_each(|c| sum[c] += rgb[c] as u32);
+            }
+            let count = ((x1 - x0) * (y1 - y0)) as f32;
+            pixels[y * width + x] = sum.map(|s| (s as f32 / count - 127.0) / 128.0);
+        }
+    }
+    tract_ndarray::Array4::from_shape_fn((1, 3, height, width), |(_, c, y, x)| pixels[y * width + x][c]).into()
+}
+
+/// Non-maximum suppression: the strongest of overlapping detections is kept
+fn suppress(mut candidates: Vec<Detection>) -> Vec<Detection> {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Detection> = Vec::new();
+    for candidate in candidates {
+        if kept.iter().all(|k| k.rect.iou(&candidate.rect) <= NMS_IOU) {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+/// A face as reported for one frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Face {
+    /// Track id, kept while the face stays in view
+    pub id: usize,
+    #[serde(flatten)]
+    pub rect: Rect,
+    pub score: f64,
+}
+
+struct Track {
+    face: Face,
+    missed: usize,
+}
+
+/// Links detections from frame to frame, so a face keeps its id and stays covered for `hold`
+/// frames after the detector last saw it
+pub struct FaceTracker {
+    tracks: Vec<Track>,
+    next_id: usize,
+    hold: usize,
+}
+
+impl FaceTracker {
+    pub fn new(hold: usize) -> Self {
+        Self { tracks: Vec::new(), next_id: 1, hold }
+    }
+
+    /// The faces of the next frame
+    pub fn update(&mut self, detections: Vec<Detection>) -> Vec<Face> {
+        let mut unmatched: Vec<Option<Detection>> = detections.into_iter().map(Some).collect();
+        for track in &mut self.tracks {
+            let best = unmatched.iter().enumerate()
+                .filter_map(|(i, d)| d.map(|d| (i, track.face.rect.iou(&d.rect))))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            match best {
+                Some((i, iou)) if iou > 0.1 => {
+                    let detection = unmatched[i].take().expect("matched detection");
+                    track.face.rect = detection.rect;
+                    track.face.score = detection.score;
+                    track.missed = 0;
+                }
+                _ => track.missed += 1,
+            }
+        }
+        self.tracks.retain(|t| t.missed <= self.hold);
+
+        for detection in unmatched.into_iter().flatten() {
+            self.tracks.push(Track { face: Face { id: self.next_id, rect: detection.rect, score: detection.score }, missed: 0 });
+            self.next_id += 1;
+        }
+        self.tracks.iter().map(|t| t.face).collect()
+    }
+}
+
+/// Detections of a whole video, exported as `*.faces.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaceReport {
+    pub source: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub frames: Vec<FrameFaces>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameFaces {
+    pub frame: usize,
+    pub time: f64,
+    pub faces: Vec<Face>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tract_onnx::pb;
+
+    fn frame(width: usize, height: usize, rgb: impl Fn(usize, usize) -> [u8; 3]) -> Frame {
+        let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| rgb(x, y)).collect();
+        Frame { index: 0, time: 0.0, width, height, data }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    fn value(name: &str, dims: &[i64]) -> pb::ValueInfoProto {
+        use pb::tensor_shape_proto::{dimension::Value, Dimension};
+        let dim = dims.iter().map(|&d| Dimension { denotation: String::new(), value: Some(Value::DimValue(d)) }).collect();
+        let tensor = pb::type_proto::Tensor { elem_type: 1, shape: Some(pb::TensorShapeProto { dim }) };
+        let r#type = pb::TypeProto { denotation: String::new(), value: Some(pb::type_proto::Value::TensorType(tensor)) };
+        pb::ValueInfoProto { name: name.into(), r#type: Some(r#type), doc_string: String::new() }
+    }
+
+    fn constant(name: &str, dims: &[i64], float_data: Vec<f32>) -> pb::TensorProto {
+        pb::TensorProto { name: name.into(), dims: dims.to_vec(), data_type: 1, float_data, ..Default::default() }
+    }
+
+    fn node(op_type: &str, input: &[&str], output: &str, attribute: Vec<pb::AttributeProto>) -> pb::NodeProto {
+        let input = input.iter().map(|s| s.to_string()).collect();
+        pb::NodeProto { op_type: op_type.into(), input, output: vec![output.into()], name: output.into(), attribute, ..Default::default() }
+    }
+
+    /// Stand-in for UltraFace with a 32×24 input and three priors whose face score follows the
+    /// mean of the normalised input: a box, a weaker one overlapping it and one too small to count
+    fn model() -> InferenceModel {
+        use pb::attribute_proto::AttributeType;
+        let axes = pb::AttributeProto { name: "axes".into(), ints: vec![1, 2, 3], r#type: AttributeType::Ints as i32, ..Default::default() };
+        let keepdims = pb::AttributeProto { name: "keepdims".into(), i: 0, r#type: AttributeType::Int as i32, ..Default::default() };
+        let graph = pb::GraphProto {
+            name: "ultraface".into(),
+            node: vec![
+                node("ReduceMean", &["input"], "mean", vec![axes, keepdims]),
+                node("Mul", &["mean", "weight"], "weighted", vec![]),
+                node("Add", &["weighted", "background"], "scores", vec![]),
+                node("Identity", &["priors"], "boxes", vec![]),
+            ],
+            initializer: vec![
+                constant("weight", &[1, 3, 2], vec![0.0, 1.0, 0.0, 0.9, 0.0, 1.0]),
+                constant("background", &[1, 3, 2], vec![1.0, 0.0, 1.0, 0.0, 1.0, 0.0]),
+                constant("priors", &[1, 3, 4], vec![0.1, 0.1, 0.3, 0.5, 0.12, 0.12, 0.32, 0.52, 0.6, 0.6, 0.65, 0.62]),
+            ],
+            input: vec![value("input", &[1, 3, 24, 32])],
+            output: vec![value("scores", &[1, 3, 2]), value("boxes", &[1, 3, 4])],
+            ..Default::default()
+        };
+        let opset = pb::OperatorSetIdProto { domain: String::new(), version: 13 };
+        let proto = pb::ModelProto { ir_version: 7, opset_import: vec![opset], graph: Some(graph), ..Default::default() };
+        tract_onnx::onnx().model_for_proto_model(&proto).unwrap()
+    }
+
+    #[test]
+    fn input_is_the_stretched_and_normalised_frame() {
+        // Left half white, right half black
+        let tensor = input_tensor(&frame(64, 48, |x, _| if x < 32 { [255; 3] } else { [0; 3] }), (32, 24));
+        let input = tensor.to_array_view::<f32>().unwrap();
+        assert_eq!(input.shape(), [1, 3, 24, 32]);
+        assert_eq!(input[[0, 0, 5, 3]], 1.0);
+        assert!((input[[0, 2, 20, 30]] + 127.0 / 128.0).abs() < 1e-6);
+
+        // Averaged over the pixels each input pixel covers
+        let tensor = input_tensor(&frame(4, 2, |x, _| if x % 2 == 0 { [254; 3] } else { [0; 3] }), (2, 1));
+        let input = tensor.to_array_view::<f32>().unwrap();
+        assert!(input.iter().all(|&v| v.abs() < 1e-6));
+    }
+
+    #[test]
+    fn overlapping_detections_keep_the_strongest() {
+        let detections = vec![
+            Detection { rect: rect(0.0, 0.0, 10.0, 10.0), score: 0.8 },
+            Detection { rect: rect(1.0, 1.0, 10.0, 10.0), score: 0.9 },
+            Detection { rect: rect(50.0, 50.0, 10.0, 10.0), score: 0.75 },
+        ];
+        let kept = suppress(detections);
+        assert_eq!(kept.iter().map(|d| d.score).collect::<Vec<_>>(), [0.9, 0.75]);
+    }
+
+    #[test]
+    fn detections_come_from_the_model() {
+        let detector = FaceDetector::from_model(model(), 0.06, 0.7).unwrap();
+        assert_eq!(detector.input, (32, 24));
+
+        let faces = detector.detect(&frame(640, 480, |_, _| [255; 3])).unwrap();
+        assert_eq!(faces.len(), 1, "{:?}", faces);
+        assert!((faces[0].score - 1.0).abs() < 1e-4);
+        let expected = rect(64.0, 48.0, 128.0, 192.0);
+        for (got, want) in [(faces[0].rect.x, expected.x), (faces[0].rect.y, expected.y), (faces[0].rect.width, expected.width), (faces[0].rect.height, expected.height)] {
+            assert!((got - want).abs() < 1e-3, "{:?}", faces[0].rect);
+        }
+
+        assert!(detector.detect(&frame(640, 480, |_, _| [127; 3])).unwrap().is_empty());
+        assert!(detector.detect(&frame(640, 480, |_, _| [0; 3])).unwrap().is_empty());
+
+        // The small box counts once it clears the minimum size
+        let detector = FaceDetector::from_model(model(), 0.01, 0.7).unwrap();
+        assert_eq!(detector.detect(&frame(640, 480, |_, _| [255; 3])).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn model_path_prefers_the_configured_model() {
+        let config = ActionConfig { face_model_path: Some("/models/face.onnx".into()), ..Default::default() };
+        assert_eq!(model_path(&config).unwrap(), Path::new("/models/face.onnx"));
+        assert!(FaceDetector::load(Path::new("/nonexistent/face.onnx"), 0.06, 0.7).is_err());
+    }
+
+    #[test]
+    fn resource_dirs_cover_the_bundle_layouts() {
+        let mac = resource_dirs(Path::new("/Applications/VMP.app/Contents/MacOS"));
+        assert!(mac.contains(&PathBuf::from("/Applications/VMP.app/Contents/Resources")));
+        let deb = resource_dirs(Path::new("/usr/bin"));
+        assert!(deb.contains(&PathBuf::from("/usr/lib/video-matrix-pro-zwm")));
+        assert!(resource_dirs(Path::new("C:/VMP")).contains(&PathBuf::from("C:/VMP")));
+    }
+
+    #[test]
+    fn tracker_keeps_ids_and_holds_lost_faces() {
+        let mut tracker = FaceTracker::new(2);
+        let face = |x: f64| Detection { rect: rect(x, 10.0, 20.0, 20.0), score: 0.9 };
+        let first = tracker.update(vec![face(10.0), face(100.0)]);
+        assert_eq!(first.iter().map(|f| f.id).collect::<Vec<_>>(), [1, 2]);
+
+        // Moved a little: same ids
+        let moved = tracker.update(vec![face(104.0), face(12.0)]);
+        assert_eq!(moved.iter().map(|f| (f.id, f.rect.x)).collect::<Vec<_>>(), [(1, 12.0), (2, 104.0)]);
+
+        // Face 2 lost: still covered for two frames, then dropped
+        assert_eq!(tracker.update(vec![face(12.0)]).len(), 2);
+        assert_eq!(tracker.update(vec![face(12.0)]).len(), 2);
+        assert_eq!(tracker.update(vec![face(12.0)]).iter().map(|f| f.id).collect::<Vec<_>>(), [1]);
+
+        // A new face gets a new id
+        assert_eq!(tracker.update(vec![face(12.0), face(200.0)]).last().unwrap().id, 3);
+    }
+}
@@ -1,27 +1,244 @@
 use std::collections::HashMap;
-use std::sync::Arc;
-use crate::core::VideoAction;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
+use crate::core::{VideoAction, ActionConfig, ParamSpec};
 
+/// Group an action is listed under, in the UI and in the AI prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionCategory {
+    Basic,
+    Visual,
+    AiAb,
+    Audio,
+    Strong,
+    OpenCv,
+    Material,
+}
+
+impl ActionCategory {
+    pub const ALL: [ActionCategory; 7] = [
+        ActionCategory::Basic,
+        ActionCategory::Visual,
+        ActionCategory::AiAb,
+        ActionCategory::Audio,
+        ActionCategory::Strong,
+        ActionCategory::OpenCv,
+        ActionCategory::Material,
+    ];
+
+    /// Group heading shown in the UI
+    pub fn title(self) -> &'static str {
+        match self {
+            ActionCategory::Basic => "✂️ 基础编辑",
+            ActionCategory::Visual => "🎨 视觉增强",
+            ActionCategory::AiAb => "🤖 AI与AB模式",
+            ActionCategory::Audio => "🎵 音频与其他",
+            ActionCategory::Strong => "💪 强力去重",
+            ActionCategory::OpenCv => "👁️ OpenCV功能",
+            ActionCategory::Material => "✨ 新素材功能",
+        }
+    }
+
+    pub fn name_en(self) -> &'static str {
+        match self {
+            ActionCategory::Basic => "Basic",
+            ActionCategory::Visual => "Visual",
+            ActionCategory::AiAb => "AI/Effects",
+            ActionCategory::Audio => "Audio/Other",
+            ActionCategory::Strong => "Strong dedup",
+            ActionCategory::OpenCv => "OpenCV",
+            ActionCategory::Material => "Materials",
+        }
+    }
+
+    /// Listed in the "附加功能" tab instead of "全部功能"
+    pub fn is_additional(self) -> bool {
+        matches!(self, ActionCategory::Strong | ActionCategory::OpenCv | ActionCategory::Material)
+    }
+}
+
+/// Which parts of the file an action changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Touches {
+    pub video: bool,
+    pub audio: bool,
+    pub container: bool,
+}
+
+impl Touches {
+    pub const VIDEO: Touches = Touches { video: true, audio: false, container: false };
+    pub const AUDIO: Touches = Touches { video: false, audio: true, container: false };
+    pub const AV: Touches = Touches { video: true, audio: true, container: false };
+    pub const CONTAINER: Touches = Touches { video: false, audio: false, container: true };
+}
+
+/// User supplied material files, one per `*_path` field of `ActionConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Material {
+    Watermark,
+    Mask,
+    Sticker,
+    Border,
+    LightEffect,
+    Pip,
+    Goods,
+    MaskVideo,
+    AbVideo,
+    Track,
+    FaceModel,
+}
+
+impl Material {
+    pub const ALL: [Material; 11] = [
+        Material::Watermark,
+        Material::Mask,
+        Material::Sticker,
+        Material::Border,
+        Material::LightEffect,
+        Material::Pip,
+        Material::Goods,
+        Material::MaskVideo,
+        Material::AbVideo,
+        Material::Track,
+        Material::FaceModel,
+    ];
+
+    pub fn name_zh(self) -> &'static str {
+        match self {
+            Material::Watermark => "水印图片",
+            Material::Mask => "蒙版图片",
+            Material::Sticker => "贴纸图片",
+            Material::Border => "边框图片",
+            Material::LightEffect => "光效素材",
+            Material::Pip => "画中画视频",
+            Material::Goods => "带货模板",
+            Material::MaskVideo => "蒙版视频",
+            Material::AbVideo => "B视频",
+            Material::Track => "追踪数据",
+            Material::FaceModel => "人脸检测模型",
+        }
+    }
+
+    pub fn name_en(self) -> &'static str {
+        match self {
+            Material::Watermark => "watermark image",
+            Material::Mask => "mask image",
+            Material::Sticker => "sticker image",
+            Material::Border => "border image",
+            Material::LightEffect => "light effect clip",
+            Material::Pip => "picture-in-picture clip",
+            Material::Goods => "goods template",
+            Material::MaskVideo => "mask video",
+            Material::AbVideo => "B video (file or folder)",
+            Material::Track => "object track (*.track.json)",
+            Material::FaceModel => "face detection model (UltraFace *.onnx)",
+        }
+    }
+
+    /// The configured path for this material, if any
+    pub fn path(self, config: &ActionConfig) -> Option<&str> {
+        let path = match self {
+            Material::Watermark => &config.watermark_path,
+            Material::Mask => &config.mask_path,
+            Material::Sticker => &config.sticker_path,
+            Material::Border => &config.border_path,
+            Material::LightEffect => &config.light_effect_path,
+            Material::Pip => &config.pip_path,
+            Material::Goods => &config.goods_path,
+            Material::MaskVideo => &config.mask_video_path,
+            Material::AbVideo => &config.ab_video_path,
+            Material::Track => &config.track_path,
+            Material::FaceModel => &config.face_model_path,
+        };
+        path.as_deref().filter(|p| !p.is_empty())
+    }
+
+    pub fn set_path(self, config: &mut ActionConfig, path: String) {
+        let slot = match self {
+            Material::Watermark => &mut config.watermark_path,
+            Material::Mask => &mut config.mask_path,
+            Material::Sticker => &mut config.sticker_path,
+            Material::Border => &mut config.border_path,
+            Material::LightEffect => &mut config.light_effect_path,
+            Material::Pip => &mut config.pip_path,
+            Material::Goods => &mut config.goods_path,
+            Material::MaskVideo => &mut config.mask_video_path,
+            Material::AbVideo => &mut config.ab_video_path,
+            Material::Track => &mut config.track_path,
+            Material::FaceModel => &mut config.face_model_path,
+        };
+        *slot = Some(path);
+    }
+}
+
+/// Static description of an action, declared next to its implementation.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ActionMeta {
+    pub name_zh: &'static str,
+    pub name_en: &'static str,
+    pub category: ActionCategory,
+    pub touches: Touches,
+    /// Materials the action reads; without them it falls back to a built-in effect or a stream copy
+    pub materials: &'static [Material],
+}
+
+/// Registry of every available action, in the order they are listed in the UI.
 pub struct ActionFactory {
-    actions: HashMap<String, Arc<dyn VideoAction>>,
+    actions: Vec<Box<dyn VideoAction>>,
+    index: HashMap<&'static str, usize>,
 }
 
 impl ActionFactory {
     pub fn new() -> Self {
         Self {
-            actions: HashMap::new(),
+            actions: Vec::new(),
+            index: HashMap::new(),
         }
     }
 
+    /// The registry holding all actions from `crate::actions`
+    pub fn global() -> &'static ActionFactory {
+        static FACTORY: OnceLock<ActionFactory> = OnceLock::new();
+        FACTORY.get_or_init(|| {
+            let mut factory = ActionFactory::new();
+            crate::actions::register_all(&mut factory);
+            factory
+        })
+    }
+
     pub fn register(&mut self, action: impl VideoAction + 'static) {
-        self.actions.insert(action.id().to_string(), Arc::new(action));
+        let id = action.id();
+        match self.index.get(id) {
+            Some(&i) => self.actions[i] = Box::new(action),
+            None => {
+                self.index.insert(id, self.actions.len());
+                self.actions.push(Box::new(action));
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn VideoAction> {
+        self.index.get(id).map(|&i| self.actions[i].as_ref())
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &dyn VideoAction> {
+        self.actions.iter().map(|a| a.as_ref())
+    }
+
+    /// Look up a parameter schema by name across all actions
+    pub fn param(&self, name: &str) -> Option<&'static ParamSpec> {
+        self.actions().flat_map(|a| a.params()).find(|p| p.name == name)
     }
 
-    pub fn get(&self, id: &str) -> Option<Arc<dyn VideoAction>> {
-        self.actions.get(id).cloned()
+    pub fn by_category(&self, category: ActionCategory) -> impl Iterator<Item = &dyn VideoAction> {
+        self.actions().filter(move |a| a.meta().category == category)
     }
-    
-    pub fn list_actions(&self) -> Vec<String> {
-        self.actions.keys().cloned().collect()
+}
+
+impl Default for ActionFactory {
+    fn default() -> Self {
+        Self::new()
     }
 }
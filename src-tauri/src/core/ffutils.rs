@@ -1,10 +1,23 @@
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::cell::Cell;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 use anyhow::{Result, anyhow};
 use std::env;
+use std::fs;
+use crate::core::cancel::{self, Cancelled};
+use crate::core::probe;
+use crate::core::progress::{self, FfmpegProgress};
 
 pub struct FFUtils;
 
+thread_local! {
+    static THREADS: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
 impl FFUtils {
     /// Get the path to bundled FFmpeg executable
     pub fn get_ffmpeg_path() -> PathBuf {
@@ -46,26 +59,122 @@ impl FFUtils {
         PathBuf::from("ffprobe")
     }
 
-    /// Run an FFmpeg command
+    /// Run `f` with every ffmpeg it starts on this thread limited to `threads` encoder and
+    /// filter threads
+    pub fn with_threads<T>(threads: usize, f: impl FnOnce() -> T) -> T {
+        let previous = THREADS.with(|t| t.replace(Some(threads)));
+        let result = f();
+        THREADS.with(|t| t.set(previous));
+        result
+    }
+
+    /// Thread limit set by `with_threads` for this thread, if any
+    pub(crate) fn threads() -> Option<usize> {
+        THREADS.with(|t| t.get())
+    }
+
+    /// Run an FFmpeg command.
+    /// If the job running on this thread is cancelled (see `CancelToken::scope`), ffmpeg is killed
+    /// and its half-written output (the last argument) is removed.
     pub fn run(args: &[&str]) -> Result<()> {
-        let mut final_args = vec!["-y"];
+        Self::run_scaled(args, 1.0)
+    }
+
+    /// Run an FFmpeg command whose output lasts `time_scale` times as long as its input.
+    /// Inside `progress::scope`, the position read from `-progress pipe:1` is reported against
+    /// that expected length.
+    pub fn run_scaled(args: &[&str], time_scale: f64) -> Result<()> {
+        let threads = Self::threads().map(|n| n.to_string());
+        let mut final_args = vec!["-y", "-progress", "pipe:1", "-nostats"];
+        if let Some(n) = &threads {
+            final_args.extend(["-filter_threads", n.as_str(), "-filter_complex_threads", n.as_str()]);
+        }
         final_args.extend_from_slice(args);
+        // `-threads` right before the output file limits the encoder
+        if let Some(n) = &threads {
+            let output = final_args.len() - 1;
+            final_args.splice(output..output, ["-threads", n.as_str()]);
+        }
         
         let ffmpeg_path = Self::get_ffmpeg_path();
+        let duration = if progress::is_active() { Self::expected_duration(args, time_scale) } else { None };
         
-        let output = Command::new(&ffmpeg_path)
+        cancel::check()?;
+        let mut child = Command::new(&ffmpeg_path)
             .args(&final_args)
-            .output()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
             .map_err(|e| anyhow!("Failed to execute ffmpeg at {:?}: {}", ffmpeg_path, e))?;
 
-        if output.status.success() {
+        // Drain stderr on its own thread so ffmpeg never blocks on a full pipe
+        let mut stderr = child.stderr.take().ok_or_else(|| anyhow!("Failed to capture ffmpeg output"))?;
+        let reader = thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        });
+
+        // The progress blocks on stdout are parsed on another thread and reported from this one,
+        // where the progress scope lives
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("Failed to capture ffmpeg progress"))?;
+        let (tx, rx) = mpsc::channel();
+        let progress_reader = thread::spawn(move || {
+            let mut current = FfmpegProgress { duration, ..Default::default() };
+            for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+                if current.update(&line) {
+                    let _ = tx.send(current);
+                }
+            }
+        });
+
+        let status = loop {
+            rx.try_iter().for_each(progress::report);
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if cancel::is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                Self::remove_output(args);
+                return Err(Cancelled.into());
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+
+        let _ = progress_reader.join();
+        rx.try_iter().for_each(progress::report);
+        let stderr = reader.join().unwrap_or_default();
+        if status.success() {
             Ok(())
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
             Err(anyhow!("FFmpeg failed: {}", stderr))
         }
     }
 
+    /// Length of the output of an ffmpeg call: the first input's duration scaled by `time_scale`,
+    /// capped by `-t`
+    fn expected_duration(args: &[&str], time_scale: f64) -> Option<f64> {
+        let value_of = |flag: &str| args.windows(2).find(|w| w[0] == flag).map(|w| w[1]);
+        let input = Self::get_duration(Path::new(value_of("-i")?)).ok()? * time_scale;
+        match value_of("-t").and_then(|t| t.parse::<f64>().ok()) {
+            Some(limit) => Some(input.min(limit)),
+            None => Some(input),
+        }
+    }
+
+    /// Delete the output file of an interrupted ffmpeg call
+    fn remove_output(args: &[&str]) {
+        let is_input = args.len() >= 2 && args[args.len() - 2] == "-i";
+        if let (Some(output), false) = (args.last(), is_input) {
+            let path = Path::new(output);
+            if path.is_file() {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
     /// Helper to generate output path with suffix
     pub fn get_dst(src: &Path, out_dir: &Path, suffix: &str) -> Result<std::path::PathBuf> {
         let file_stem = src.file_stem()
@@ -80,26 +189,31 @@ impl FFUtils {
         Ok(out_dir.join(format!("{}_{}.{}", file_stem, suffix, ext)))
     }
 
-    /// Get video duration using ffprobe
+    /// Get video duration using ffprobe (cached, see `probe::probe`)
     pub fn get_duration(src: &Path) -> Result<f64> {
+        probe::probe(src)?.duration.ok_or_else(|| anyhow!("Failed to read duration of {}", src.display()))
+    }
+
+    /// Full ffprobe report (format and streams) as JSON. Prefer the typed and cached `probe::probe`.
+    pub fn probe(src: &Path) -> Result<serde_json::Value> {
         let ffprobe_path = Self::get_ffprobe_path();
         
         let output = Command::new(&ffprobe_path)
-            .args(&[
+            .args([
                 "-v", "error",
-                "-show_entries", "format=duration",
-                "-of", "default=noprint_wrappers=1:nokey=1",
-                src.to_str().unwrap()
+                "-show_format",
+                "-show_streams",
+                "-of", "json",
             ])
+            .arg(src)
             .output()
             .map_err(|e| anyhow!("Failed to execute ffprobe at {:?}: {}", ffprobe_path, e))?;
 
         if !output.status.success() {
-            return Err(anyhow!("ffprobe failed"));
+            return Err(anyhow!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
         }
 
-        let output_str = String::from_utf8(output.stdout)?;
-        output_str.trim().parse::<f64>().map_err(|e| anyhow!("Failed to parse duration: {}", e))
+        Ok(serde_json::from_slice(&output.stdout)?)
     }
 
     /// Escape path for use in FFmpeg filter graph
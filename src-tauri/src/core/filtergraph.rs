@@ -0,0 +1,334 @@
+use std::path::Path;
+use std::time::Instant;
+use anyhow::{Result, anyhow};
+use crate::core::{VideoAction, ActionConfig, ActionOutput, FFUtils};
+use crate::core::probe::probe;
+use crate::core::scenes;
+
+/// One filter stage contributed by an action, for either the video or the audio stream.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    /// A linear filter chain, e.g. `crop=iw*0.9:ih*0.9,hflip`
+    Chain(String),
+    /// A small graph that reads its input from `[in]` and leaves its last output pad unlabelled,
+    /// the same convention `-vf` uses. Any other labels are private to the graph.
+    Graph(String),
+}
+
+/// The part of an action that can be merged into a shared `-filter_complex` graph.
+#[derive(Debug, Clone, Default)]
+pub struct FilterFragment {
+    pub video: Option<Filter>,
+    pub audio: Option<Filter>,
+    /// Remove the audio stream from the output (mute, frame pulling)
+    pub drop_audio: bool,
+    /// Give the output a silent audio track when the source has none; later audio stages
+    /// then apply to it
+    pub silent_audio: bool,
+    /// Extra output options such as `-r 60` or `-b:v 15M`
+    pub output_args: Vec<String>,
+    /// Factor applied to the stream duration (e.g. 1/speed), used by later duration-dependent stages
+    pub time_scale: Option<f64>,
+    /// Randomly chosen values as `name=value`
+    pub choices: Vec<String>,
+}
+
+impl FilterFragment {
+    pub fn video(filter: Filter) -> Self {
+        Self { video: Some(filter), ..Default::default() }
+    }
+
+    pub fn audio(filter: Filter) -> Self {
+        Self { audio: Some(filter), ..Default::default() }
+    }
+
+    pub fn output_args(args: &[&str]) -> Self {
+        Self { output_args: args.iter().map(|s| s.to_string()).collect(), ..Default::default() }
+    }
+
+    /// Record a randomly chosen value
+    pub fn choice(mut self, name: &str, value: impl std::fmt::Display) -> Self {
+        self.choices.push(format!("{}={}", name, value));
+        self
+    }
+}
+
+/// What a filter stage needs to know about the stream it will be applied to.
+pub struct FilterContext<'a> {
+    pub src: &'a Path,
+    /// Accumulated duration factor of the stages merged before this one
+    pub time_scale: f64,
+}
+
+impl<'a> FilterContext<'a> {
+    pub fn new(src: &'a Path) -> Self {
+        Self { src, time_scale: 1.0 }
+    }
+
+    /// Duration of the stream as this stage will see it
+    pub fn duration(&self) -> Result<f64> {
+        Ok(FFUtils::get_duration(self.src)? * self.time_scale)
+    }
+
+    /// Whether the stream has audio to filter. Assumed when the file cannot be probed.
+    pub fn has_audio(&self) -> bool {
+        probe(self.src).map(|info| info.has_audio()).unwrap_or(true)
+    }
+}
+
+/// Merges fragments from several actions into one ffmpeg invocation.
+#[derive(Debug, Default)]
+pub struct FilterGraph {
+    fragments: Vec<FilterFragment>,
+}
+
+impl FilterGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, fragment: FilterFragment) {
+        self.fragments.push(fragment);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fragments.is_empty()
+    }
+
+    /// Product of the time scales of all merged fragments
+    pub fn time_scale(&self) -> f64 {
+        self.fragments.iter().filter_map(|f| f.time_scale).product()
+    }
+
+    /// Build the full ffmpeg argument list (without the leading `-y`).
+    /// Audio stages are left out when the source has no audio stream, unless a fragment asked for
+    /// a silent track before them.
+    pub fn args(&self, src: &Path, dst: &Path) -> Result<Vec<String>> {
+        let has_audio = FilterContext::new(src).has_audio();
+        let src = src.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+        let dst = dst.to_str().ok_or_else(|| anyhow!("Invalid output path encoding"))?;
+        Ok(self.build_args(src, dst, has_audio))
+    }
+
+    fn build_args(&self, src: &str, dst: &str, has_audio: bool) -> Vec<String> {
+        let mut graph: Vec<String> = Vec::new();
+        let mut video_label: Option<String> = None;
+        let mut audio_label: Option<String> = None;
+        let mut audio_dropped = false;
+        let mut audio_missing = !has_audio;
+        let mut silence_added = false;
+        let mut output_args: Vec<String> = Vec::new();
+
+        for (i, fragment) in self.fragments.iter().enumerate() {
+            if let Some(filter) = &fragment.video {
+                let input = video_label.clone().unwrap_or_else(|| "0:v:0".to_string());
+                let output = format!("v{}", i);
+                graph.push(Self::stage(filter, &input, &output, i));
+                video_label = Some(output);
+            }
+
+            if fragment.drop_audio {
+                audio_dropped = true;
+                audio_label = None;
+            } else if fragment.silent_audio && audio_missing && !audio_dropped {
+                // Endless silence, cut to the video by `-shortest`
+                let output = format!("a{}", i);
+                graph.push(format!("anullsrc=channel_layout=stereo:sample_rate=44100[{}]", output));
+                audio_label = Some(output);
+                audio_missing = false;
+                silence_added = true;
+            } else if let (Some(filter), false, false) = (&fragment.audio, audio_dropped, audio_missing) {
+                let input = audio_label.clone().unwrap_or_else(|| "0:a:0".to_string());
+                let output = format!("a{}", i);
+                graph.push(Self::stage(filter, &input, &output, i));
+                audio_label = Some(output);
+            }
+
+            output_args.extend(fragment.output_args.iter().cloned());
+        }
+
+        let mut args: Vec<String> = vec!["-i".into(), src.into()];
+
+        if !graph.is_empty() {
+            args.push("-filter_complex".into());
+            args.push(graph.join(";"));
+        }
+
+        match &video_label {
+            Some(label) => args.extend(["-map".into(), format!("[{}]", label)]),
+            None => args.extend(["-map".into(), "0:v:0".into()]),
+        }
+        if video_label.is_none() && output_args.is_empty() {
+            args.extend(["-c:v".into(), "copy".into()]);
+        }
+
+        if !audio_dropped {
+            match &audio_label {
+                Some(label) => args.extend(["-map".into(), format!("[{}]", label)]),
+                None => args.extend(["-map".into(), "0:a?".into(), "-c:a".into(), "copy".into()]),
+            }
+        }
+        if silence_added && !audio_dropped {
+            args.push("-shortest".into());
+        }
+
+        args.extend(output_args);
+        args.extend(["-loglevel".into(), "error".into(), dst.into()]);
+        args
+    }
+
+    /// Run the merged graph as a single ffmpeg pass
+    pub fn run(&self, src: &Path, dst: &Path) -> Result<()> {
+        let args = self.args(src, dst)?;
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        FFUtils::run_scaled(&args, self.time_scale())
+    }
+
+    /// Run a single action through its filter fragment (used by the actions' own `execute`),
+    /// scene by scene when the config asks for it and the action supports it
+    pub fn apply(action: &dyn VideoAction, src: &Path, dst: &Path, config: &ActionConfig) -> Result<ActionOutput> {
+        let started = Instant::now();
+        if let (Some(options), true) = (&config.scenes, action.per_scene()) {
+            let choices = scenes::render(&[action], src, dst, config, options, &|_| {})?;
+            let mut output = ActionOutput::new(dst.to_path_buf(), started.elapsed());
+            output.choices = choices;
+            return Ok(output);
+        }
+        let fragment = action.filter(&FilterContext::new(src), config)?
+            .ok_or_else(|| anyhow!("Action {} has no filter fragment", action.id()))?;
+        let choices = fragment.choices.iter().map(|c| format!("{}: {}", action.id(), c)).collect();
+        FilterGraph::from(fragment).run(src, dst)?;
+
+        let mut output = ActionOutput::new(dst.to_path_buf(), started.elapsed());
+        output.choices = choices;
+        Ok(output)
+    }
+
+    /// Render one stage with the given input/output pads, namespacing graph-internal labels
+    fn stage(filter: &Filter, input: &str, output: &str, index: usize) -> String {
+        match filter {
+            Filter::Chain(chain) => format!("[{}]{}[{}]", input, chain, output),
+            Filter::Graph(graph) => {
+                let body = Self::relabel(graph, |label| {
+                    if label == "in" {
+                        input.to_string()
+                    } else {
+                        format!("s{}_{}", index, label)
+                    }
+                });
+                format!("{}[{}]", body, output)
+            }
+        }
+    }
+
+    /// Rewrite every `[label]` outside of quoted arguments
+    fn relabel(graph: &str, map: impl Fn(&str) -> String) -> String {
+        let mut out = String::with_capacity(graph.len() + 32);
+        let mut chars = graph.chars();
+        let mut in_quotes = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    out.push(c);
+                    if let Some(next) = chars.next() {
+                        out.push(next);
+                    }
+                }
+                '\'' => {
+                    in_quotes = !in_quotes;
+                    out.push(c);
+                }
+                '[' if !in_quotes => {
+                    let mut label = String::new();
+                    for l in chars.by_ref() {
+                        if l == ']' {
+                            break;
+                        }
+                        label.push(l);
+                    }
+                    out.push('[');
+                    out.push_str(&map(&label));
+                    out.push(']');
+                }
+                _ => out.push(c),
+            }
+        }
+
+        out
+    }
+}
+
+impl From<FilterFragment> for FilterGraph {
+    fn from(fragment: FilterFragment) -> Self {
+        Self { fragments: vec![fragment] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(fragments: Vec<FilterFragment>) -> FilterGraph {
+        FilterGraph { fragments }
+    }
+
+    fn args(graph: &FilterGraph, has_audio: bool) -> String {
+        graph.build_args("in.mp4", "out.mp4", has_audio).join(" ")
+    }
+
+    #[test]
+    fn chains_are_linked_and_audio_copied_when_untouched() {
+        let g = graph(vec![
+            FilterFragment::video(Filter::Chain("hflip".into())),
+            FilterFragment::video(Filter::Chain("crop=iw/2:ih".into())),
+        ]);
+        assert_eq!(args(&g, true), "-i in.mp4 -filter_complex [0:v:0]hflip[v0];[v0]crop=iw/2:ih[v1] -map [v1] -map 0:a? -c:a copy -loglevel error out.mp4");
+    }
+
+    #[test]
+    fn audio_stages_are_skipped_without_audio() {
+        let g = graph(vec![FilterFragment::audio(Filter::Chain("volume=2".into()))]);
+        assert_eq!(args(&g, true), "-i in.mp4 -filter_complex [0:a:0]volume=2[a0] -map 0:v:0 -c:v copy -map [a0] -loglevel error out.mp4");
+        assert_eq!(args(&g, false), "-i in.mp4 -map 0:v:0 -c:v copy -map 0:a? -c:a copy -loglevel error out.mp4");
+    }
+
+    #[test]
+    fn silent_track_feeds_later_audio_stages() {
+        let g = graph(vec![
+            FilterFragment { silent_audio: true, ..Default::default() },
+            FilterFragment::audio(Filter::Chain("volume=2".into())),
+        ]);
+        let with_audio = args(&g, true);
+        assert!(!with_audio.contains("anullsrc") && with_audio.contains("[0:a:0]volume=2[a1]"));
+        assert_eq!(args(&g, false), "-i in.mp4 -filter_complex anullsrc=channel_layout=stereo:sample_rate=44100[a0];[a0]volume=2[a1] -map 0:v:0 -c:v copy -map [a1] -shortest -loglevel error out.mp4");
+    }
+
+    #[test]
+    fn dropped_audio_stays_dropped() {
+        let g = graph(vec![
+            FilterFragment { drop_audio: true, ..Default::default() },
+            FilterFragment::audio(Filter::Chain("volume=2".into())),
+            FilterFragment { silent_audio: true, ..Default::default() },
+        ]);
+        assert_eq!(args(&g, true), "-i in.mp4 -map 0:v:0 -c:v copy -loglevel error out.mp4");
+    }
+
+    #[test]
+    fn graph_labels_are_namespaced_and_output_args_kept() {
+        let g = graph(vec![
+            FilterFragment::video(Filter::Chain("hflip".into())),
+            FilterFragment {
+                video: Some(Filter::Graph("[in]split[a][b];[a][b]overlay=x='if(gt(t,1),[x],0)'".into())),
+                output_args: vec!["-r".into(), "60".into()],
+                time_scale: Some(0.5),
+                ..Default::default()
+            },
+            FilterFragment { time_scale: Some(0.5), ..Default::default() },
+        ]);
+        assert_eq!(g.time_scale(), 0.25);
+        let args = args(&g, false);
+        assert!(args.contains("[v0]split[s1_a][s1_b];[s1_a][s1_b]overlay=x='if(gt(t,1),[x],0)'[v1]"), "{}", args);
+        assert!(args.ends_with("-map [v1] -map 0:a? -c:a copy -r 60 -loglevel error out.mp4"), "{}", args);
+    }
+}
@@ -0,0 +1,212 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow, bail};
+use uuid::Uuid;
+use crate::core::FFUtils;
+
+/// Seconds of audio fingerprinted from the start of a file, as chromaprint does
+pub const MAX_SECONDS: f64 = 120.0;
+
+/// Score from which the two soundtracks would still be matched by a fingerprint service:
+/// a bit error rate below 0.35
+pub const MATCH_THRESHOLD: f64 = 0.65;
+
+/// Sample rate the audio is decoded at; the bands below stay under its Nyquist frequency
+const SAMPLE_RATE: usize = 5512;
+const FRAME: usize = 2048;
+const HOP: usize = 256;
+/// 33 bands give 32 energy differences, one bit each
+const BANDS: usize = 33;
+const MIN_FREQ: f64 = 300.0;
+const MAX_FREQ: f64 = 2000.0;
+/// Shifts tried when aligning two fingerprints, about ±10 s, for trimmed heads
+const MAX_OFFSET: usize = 10 * SAMPLE_RATE / HOP;
+/// Fewest overlapping frames (~2 s) an alignment is scored on
+const MIN_OVERLAP: usize = 2 * SAMPLE_RATE / HOP;
+
+/// Fingerprint of the start of a soundtrack: one 32-bit word per frame, in the style of
+/// Haitsma and Kalker. Bit `m` tells whether the energy difference between bands `m` and `m + 1`
+/// grew since the previous frame, which survives re-encoding and small volume changes but not
+/// pitch shifts or tempo changes.
+pub fn fingerprint(path: &Path) -> Result<Vec<u32>> {
+    let samples = decode(path)?;
+    if samples.len() < FRAME {
+        bail!("音频过短，无法计算指纹: {}", path.display());
+    }
+    Ok(words(&samples))
+}
+
+/// Fingerprint words of mono samples at `SAMPLE_RATE`, at least `FRAME` of them
+fn words(samples: &[f64]) -> Vec<u32> {
+    let window: Vec<f64> = (0..FRAME).map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / FRAME as f64).cos()).collect();
+    let edges: Vec<usize> = (0..=BANDS)
+        .map(|b| MIN_FREQ * (MAX_FREQ / MIN_FREQ).powf(b as f64 / BANDS as f64))
+        .map(|f| (f * FRAME as f64 / SAMPLE_RATE as f64).round() as usize)
+        .collect();
+
+    let mut words = Vec::new();
+    let mut previous: Option<[f64; BANDS]> = None;
+    let (mut re, mut im) = (vec![0.0; FRAME], vec![0.0; FRAME]);
+    for start in (0..=samples.len() - FRAME).step_by(HOP) {
+        for i in 0..FRAME {
+            re[i] = samples[start + i] * window[i];
+            im[i] = 0.0;
+        }
+        fft(&mut re, &mut im);
+
+        let energy: [f64; BANDS] = std::array::from_fn(|b| {
+            (edges[b]..edges[b + 1].max(edges[b] + 1)).map(|k| re[k] * re[k] + im[k] * im[k]).sum()
+        });
+        if let Some(prev) = previous {
+            words.push((0..BANDS - 1).fold(0u32, |word, m| {
+                let delta = (energy[m] - energy[m + 1]) - (prev[m] - prev[m + 1]);
+                word << 1 | (delta > 0.0) as u32
+            }));
+        }
+        previous = Some(energy);
+    }
+    words
+}
+
+/// Share of equal bits at the best alignment of the two fingerprints; about 0.5 for unrelated
+/// audio and 1.0 for the same soundtrack
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    // Short clips are scored on all they have
+    let needed = MIN_OVERLAP.min(a.len()).min(b.len()).max(1);
+    let score = |offset: isize| -> Option<f64> {
+        let (a, b) = if offset >= 0 { (a.get(offset as usize..)?, b) } else { (a, b.get(offset.unsigned_abs()..)?) };
+        let overlap = a.len().min(b.len());
+        if overlap < needed {
+            return None;
+        }
+        let errors: u32 = a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum();
+        Some(1.0 - errors as f64 / (overlap * 32) as f64)
+    };
+    let max = MAX_OFFSET as isize;
+    (-max..=max).filter_map(score).fold(0.0, f64::max)
+}
+
+/// Compare the soundtracks of two files
+pub fn compare(source: &Path, output: &Path) -> Result<f64> {
+    Ok(similarity(&fingerprint(source)?, &fingerprint(output)?))
+}
+
+/// Mono PCM of the first `MAX_SECONDS`, scaled to -1..1
+fn decode(path: &Path) -> Result<Vec<f64>> {
+    let src = path.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+    let raw = std::env::temp_dir().join(format!("vmp_audio_{}.pcm", Uuid::new_v4().simple()));
+    let raw_str = raw.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+
+    let result = FFUtils::run(&[
+        "-t", &MAX_SECONDS.to_string(),
+        "-i", src,
+        "-vn", "-ac", "1", "-ar", &SAMPLE_RATE.to_string(),
+        "-f", "s16le",
+        "-loglevel", "error",
+        raw_str,
+    ]).and_then(|_| Ok(fs::read(&raw)?));
+    let _ = fs::remove_file(&raw);
+
+    Ok(result?.chunks_exact(2).map(|s| i16::from_le_bytes([s[0], s[1]]) as f64 / 32768.0).collect())
+}
+
+/// In-place radix-2 FFT; the length must be a power of two
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    /// Seconds of noise shaped by a slowly moving tone, so every band changes over time
+    fn signal(seconds: f64, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..(seconds * SAMPLE_RATE as f64) as usize)
+            .map(|i| {
+                let t = i as f64 / SAMPLE_RATE as f64;
+                let tone = (2.0 * PI * (400.0 + 300.0 * (t * 0.7).sin()) * t).sin();
+                0.5 * tone + 0.2 * rng.gen_range(-1.0..1.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fft_of_impulse_and_cosine() {
+        let (mut re, mut im) = (vec![0.0; 8], vec![0.0; 8]);
+        re[0] = 1.0;
+        fft(&mut re, &mut im);
+        assert!(re.iter().all(|&r| (r - 1.0).abs() < 1e-12) && im.iter().all(|&i| i.abs() < 1e-12));
+
+        let n = 64;
+        let mut re: Vec<f64> = (0..n).map(|i| (2.0 * PI * 5.0 * i as f64 / n as f64).cos()).collect();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+        let magnitude = |k: usize| (re[k] * re[k] + im[k] * im[k]).sqrt();
+        assert!((magnitude(5) - n as f64 / 2.0).abs() < 1e-9);
+        assert!((magnitude(n - 5) - n as f64 / 2.0).abs() < 1e-9);
+        assert!(magnitude(4) < 1e-9);
+    }
+
+    #[test]
+    fn words_ignore_volume() {
+        let loud = signal(3.0, 1);
+        let quiet: Vec<f64> = loud.iter().map(|s| s * 0.3).collect();
+        assert_eq!(words(&loud), words(&quiet));
+        assert_eq!(words(&loud).len(), (loud.len() - FRAME) / HOP);
+    }
+
+    #[test]
+    fn similarity_finds_a_trimmed_head() {
+        let full = words(&signal(20.0, 2));
+        assert_eq!(similarity(&full, &full), 1.0);
+        // The first 3 s cut off
+        let trimmed = &full[3 * SAMPLE_RATE / HOP..];
+        assert_eq!(similarity(&full, trimmed), 1.0);
+        assert_eq!(similarity(trimmed, &full), 1.0);
+    }
+
+    #[test]
+    fn unrelated_audio_scores_about_half() {
+        let a = words(&signal(20.0, 3));
+        let mut rng = StdRng::seed_from_u64(4);
+        let b: Vec<u32> = (0..a.len()).map(|_| rng.gen()).collect();
+        let score = similarity(&a, &b);
+        assert!(score < MATCH_THRESHOLD, "{}", score);
+        assert!(score > 0.45, "{}", score);
+    }
+}
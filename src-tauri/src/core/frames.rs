@@ -0,0 +1,552 @@
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use crate::core::{cancel, FFUtils};
+use crate::core::probe::probe;
+use crate::core::progress::{self, FfmpegProgress};
+
+/// Frames between two progress reports
+const REPORT_EVERY: usize = 10;
+
+/// One decoded frame, packed RGB24
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Counted from 0
+    pub index: usize,
+    /// Seconds from the start of the video
+    pub time: f64,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+/// Box in frame pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    /// Grown by `margin` times its size on every side
+    pub fn expand(&self, margin: f64) -> Rect {
+        let (dx, dy) = (self.width * margin, self.height * margin);
+        Rect { x: self.x - dx, y: self.y - dy, width: self.width + 2.0 * dx, height: self.height + 2.0 * dy }
+    }
+
+    /// Intersection over union, 0 for disjoint boxes
+    pub fn iou(&self, other: &Rect) -> f64 {
+        let w = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+        let h = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+        if w <= 0.0 || h <= 0.0 {
+            return 0.0;
+        }
+        let intersection = w * h;
+        intersection / (self.width * self.height + other.width * other.height - intersection)
+    }
+
+    /// Scaled by `sx` horizontally and `sy` vertically, e.g. from shares of the frame to pixels
+    pub fn scale(&self, sx: f64, sy: f64) -> Rect {
+        Rect { x: self.x * sx, y: self.y * sy, width: self.width * sx, height: self.height * sy }
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    /// Pixel bounds `(x0, y0, x1, y1)` clipped to a `width`×`height` frame; None when nothing is left
+    pub fn bounds(&self, width: usize, height: usize) -> Option<(usize, usize, usize, usize)> {
+        let clip = |v: f64, max: usize| v.round().clamp(0.0, max as f64) as usize;
+        let (x0, x1) = (clip(self.x, width), clip(self.x + self.width, width));
+        let (y0, y1) = (clip(self.y, height), clip(self.y + self.height, height));
+        (x0 < x1 && y0 < y1).then_some((x0, y0, x1, y1))
+    }
+}
+
+impl Frame {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * self.width + x) * 3;
+        [self.data[i], self.data[i + 1], self.data[i + 2]]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
+        let i = (y * self.width + x) * 3;
+        self.data[i..i + 3].copy_from_slice(&rgb);
+    }
+
+    /// Blur the inside of `rect`; three box blur passes come close to a gaussian
+    pub fn blur(&mut self, rect: &Rect, radius: usize) {
+        let Some((x0, y0, x1, y1)) = rect.bounds(self.width, self.height) else { return };
+        let (w, h) = (x1 - x0, y1 - y0);
+        let mut region: Vec<[f32; 3]> = (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel(x, y).map(f32::from))
+            .collect();
+        for _ in 0..3 {
+            box_pass(&mut region, h, w, radius, |line, i| line * w + i);
+            box_pass(&mut region, w, h, radius, |line, i| i * w + line);
+        }
+        for (i, rgb) in region.iter().enumerate() {
+            self.set_pixel(x0 + i % w, y0 + i / w, rgb.map(|c| c.round() as u8));
+        }
+    }
+
+    /// Fill `rect` with `block`×`block` squares of their average colour
+    pub fn pixelate(&mut self, rect: &Rect, block: usize) {
+        let Some((x0, y0, x1, y1)) = rect.bounds(self.width, self.height) else { return };
+        let block = block.max(1);
+        for by in (y0..y1).step_by(block) {
+            for bx in (x0..x1).step_by(block) {
+                let cells: Vec<(usize, usize)> = (by..(by + block).min(y1))
+                    .flat_map(|y| (bx..(bx + block).min(x1)).map(move |x| (x, y)))
+                    .collect();
+                let mut sum = [0u32; 3];
+                for &(x, y) in &cells {
+                    let rgb = self.pixel(x, y);
+                    (0..3).for_each(|c| sum[c] += rgb[c] as u32);
+                }
+                let average = sum.map(|s| (s / cells.len() as u32) as u8);
+                for &(x, y) in &cells {
+                    self.set_pixel(x, y, average);
+                }
+            }
+        }
+    }
+
+    /// Outline `rect` with lines `thickness` pixels wide
+    pub fn draw_rect(&mut self, rect: &Rect, rgb: [u8; 3], thickness: usize) {
+        let Some((x0, y0, x1, y1)) = rect.bounds(self.width, self.height) else { return };
+        let t = thickness.max(1);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if x < x0 + t || x + t >= x1 || y < y0 + t || y + t >= y1 {
+                    self.set_pixel(x, y, rgb);
+                }
+            }
+        }
+    }
+}
+
+/// RGBA picture laid over frames, such as a sticker
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// Decode the first frame of `path` scaled to `width`×`height`
+    pub fn load(path: &Path, width: usize, height: usize) -> Result<Image> {
+        let path_str = path.to_str().ok_or_else(|| anyhow!("Invalid image path encoding"))?;
+        let scale = format!("scale={}:{}", width.max(1), height.max(1));
+        let output = Command::new(FFUtils::get_ffmpeg_path())
+            .args(["-loglevel", "error", "-i", path_str, "-frames:v", "1", "-vf", &scale, "-f", "rawvideo", "-pix_fmt", "rgba", "pipe:1"])
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| anyhow!("Failed to execute ffmpeg: {}", e))?;
+        let size = width.max(1) * height.max(1) * 4;
+        if !output.status.success() || output.stdout.len() < size {
+            bail!("无法读取图片 {}: {}", path.display(), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(Image { width: width.max(1), height: height.max(1), data: output.stdout[..size].to_vec() })
+    }
+}
+
+impl Frame {
+    /// Blend `image` over the frame with its top left corner at (`x`, `y`); parts outside are cut off
+    pub fn draw_image(&mut self, image: &Image, x: isize, y: isize) {
+        for iy in 0..image.height {
+            for ix in 0..image.width {
+                let (fx, fy) = (x + ix as isize, y + iy as isize);
+                if fx < 0 || fy < 0 || fx >= self.width as isize || fy >= self.height as isize {
+                    continue;
+                }
+                let i = (iy * image.width + ix) * 4;
+                let alpha = image.data[i + 3] as u32;
+                if alpha == 0 {
+                    continue;
+                }
+                let under = self.pixel(fx as usize, fy as usize);
+                let over: [u8; 3] = std::array::from_fn(|c| {
+                    ((image.data[i + c] as u32 * alpha + under[c] as u32 * (255 - alpha)) / 255) as u8
+                });
+                self.set_pixel(fx as usize, fy as usize, over);
+            }
+        }
+    }
+
+    /// Scale the inside of `rect` up to fill the frame, with bilinear sampling
+    pub fn zoom_to(&mut self, rect: &Rect) {
+        if rect.width <= 0.0 || rect.height <= 0.0 {
+            return;
+        }
+        let source = self.clone();
+        let (sx, sy) = (rect.width / self.width as f64, rect.height / self.height as f64);
+        for y in 0..self.height {
+            let fy = (rect.y + (y as f64 + 0.5) * sy - 0.5).clamp(0.0, (self.height - 1) as f64);
+            let (y0, ty) = (fy.floor() as usize, fy.fract());
+            let y1 = (y0 + 1).min(self.height - 1);
+            for x in 0..self.width {
+                let fx = (rect.x + (x as f64 + 0.5) * sx - 0.5).clamp(0.0, (self.width - 1) as f64);
+                let (x0, tx) = (fx.floor() as usize, fx.fract());
+                let x1 = (x0 + 1).min(self.width - 1);
+                let (a, b, c, d) = (source.pixel(x0, y0), source.pixel(x1, y0), source.pixel(x0, y1), source.pixel(x1, y1));
+                let rgb = std::array::from_fn(|k| {
+                    let top = a[k] as f64 * (1.0 - tx) + b[k] as f64 * tx;
+                    let bottom = c[k] as f64 * (1.0 - tx) + d[k] as f64 * tx;
+                    (top * (1.0 - ty) + bottom * ty).round() as u8
+                });
+                self.set_pixel(x, y, rgb);
+            }
+        }
+    }
+}
+
+/// One sliding-window box blur pass along `lines` lines of `len` pixels; `at(line, i)` gives the
+/// index of pixel `i` of a line. The edges are extended.
+fn box_pass(buf: &mut [[f32; 3]], lines: usize, len: usize, radius: usize, at: impl Fn(usize, usize) -> usize) {
+    let r = radius as isize;
+    let n = (2 * radius + 1) as f32;
+    let clamp = |i: isize| i.clamp(0, len as isize - 1) as usize;
+    let mut line = vec![[0.0f32; 3]; len];
+    for l in 0..lines {
+        for (i, pixel) in line.iter_mut().enumerate() {
+            *pixel = buf[at(l, i)];
+        }
+        let mut sum = [0.0f32; 3];
+        for k in -r..=r {
+            (0..3).for_each(|c| sum[c] += line[clamp(k)][c]);
+        }
+        for i in 0..len {
+            buf[at(l, i)] = sum.map(|s| s / n);
+            let (leaving, entering) = (line[clamp(i as isize - r)], line[clamp(i as isize + r + 1)]);
+            (0..3).for_each(|c| sum[c] += entering[c] - leaving[c]);
+        }
+    }
+}
+
+/// Decodes the video stream of a file to RGB24 frames over a pipe, at a constant frame rate.
+/// Frames come out upright: ffmpeg applies the rotation metadata while decoding.
+pub struct FrameReader {
+    child: Child,
+    stdout: ChildStdout,
+    stderr: Option<JoinHandle<String>>,
+    pub width: usize,
+    pub height: usize,
+    pub fps: f64,
+    /// Length of the video in seconds, when it could be probed
+    pub duration: Option<f64>,
+    next: usize,
+    started: Instant,
+}
+
+impl FrameReader {
+    pub fn open(src: &Path) -> Result<Self> {
+        let info = probe(src)?;
+        let (width, height) = info.display_size().ok_or_else(|| anyhow!("没有视频流: {}", src.display()))?;
+        let fps = info.video()
+            .and_then(|(_, video)| video.avg_fps.or(video.r_fps))
+            .filter(|fps| *fps > 0.0)
+            .unwrap_or(30.0);
+        let src_str = src.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+
+        let fps_arg = format!("{:.6}", fps);
+        let mut args = vec!["-loglevel", "error", "-i", src_str, "-map", "0:v:0", "-r", &fps_arg, "-f", "rawvideo", "-pix_fmt", "rgb24"];
+        let threads = FFUtils::threads().map(|n| n.to_string());
+        if let Some(n) = &threads {
+            args.extend(["-threads", n.as_str()]);
+        }
+        args.push("pipe:1");
+
+        cancel::check()?;
+        let (mut child, stderr) = spawn(&args, Stdio::null(), Stdio::piped())?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("Failed to capture ffmpeg output"))?;
+        Ok(Self {
+            child,
+            stdout,
+            stderr: Some(stderr),
+            width: width as usize,
+            height: height as usize,
+            fps,
+            duration: info.duration,
+            next: 0,
+            started: Instant::now(),
+        })
+    }
+
+    /// The next frame, or None at the end of the video
+    pub fn read(&mut self) -> Result<Option<Frame>> {
+        cancel::check()?;
+        let mut data = vec![0; self.width * self.height * 3];
+        let mut filled = 0;
+        while filled < data.len() {
+            match self.stdout.read(&mut data[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        // A trailing partial frame is dropped
+        if filled < data.len() {
+            return Ok(None);
+        }
+
+        let frame = Frame {
+            index: self.next,
+            time: self.next as f64 / self.fps,
+            width: self.width,
+            height: self.height,
+            data,
+        };
+        self.next += 1;
+        if self.next.is_multiple_of(REPORT_EVERY) {
+            self.report(frame.time, false);
+        }
+        Ok(Some(frame))
+    }
+
+    /// Wait for the decoder to exit and surface its errors
+    pub fn finish(mut self) -> Result<()> {
+        let status = self.child.wait()?;
+        let stderr = self.stderr.take().and_then(|h| h.join().ok()).unwrap_or_default();
+        if !status.success() {
+            return Err(anyhow!("FFmpeg failed: {}", stderr));
+        }
+        self.report(self.next as f64 / self.fps, true);
+        Ok(())
+    }
+
+    fn report(&self, time: f64, finished: bool) {
+        if !progress::is_active() {
+            return;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        progress::report(FfmpegProgress {
+            out_time: time,
+            duration: self.duration,
+            speed: (elapsed > 0.0).then(|| time / elapsed),
+            finished,
+        });
+    }
+}
+
+impl Drop for FrameReader {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Encodes RGB24 frames from a pipe, taking the audio (if any) of another file. The codecs
+/// follow the container of `dst`, see `codec_args`.
+/// An output that is dropped without `finish` is deleted.
+pub struct FrameWriter {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stderr: Option<JoinHandle<String>>,
+    dst: PathBuf,
+    finished: bool,
+}
+
+impl FrameWriter {
+    pub fn create(dst: &Path, width: usize, height: usize, fps: f64, audio_from: &Path) -> Result<Self> {
+        let dst_str = dst.to_str().ok_or_else(|| anyhow!("Invalid output path encoding"))?;
+        let audio_str = audio_from.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+        let size = format!("{}x{}", width, height);
+        let fps_arg = format!("{:.6}", fps);
+        let audio = probe(audio_from)?;
+        let codecs = codec_args(dst, audio.audio().map(|(stream, _)| stream.codec.as_str()));
+
+        let mut args = vec![
+            "-y", "-loglevel", "error",
+            "-f", "rawvideo", "-pix_fmt", "rgb24", "-s", &size, "-r", &fps_arg, "-i", "pipe:0",
+            "-i", audio_str,
+            "-map", "0:v:0", "-map", "1:a?",
+            // yuv420p needs even sides
+            "-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p",
+        ];
+        args.extend(codecs);
+        let threads = FFUtils::threads().map(|n| n.to_string());
+        if let Some(n) = &threads {
+            args.extend(["-threads", n.as_str()]);
+        }
+        args.push(dst_str);
+
+        cancel::check()?;
+        let (mut child, stderr) = spawn(&args, Stdio::piped(), Stdio::null())?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("Failed to open the ffmpeg input pipe"))?;
+        Ok(Self { child, stdin: Some(stdin), stderr: Some(stderr), dst: dst.to_path_buf(), finished: false })
+    }
+
+    pub fn write(&mut self, frame: &Frame) -> Result<()> {
+        let stdin = self.stdin.as_mut().ok_or_else(|| anyhow!("Frame writer already closed"))?;
+        if stdin.write_all(&frame.data).is_err() {
+            // The encoder quit; its error output says why
+            self.stdin = None;
+            let _ = self.child.wait();
+            let stderr = self.stderr.take().and_then(|h| h.join().ok()).unwrap_or_default();
+            return Err(anyhow!("FFmpeg failed: {}", stderr));
+        }
+        Ok(())
+    }
+
+    /// Close the pipe and wait for the encoder to write the file
+    pub fn finish(mut self) -> Result<()> {
+        self.stdin = None;
+        let status = self.child.wait()?;
+        let stderr = self.stderr.take().and_then(|h| h.join().ok()).unwrap_or_default();
+        if !status.success() {
+            return Err(anyhow!("FFmpeg failed: {}", stderr));
+        }
+        self.finished = true;
+        Ok(())
+    }
+}
+
+/// Encoder arguments for a file written to `dst` with audio in `audio_codec`: H.264 in the
+/// containers that hold it, VP9 in WebM and the container's default codec otherwise. The audio
+/// is copied when the container takes its codec and re-encoded to the default otherwise.
+fn codec_args(dst: &Path, audio_codec: Option<&str>) -> Vec<&'static str> {
+    const H264: &[&str] = &["-c:v", "libx264", "-preset", "medium", "-crf", "18"];
+    const VP9: &[&str] = &["-c:v", "libvpx-vp9", "-crf", "32", "-b:v", "0"];
+    let ext = dst.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let (video, audio_codecs): (&[&str], &[&str]) = match ext.as_str() {
+        "mp4" | "m4v" | "mov" => (H264, &["aac", "mp3", "alac", "ac3", "eac3", "opus", "flac"]),
+        "mkv" => (H264, &[]),
+        "avi" => (H264, &["mp3", "ac3", "pcm_s16le", "pcm_u8"]),
+        "flv" => (H264, &["aac", "mp3"]),
+        "webm" => (VP9, &["opus", "vorbis"]),
+        "wmv" => (&[], &["wmav1", "wmav2"]),
+        _ => (&[], &[]),
+    };
+
+    let mut args = video.to_vec();
+    // Matroska holds any audio codec
+    if audio_codec.is_some_and(|codec| ext == "mkv" || audio_codecs.contains(&codec)) {
+        args.extend(["-c:a", "copy"]);
+    }
+    args
+}
+
+impl Drop for FrameWriter {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.stdin = None;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_file(&self.dst);
+    }
+}
+
+/// Effect written in Rust that changes decoded frames in place. Frames arrive in order, so an
+/// implementation may keep state from one frame to the next (trails, tracking, ...).
+/// Closures taking `&mut Frame` are processors too.
+pub trait FrameProcessor {
+    fn process(&mut self, frame: &mut Frame) -> Result<()>;
+}
+
+impl<F: FnMut(&mut Frame) -> Result<()>> FrameProcessor for F {
+    fn process(&mut self, frame: &mut Frame) -> Result<()> {
+        self(frame)
+    }
+}
+
+/// Decode `src`, pass every frame through `processor` and encode the result to `dst` with the
+/// audio of `src`
+pub fn process(src: &Path, dst: &Path, mut processor: impl FrameProcessor) -> Result<()> {
+    let mut reader = FrameReader::open(src)?;
+    let mut writer = FrameWriter::create(dst, reader.width, reader.height, reader.fps, src)?;
+    while let Some(mut frame) = reader.read()? {
+        processor.process(&mut frame)?;
+        writer.write(&frame)?;
+    }
+    reader.finish()?;
+    writer.finish()
+}
+
+/// Start ffmpeg with its stderr drained on a thread, so it never blocks on a full pipe
+fn spawn(args: &[&str], stdin: Stdio, stdout: Stdio) -> Result<(Child, JoinHandle<String>)> {
+    let ffmpeg_path = FFUtils::get_ffmpeg_path();
+    let mut child = Command::new(&ffmpeg_path)
+        .args(args)
+        .stdin(stdin)
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to execute ffmpeg at {:?}: {}", ffmpeg_path, e))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| anyhow!("Failed to capture ffmpeg output"))?;
+    let handle = thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    Ok((child, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: usize, height: usize, rgb: impl Fn(usize, usize) -> [u8; 3]) -> Frame {
+        let data = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).flat_map(|(x, y)| rgb(x, y)).collect();
+        Frame { index: 0, time: 0.0, width, height, data }
+    }
+
+    #[test]
+    fn pixelate_averages_blocks_inside_the_rect() {
+        let source = frame(6, 4, |x, y| [(x * 10 + y * 40) as u8, 0, 255]);
+        let mut pixelated = source.clone();
+        pixelated.pixelate(&Rect { x: 0.0, y: 0.0, width: 5.0, height: 4.0 }, 2);
+        // (0 + 10 + 40 + 50) / 4
+        assert_eq!(pixelated.pixel(1, 1), [25, 0, 255]);
+        assert_eq!(pixelated.pixel(0, 0), pixelated.pixel(1, 1));
+        // The last column of the rect is a block of its own: (120 + 160) / 2
+        assert_eq!(pixelated.pixel(4, 2), [140, 0, 255]);
+        assert_eq!(pixelated.pixel(5, 2), source.pixel(5, 2));
+    }
+
+    #[test]
+    fn blur_spreads_a_dot_and_leaves_the_outside() {
+        let source = frame(9, 9, |x, y| if (x, y) == (4, 4) { [255; 3] } else { [0; 3] });
+        let mut blurred = source.clone();
+        blurred.blur(&Rect { x: 1.0, y: 1.0, width: 7.0, height: 7.0 }, 1);
+        let centre = blurred.pixel(4, 4)[0];
+        assert!(centre < 255 && centre > blurred.pixel(3, 4)[0] && blurred.pixel(3, 4)[0] > 0, "{:?}", blurred.data);
+        // Symmetric around the dot
+        assert_eq!(blurred.pixel(3, 4), blurred.pixel(5, 4));
+        assert_eq!(blurred.pixel(4, 3), blurred.pixel(4, 5));
+        for (x, y) in (0..9).flat_map(|i| [(i, 0), (0, i), (i, 8), (8, i)]) {
+            assert_eq!(blurred.pixel(x, y), [0; 3]);
+        }
+
+        let mut flat = frame(6, 6, |_, _| [90, 120, 30]);
+        flat.blur(&Rect { x: 0.0, y: 0.0, width: 6.0, height: 6.0 }, 2);
+        assert!(flat.data.chunks_exact(3).all(|p| p == [90, 120, 30]));
+    }
+
+    #[test]
+    fn codecs_follow_the_container() {
+        let codecs = |name: &str, audio: Option<&str>| codec_args(Path::new(name), audio).join(" ");
+        assert_eq!(codecs("a.mp4", Some("aac")), "-c:v libx264 -preset medium -crf 18 -c:a copy");
+        assert_eq!(codecs("a.MOV", Some("pcm_s16le")), "-c:v libx264 -preset medium -crf 18");
+        assert_eq!(codecs("a.mkv", Some("truehd")), "-c:v libx264 -preset medium -crf 18 -c:a copy");
+        assert_eq!(codecs("a.webm", Some("opus")), "-c:v libvpx-vp9 -crf 32 -b:v 0 -c:a copy");
+        assert_eq!(codecs("a.webm", Some("aac")), "-c:v libvpx-vp9 -crf 32 -b:v 0");
+        assert_eq!(codecs("a.avi", Some("mp3")), "-c:v libx264 -preset medium -crf 18 -c:a copy");
+        assert_eq!(codecs("a.wmv", Some("wmav2")), "-c:a copy");
+        assert_eq!(codecs("a.flv", None), "-c:v libx264 -preset medium -crf 18");
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::core::{ActionConfig, ActionFactory, Material, SceneOptions};
+use crate::core::engine::{self, BatchJob, ProcessMode};
+use crate::core::scan::ScanOptions;
+use crate::core::search::SimilarityTarget;
+
+/// A whole batch run described in a JSON or TOML manifest, so runs can be reviewed,
+/// repeated and kept under version control.
+///
+/// ```toml
+/// inputs = ["raw", "extra/*.mov"]
+/// output_dir = "out"
+/// mode = "stacked"
+/// naming = "{stem}_v2"
+/// seed = 42
+/// workers = 2          # files at once, omit for auto
+/// ffmpeg_threads = 8   # threads per ffmpeg, omit for auto
+/// similarity = true    # compare outputs with their sources
+///
+/// [materials]
+/// watermark = "assets/logo.png"
+///
+/// [target]            # re-render until outputs are this different
+/// max_similarity = 0.8
+/// min_ssim = 0.6
+///
+/// [scenes]            # fresh random values for every scene
+/// threshold = 10
+/// min_length = 2.0
+///
+/// [scan]
+/// recursive = true
+/// exclude = ["*drafts*"]
+/// mirror_tree = true
+///
+/// [[actions]]
+/// id = "crop"
+/// params = { crop_min = 0.01, crop_max = 0.03 }
+///
+/// [[actions]]
+/// id = "md5"
+/// ```
+///
+/// Relative paths are resolved against the directory of the manifest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobSpec {
+    /// Directories, video files or glob patterns
+    pub inputs: Vec<String>,
+    pub output_dir: PathBuf,
+    #[serde(default)]
+    pub mode: ProcessMode,
+    /// Output file name template, see `BatchJob::naming`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub naming: Option<String>,
+    /// Job seed for the randomized actions; a random one is picked and logged when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// See `BatchJob::workers`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ffmpeg_threads: Option<usize>,
+    /// See `BatchJob::similarity`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub similarity: bool,
+    /// How input directories are scanned
+    #[serde(default, skip_serializing_if = "ScanOptions::is_default")]
+    pub scan: ScanOptions,
+    /// See `BatchJob::target`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<SimilarityTarget>,
+    /// See `ActionConfig::scenes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenes: Option<SceneOptions>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<Material, PathBuf>,
+    /// Parameters not tied to a single step
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub params: Map<String, Value>,
+    /// Ordered action chain
+    pub actions: Vec<ActionStep>,
+}
+
+/// One step of the chain: either a bare action id or an id with its parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ActionStep {
+    Id(String),
+    WithParams {
+        id: String,
+        #[serde(default)]
+        params: Map<String, Value>,
+    },
+}
+
+impl ActionStep {
+    pub fn id(&self) -> &str {
+        match self {
+            ActionStep::Id(id) | ActionStep::WithParams { id, .. } => id,
+        }
+    }
+
+    fn params(&self) -> Option<&Map<String, Value>> {
+        match self {
+            ActionStep::Id(_) => None,
+            ActionStep::WithParams { params, .. } => Some(params),
+        }
+    }
+}
+
+impl JobSpec {
+    /// Read a manifest; `.toml` files are parsed as TOML, everything else as JSON
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("无法读取任务文件 {}: {}", path.display(), e))?;
+        let spec = if Self::is_toml(path) {
+            toml::from_str(&text).map_err(|e| anyhow!("任务文件格式错误 {}: {}", path.display(), e))?
+        } else {
+            serde_json::from_str(&text).map_err(|e| anyhow!("任务文件格式错误 {}: {}", path.display(), e))?
+        };
+        Ok(spec)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = if Self::is_toml(path) {
+            toml::to_string_pretty(self)?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        fs::write(path, text).map_err(|e| anyhow!("无法写入任务文件 {}: {}", path.display(), e))
+    }
+
+    fn is_toml(path: &Path) -> bool {
+        path.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml"))
+    }
+
+    /// Resolve inputs and paths against `base_dir` and merge all parameters into one config.
+    /// Fails on unknown actions or parameters, parameters given to the wrong step and globs
+    /// that match nothing.
+    pub fn to_batch(&self, base_dir: &Path) -> Result<BatchJob> {
+        let factory = ActionFactory::global();
+        let resolve = |p: &Path| if p.is_absolute() { p.to_path_buf() } else { base_dir.join(p) };
+
+        let mut inputs = Vec::new();
+        for pattern in &self.inputs {
+            if pattern.contains(['*', '?', '[']) {
+                let full = resolve(Path::new(pattern));
+                let full = full.to_str().ok_or_else(|| anyhow!("Invalid input path encoding: {}", pattern))?;
+                let matches: Vec<PathBuf> = glob::glob(full)
+                    .map_err(|e| anyhow!("无效的通配符 {}: {}", pattern, e))?
+                    .filter_map(|p| p.ok())
+                    .collect();
+                if matches.is_empty() {
+                    bail!("没有文件匹配 {}", pattern);
+                }
+                inputs.extend(matches);
+            } else {
+                inputs.push(resolve(Path::new(pattern)));
+            }
+        }
+
+        let mut config = ActionConfig { seed: self.seed, scenes: self.scenes.clone(), ..Default::default() };
+        for (material, path) in &self.materials {
+            material.set_path(&mut config, resolve(path).to_string_lossy().to_string());
+        }
+
+        let params = config.params.as_object_mut().unwrap();
+        for (name, value) in &self.params {
+            if factory.param(name).is_none() {
+                bail!("Unknown parameter: {}", name);
+            }
+            params.insert(name.clone(), value.clone());
+        }
+
+        let mut actions = Vec::new();
+        for step in &self.actions {
+            let action = factory.get(step.id()).ok_or_else(|| anyhow!("Unknown action: {}", step.id()))?;
+            for (name, value) in step.params().into_iter().flatten() {
+                if !action.params().iter().any(|p| p.name == name) {
+                    bail!("参数 {} 不属于动作 {}", name, action.id());
+                }
+                if params.get(name).is_some_and(|v| v != value) {
+                    bail!("参数 {} 被设置了两次且取值不同", name);
+                }
+                params.insert(name.clone(), value.clone());
+            }
+            actions.push(action.id().to_string());
+        }
+
+        if let Some(naming) = &self.naming {
+            engine::check_naming(naming, self.mode, actions.len())?;
+        }
+
+        Ok(BatchJob {
+            inputs,
+            output_dir: resolve(&self.output_dir),
+            actions,
+            mode: self.mode,
+            naming: self.naming.clone(),
+            scan: self.scan.clone(),
+            workers: self.workers,
+            ffmpeg_threads: self.ffmpeg_threads,
+            restart: false,
+            similarity: self.similarity,
+            target: self.target.clone(),
+            config,
+        })
+    }
+
+    /// Manifest reproducing a batch; each parameter is listed under the step it belongs to
+    pub fn from_batch(job: &BatchJob) -> Self {
+        let factory = ActionFactory::global();
+        let mut params = job.config.params.as_object().cloned().unwrap_or_default();
+
+        let actions = job.actions.iter()
+            .map(|id| {
+                let owned: Map<String, Value> = factory.get(id)
+                    .map(|action| action.params().iter()
+                        .filter_map(|spec| params.remove(spec.name).map(|v| (spec.name.to_string(), v)))
+                        .collect())
+                    .unwrap_or_default();
+                if owned.is_empty() {
+                    ActionStep::Id(id.clone())
+                } else {
+                    ActionStep::WithParams { id: id.clone(), params: owned }
+                }
+            })
+            .collect();
+
+        let materials = Material::ALL.iter()
+            .filter_map(|&m| m.path(&job.config).map(|p| (m, PathBuf::from(p))))
+            .collect();
+
+        Self {
+            inputs: job.inputs.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+            output_dir: job.output_dir.clone(),
+            mode: job.mode,
+            naming: job.naming.clone(),
+            seed: job.config.seed,
+            workers: job.workers,
+            ffmpeg_threads: job.ffmpeg_threads,
+            similarity: job.similarity,
+            scan: job.scan.clone(),
+            target: job.target.clone(),
+            scenes: job.config.scenes.clone(),
+            materials,
+            params,
+            actions,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use crate::core::FFUtils;
+use crate::core::engine::{BatchJob, ProcessMode};
+use crate::core::seed::stable_hash;
+
+/// State of one task in the journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+/// One line of the journal. The last line of a task wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub file: PathBuf,
+    /// Action id, or the ids joined with ` → ` in stacked mode
+    pub chain: String,
+    /// `Journal::params_hash` of the job that ran the task
+    pub params: String,
+    pub status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// Size of `output` when it was written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Record of the tasks of a batch, kept as JSON lines in the output directory so an interrupted
+/// batch can be resumed: tasks done with the same parameters are skipped, failed and unfinished
+/// ones run again.
+pub struct Journal {
+    params: String,
+    previous: HashMap<(PathBuf, String), JournalEntry>,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    pub const FILE_NAME: &'static str = ".vmp_journal.jsonl";
+
+    /// Hash of everything besides the file and the chain that decides what a task writes and
+    /// where: the config (with the seed and the scene options), the naming, the mode, the
+    /// mirrored directories and the similarity target. Options at their default are left out, so
+    /// journals written before they existed still match. A job without a seed hashes the same on
+    /// every run, so its finished tasks are kept.
+    pub fn params_hash(job: &BatchJob) -> String {
+        let mut value = serde_json::json!({ "config": job.config, "naming": job.naming });
+        if job.mode != ProcessMode::default() {
+            value["mode"] = serde_json::json!(job.mode);
+        }
+        if job.scan.mirror_tree {
+            value["mirror_tree"] = serde_json::json!(true);
+        }
+        if let Some(target) = &job.target {
+            value["target"] = serde_json::json!(target);
+        }
+        let text = value.to_string();
+        format!("{:016x}", stable_hash(text.as_bytes()))
+    }
+
+    /// Load the journal of `out_dir`, keeping only the last entry of each task, and open it for
+    /// appending. Unreadable lines (such as one cut off by a crash) are dropped.
+    pub fn open(out_dir: &Path, params: String) -> Result<Self> {
+        let path = out_dir.join(Self::FILE_NAME);
+        let mut previous = HashMap::new();
+        if let Ok(text) = fs::read_to_string(&path) {
+            for entry in text.lines().filter_map(|l| serde_json::from_str::<JournalEntry>(l).ok()) {
+                previous.insert((entry.file.clone(), entry.chain.clone()), entry);
+            }
+        }
+
+        let mut compacted = String::new();
+        for entry in previous.values() {
+            compacted.push_str(&serde_json::to_string(entry)?);
+            compacted.push('\n');
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, compacted).and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|e| anyhow!("无法写入任务记录 {}: {}", path.display(), e))?;
+
+        let file = OpenOptions::new().append(true).open(&path)
+            .map_err(|e| anyhow!("无法写入任务记录 {}: {}", path.display(), e))?;
+        Ok(Self { params, previous, file: Mutex::new(file) })
+    }
+
+    /// Output of an earlier run of the task, if it finished with the same parameters and the
+    /// output is still there, has the recorded size and can be probed
+    pub fn completed(&self, file: &Path, chain: &str) -> Option<&Path> {
+        let entry = self.previous.get(&(file.to_path_buf(), chain.to_string()))?;
+        if entry.status != TaskStatus::Done || entry.params != self.params {
+            return None;
+        }
+        let output = entry.output.as_deref()?;
+        let size = fs::metadata(output).ok().filter(|m| m.is_file())?.len();
+        (Some(size) == entry.size && FFUtils::get_duration(output).is_ok()).then_some(output)
+    }
+
+    /// Number of tasks the earlier runs finished with the same parameters
+    pub fn done_count(&self) -> usize {
+        self.previous.values()
+            .filter(|e| e.status == TaskStatus::Done && e.params == self.params)
+            .count()
+    }
+
+    pub fn started(&self, file: &Path, chain: &str) {
+        self.append(self.entry(file, chain, TaskStatus::Pending));
+    }
+
+    pub fn done(&self, file: &Path, chain: &str, output: &Path) {
+        let mut entry = self.entry(file, chain, TaskStatus::Done);
+        entry.size = fs::metadata(output).ok().map(|m| m.len());
+        entry.output = Some(output.to_path_buf());
+        self.append(entry);
+    }
+
+    pub fn failed(&self, file: &Path, chain: &str, error: &str) {
+        let mut entry = self.entry(file, chain, TaskStatus::Failed);
+        entry.error = Some(error.to_string());
+        self.append(entry);
+    }
+
+    fn entry(&self, file: &Path, chain: &str, status: TaskStatus) -> JournalEntry {
+        JournalEntry {
+            file: file.to_path_buf(),
+            chain: chain.to_string(),
+            params: self.params.clone(),
+            status,
+            output: None,
+            size: None,
+            error: None,
+        }
+    }
+
+    /// Write one line and flush it, so the journal survives a crash right after
+    fn append(&self, entry: JournalEntry) {
+        let Ok(line) = serde_json::to_string(&entry) else { return };
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(file, "{}", line).and_then(|_| file.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(extra: serde_json::Value) -> BatchJob {
+        let mut value = serde_json::json!({ "inputs": ["in"], "output_dir": "out", "actions": ["crop"] });
+        value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn hash_covers_what_changes_the_output() {
+        let base = Journal::params_hash(&job(serde_json::json!({})));
+        for extra in [
+            serde_json::json!({ "mode": "independent" }),
+            serde_json::json!({ "scan": { "mirror_tree": true } }),
+            serde_json::json!({ "naming": "{stem}_v2" }),
+            serde_json::json!({ "config": { "seed": 1 } }),
+            serde_json::json!({ "config": { "scenes": { "threshold": 20.0 } } }),
+            serde_json::json!({ "config": { "crop_max": 0.1 } }),
+        ] {
+            assert_ne!(Journal::params_hash(&job(extra.clone())), base, "{}", extra);
+        }
+    }
+
+    #[test]
+    fn hash_ignores_what_does_not() {
+        let base = Journal::params_hash(&job(serde_json::json!({})));
+        for extra in [
+            serde_json::json!({ "workers": 3, "ffmpeg_threads": 2 }),
+            serde_json::json!({ "restart": true, "similarity": true }),
+            serde_json::json!({ "scan": { "recursive": true } }),
+            serde_json::json!({ "mode": "stacked" }),
+        ] {
+            assert_eq!(Journal::params_hash(&job(extra.clone())), base, "{}", extra);
+        }
+    }
+
+    #[test]
+    fn open_keeps_the_last_entry_of_each_task() {
+        let dir = std::env::temp_dir().join(format!("vmp_journal_{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let line = |file: &str, status: &str| format!(r#"{{"file":"{}","chain":"crop","params":"p","status":"{}"}}"#, file, status);
+        let text = [line("a.mp4", "pending"), line("b.mp4", "pending"), line("a.mp4", "failed"), "{\"file\":\"cut off".to_string()].join("\n");
+        fs::write(dir.join(Journal::FILE_NAME), text).unwrap();
+
+        let journal = Journal::open(&dir, "p".to_string()).unwrap();
+        assert_eq!(journal.previous.len(), 2);
+        assert_eq!(journal.previous[&(PathBuf::from("a.mp4"), "crop".to_string())].status, TaskStatus::Failed);
+        journal.done(Path::new("c.mp4"), "crop", &dir.join("missing.mp4"));
+
+        let lines: Vec<JournalEntry> = fs::read_to_string(dir.join(Journal::FILE_NAME)).unwrap().lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines.iter().filter(|e| e.file == Path::new("a.mp4")).count(), 1);
+        assert_eq!(journal.done_count(), 0);
+        // Done, but the output is gone
+        let reopened = Journal::open(&dir, "p".to_string()).unwrap();
+        assert_eq!(reopened.done_count(), 1);
+        assert_eq!(reopened.completed(Path::new("c.mp4"), "crop"), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,35 @@
 pub mod ffutils;
 pub mod factory;
 pub mod ai;
+pub mod filtergraph;
+pub mod pipeline;
+pub mod params;
+pub mod engine;
+pub mod job;
+pub mod seed;
+pub mod cancel;
+pub mod progress;
+pub mod journal;
+pub mod scan;
+pub mod watch;
+pub mod probe;
+pub mod similarity;
+pub mod fingerprint;
+pub mod search;
+pub mod frames;
+pub mod face;
+pub mod track;
+pub mod effects;
+pub mod scenes;
 
 pub use ffutils::FFUtils;
-pub use factory::ActionFactory;
+pub use factory::{ActionFactory, ActionMeta, ActionCategory, Touches, Material};
+pub use filtergraph::{Filter, FilterContext, FilterFragment, FilterGraph};
+pub use params::{ParamSpec, ParamKind};
+pub use scenes::SceneOptions;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +43,28 @@ pub struct ActionConfig {
     pub pip_path: Option<String>,
     pub goods_path: Option<String>,
     pub mask_video_path: Option<String>,
+    /// B video of the A/B replace actions, or a directory to pick one from per file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ab_video_path: Option<String>,
+    /// Object track exported by an earlier run, followed instead of tracking again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track_path: Option<String>,
+    /// UltraFace ONNX model of the face detection, instead of the one next to the executable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub face_model_path: Option<String>,
+    
+    /// Seed for the randomized actions, see `ActionConfig::for_file` and `ActionConfig::rng`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// Split videos at scene cuts and give every scene its own random values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenes: Option<SceneOptions>,
+
+    /// File the batch is working on, set by `for_file`. Actions in a stacked chain run on
+    /// intermediate files and name this one in what they export.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
     
     // Generic config map for flexibility
     #[serde(flatten)]
@@ -36,16 +82,72 @@ impl Default for ActionConfig {
             pip_path: None,
             goods_path: None,
             mask_video_path: None,
+            ab_video_path: None,
+            track_path: None,
+            face_model_path: None,
+            seed: None,
+            scenes: None,
+            source: None,
             params: serde_json::json!({}),
         }
     }
 }
 
+/// What an action produced
+#[derive(Debug, Clone)]
+pub struct ActionOutput {
+    /// The main output video, or the source itself when the action only exported data
+    pub path: PathBuf,
+    /// Extra files written next to it (reports, exported data, ...)
+    pub side_outputs: Vec<PathBuf>,
+    /// Wall-clock time spent producing the output
+    pub elapsed: Duration,
+    /// Randomly chosen values as `action: name=value`, logged so an output can be reproduced
+    pub choices: Vec<String>,
+}
+
+impl ActionOutput {
+    pub fn new(path: PathBuf, elapsed: Duration) -> Self {
+        Self { path, side_outputs: Vec::new(), elapsed, choices: Vec::new() }
+    }
+}
+
 /// Trait that all video processing actions must implement
 pub trait VideoAction: Send + Sync {
-    /// Execute the action on the source file
-    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<()>;
+    /// Execute the action on the source file and report the file(s) it wrote
+    fn execute(&self, src: &Path, out_dir: &Path, config: &ActionConfig) -> Result<ActionOutput>;
     
     /// Get the identifier for this action (e.g., "crop", "speed")
     fn id(&self) -> &'static str;
+
+    /// Display names, category and the streams/materials this action works with
+    fn meta(&self) -> ActionMeta;
+
+    /// Parameters this action reads from `ActionConfig.params`
+    fn params(&self) -> &'static [ParamSpec] {
+        &[]
+    }
+
+    /// Numeric parameters that make the output differ more from its source as they grow.
+    /// The similarity target search raises them when an output is still too similar.
+    fn intensity(&self) -> &'static [ParamSpec] {
+        &[]
+    }
+
+    /// Whether the action draws fresh random values for every scene when scene splitting is on
+    /// (see `ActionConfig::scenes`). Needs a filter fragment that does not depend on the duration.
+    fn per_scene(&self) -> bool {
+        false
+    }
+
+    /// Check the configured parameters against the schema before any work is done
+    fn validate(&self, config: &ActionConfig) -> Result<()> {
+        params::check_all(self.params(), config)
+    }
+
+    /// Filter fragment for merging this action into a shared filtergraph.
+    /// Returns `None` for actions that need their own ffmpeg pass (remux, file copy, ...)
+    fn filter(&self, _ctx: &FilterContext, _config: &ActionConfig) -> Result<Option<FilterFragment>> {
+        Ok(None)
+    }
 }
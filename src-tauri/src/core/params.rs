@@ -0,0 +1,128 @@
+use anyhow::{Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::core::ActionConfig;
+
+/// Type, range and default of a parameter.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamKind {
+    Float { min: f64, max: f64, default: f64 },
+    Int { min: i64, max: i64, default: i64 },
+    /// One of a fixed set of `(value, label)` pairs
+    Choice { options: &'static [(&'static str, &'static str)], default: &'static str },
+    /// ffmpeg bitrate such as `15M` or `5000k`
+    Bitrate { default: &'static str },
+}
+
+/// Declarative description of one action parameter, stored under `name` in `ActionConfig.params`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    /// Label shown in the settings dialog
+    pub label: &'static str,
+    pub kind: ParamKind,
+    pub unit: &'static str,
+    pub description: &'static str,
+}
+
+impl ParamSpec {
+    pub fn default_value(&self) -> Value {
+        match self.kind {
+            ParamKind::Float { default, .. } => json!(default),
+            ParamKind::Int { default, .. } => json!(default),
+            ParamKind::Choice { default, .. } => json!(default),
+            ParamKind::Bitrate { default } => json!(default),
+        }
+    }
+
+    /// Human readable range, e.g. `0.1-10` or `horizontal|vertical|both`
+    pub fn range_text(&self) -> String {
+        match self.kind {
+            ParamKind::Float { min, max, .. } => format!("{}-{}", min, max),
+            ParamKind::Int { min, max, .. } => format!("{}-{}", min, max),
+            ParamKind::Choice { options, .. } => options.iter().map(|(v, _)| *v).collect::<Vec<_>>().join("|"),
+            ParamKind::Bitrate { .. } => "e.g. 5000k, 15M".to_string(),
+        }
+    }
+
+    /// Check a value against the schema
+    pub fn check(&self, value: &Value) -> Result<()> {
+        match self.kind {
+            ParamKind::Float { min, max, .. } => {
+                let v = value.as_f64().ok_or_else(|| self.type_error(value, "数字"))?;
+                if !(min..=max).contains(&v) {
+                    bail!("参数 {} ({}) 超出范围 {}-{}: {}", self.name, self.label, min, max, v);
+                }
+            }
+            ParamKind::Int { min, max, .. } => {
+                let v = Self::as_int(value).ok_or_else(|| self.type_error(value, "整数"))?;
+                if !(min..=max).contains(&v) {
+                    bail!("参数 {} ({}) 超出范围 {}-{}: {}", self.name, self.label, min, max, v);
+                }
+            }
+            ParamKind::Choice { options, .. } => {
+                let v = value.as_str().ok_or_else(|| self.type_error(value, "字符串"))?;
+                if !options.iter().any(|(o, _)| *o == v) {
+                    bail!("参数 {} ({}) 不支持的取值 \"{}\"，可选: {}", self.name, self.label, v, self.range_text());
+                }
+            }
+            ParamKind::Bitrate { .. } => {
+                let v = value.as_str().ok_or_else(|| self.type_error(value, "码率字符串"))?;
+                if !Self::is_bitrate(v) {
+                    bail!("参数 {} ({}) 不是有效的码率: \"{}\" (例如 5000k, 15M)", self.name, self.label, v);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn type_error(&self, value: &Value, expected: &str) -> anyhow::Error {
+        anyhow!("参数 {} ({}) 类型错误: 需要{}，实际为 {}", self.name, self.label, expected, value)
+    }
+
+    /// Integers may arrive as whole floats (`60.0`) from JSON sliders or the AI
+    fn as_int(value: &Value) -> Option<i64> {
+        value.as_i64().or_else(|| value.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i64))
+    }
+
+    fn is_bitrate(value: &str) -> bool {
+        let digits = value.strip_suffix(['k', 'K', 'm', 'M']).unwrap_or(value);
+        digits.parse::<f64>().is_ok_and(|v| v > 0.0)
+    }
+}
+
+/// Check every parameter of an action that is present in the config
+pub fn check_all(specs: &[ParamSpec], config: &ActionConfig) -> Result<()> {
+    for spec in specs {
+        if let Some(value) = config.params.get(spec.name) {
+            spec.check(value)?;
+        }
+    }
+    Ok(())
+}
+
+impl ActionConfig {
+    /// The validated value of a parameter, or its default when it is not set
+    fn param(&self, spec: &ParamSpec) -> Result<Value> {
+        match self.params.get(spec.name) {
+            Some(value) => {
+                spec.check(value)?;
+                Ok(value.clone())
+            }
+            None => Ok(spec.default_value()),
+        }
+    }
+
+    pub fn float(&self, spec: &ParamSpec) -> Result<f64> {
+        Ok(self.param(spec)?.as_f64().unwrap_or_default())
+    }
+
+    pub fn int(&self, spec: &ParamSpec) -> Result<i64> {
+        Ok(ParamSpec::as_int(&self.param(spec)?).unwrap_or_default())
+    }
+
+    pub fn text(&self, spec: &ParamSpec) -> Result<String> {
+        Ok(self.param(spec)?.as_str().unwrap_or_default().to_string())
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use anyhow::{Result, anyhow};
+use uuid::Uuid;
+use crate::core::{VideoAction, ActionConfig, ActionOutput, FilterContext, FilterGraph};
+use crate::core::{cancel, probe, scenes};
+
+/// Applies several actions to one file. Consecutive actions that expose a filter fragment are
+/// merged into a single ffmpeg pass; the others (remux, cut, file copy) run on their own in between.
+/// With scene splitting on, consecutive actions that support it are rendered scene by scene in
+/// a pass of their own.
+pub struct StackedChain<'a> {
+    actions: Vec<&'a dyn VideoAction>,
+}
+
+impl<'a> StackedChain<'a> {
+    pub fn new(actions: Vec<&'a dyn VideoAction>) -> Self {
+        Self { actions }
+    }
+
+    /// Run the chain on `src` and write the final result to `dst`.
+    /// Intermediate files live in a scratch folder next to `dst` and are removed afterwards;
+    /// side outputs of stand-alone steps are moved next to `dst`, named after `src`.
+    pub fn run(&self, src: &Path, dst: &Path, config: &ActionConfig, log: &dyn Fn(String)) -> Result<ActionOutput> {
+        let out_dir = dst.parent().ok_or_else(|| anyhow!("Invalid output path"))?;
+        let work_dir = out_dir.join(format!(".stack_{}", Uuid::new_v4().simple()));
+        fs::create_dir_all(&work_dir)?;
+
+        let result = self.run_in(src, dst, &work_dir, config, log);
+        let _ = fs::remove_dir_all(&work_dir);
+        probe::forget(&work_dir);
+        result
+    }
+
+    fn run_in(&self, src: &Path, dst: &Path, work_dir: &Path, config: &ActionConfig, log: &dyn Fn(String)) -> Result<ActionOutput> {
+        let started = Instant::now();
+        let out_dir = dst.parent().ok_or_else(|| anyhow!("Invalid output path"))?;
+        let mut output = ActionOutput::new(dst.to_path_buf(), Default::default());
+        let ext = src.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+        let mut current = src.to_path_buf();
+        let mut graph = FilterGraph::new();
+        let mut merged: Vec<&str> = Vec::new();
+        let mut scene_group: Vec<&dyn VideoAction> = Vec::new();
+        let scene_options = config.scenes.clone().unwrap_or_default();
+        let mut step = 0;
+
+        for action in &self.actions {
+            cancel::check()?;
+            let per_scene = config.scenes.is_some() && action.per_scene();
+            if per_scene && !graph.is_empty() {
+                let next = work_dir.join(format!("step{}.{}", step, ext));
+                log(format!("合并滤镜: {}", merged.join(" + ")));
+                graph.run(&current, &next)?;
+                current = next;
+                graph = FilterGraph::new();
+                merged.clear();
+                step += 1;
+            }
+            if !per_scene && !scene_group.is_empty() {
+                let next = work_dir.join(format!("step{}.{}", step, ext));
+                output.choices.extend(scenes::render(&scene_group, &current, &next, config, &scene_options, log)?);
+                current = next;
+                scene_group.clear();
+                step += 1;
+            }
+            if per_scene {
+                scene_group.push(*action);
+                continue;
+            }
+
+            let ctx = FilterContext { src: &current, time_scale: graph.time_scale() };
+
+            match action.filter(&ctx, config)? {
+                Some(fragment) => {
+                    output.choices.extend(fragment.choices.iter().map(|c| format!("{}: {}", action.id(), c)));
+                    graph.push(fragment);
+                    merged.push(action.id());
+                }
+                None => {
+                    // Flush what has been merged so far, then run this action on its own
+                    if !graph.is_empty() {
+                        let next = work_dir.join(format!("step{}.{}", step, ext));
+                        log(format!("合并滤镜: {}", merged.join(" + ")));
+                        graph.run(&current, &next)?;
+                        current = next;
+                        graph = FilterGraph::new();
+                        merged.clear();
+                        step += 1;
+                    }
+
+                    let step_dir = work_dir.join(format!("step{}_{}", step, action.id()));
+                    fs::create_dir_all(&step_dir)?;
+                    log(format!("独立执行: {}", action.id()));
+                    let step_output = action.execute(&current, &step_dir, config)?;
+                    for side in step_output.side_outputs {
+                        let kept = out_dir.join(side_name(&side, &current, src));
+                        fs::rename(&side, &kept)?;
+                        output.side_outputs.push(kept);
+                    }
+                    output.choices.extend(step_output.choices);
+                    current = step_output.path;
+                    step += 1;
+                }
+            }
+        }
+
+        cancel::check()?;
+        if !scene_group.is_empty() {
+            output.choices.extend(scenes::render(&scene_group, &current, dst, config, &scene_options, log)?);
+        } else if !graph.is_empty() {
+            log(format!("合并滤镜: {}", merged.join(" + ")));
+            graph.run(&current, dst)?;
+        } else if current != src {
+            fs::rename(&current, dst)?;
+        } else {
+            fs::copy(src, dst)?;
+        }
+
+        output.elapsed = started.elapsed();
+        Ok(output)
+    }
+}
+
+/// File name for a side output written by a step that ran on `step_src`, with the step file's
+/// stem replaced by the stem of the chain's source: `step1_face.faces.json` of `clip.mp4`
+/// becomes `clip_face.faces.json`, so files of different sources do not collide
+fn side_name(side: &Path, step_src: &Path, src: &Path) -> String {
+    let name = side.file_name().unwrap_or_default().to_string_lossy();
+    let step_stem = step_src.file_stem().unwrap_or_default().to_string_lossy();
+    let stem = src.file_stem().unwrap_or_default().to_string_lossy();
+    match name.strip_prefix(step_stem.as_ref()) {
+        Some(rest) if !step_stem.is_empty() => format!("{}{}", stem, rest),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_outputs_are_named_after_the_source() {
+        let src = Path::new("/in/clip.mp4");
+        let step = Path::new("/out/.stack_1/step1.mp4");
+        assert_eq!(side_name(Path::new("/out/.stack_1/step2_face/step1_face.faces.json"), step, src), "clip_face.faces.json");
+        // The first step runs on the source itself
+        assert_eq!(side_name(Path::new("/out/.stack_1/step0_face/clip_face.faces.json"), src, src), "clip_face.faces.json");
+        assert_eq!(side_name(Path::new("/out/.stack_1/step2_track/report.json"), step, src), "report.json");
+    }
+}
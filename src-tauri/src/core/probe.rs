@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::core::FFUtils;
+
+/// What ffprobe knows about a media file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaInfo {
+    pub path: PathBuf,
+    /// Demuxer names, e.g. `mov,mp4,m4a,3gp,3g2,mj2`
+    pub container: String,
+    /// Seconds
+    pub duration: Option<f64>,
+    /// Bits per second over the whole file
+    pub bit_rate: Option<u64>,
+    pub size: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub index: usize,
+    pub kind: StreamKind,
+    pub codec: String,
+    pub profile: Option<String>,
+    pub bit_rate: Option<u64>,
+    pub duration: Option<f64>,
+    pub language: Option<String>,
+    /// Set for video streams
+    pub video: Option<VideoInfo>,
+    /// Set for audio streams
+    pub audio: Option<AudioInfo>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VideoInfo {
+    /// Coded size, before rotation
+    pub width: u32,
+    pub height: u32,
+    pub pix_fmt: Option<String>,
+    /// Average frame rate (`avg_frame_rate`)
+    pub avg_fps: Option<f64>,
+    /// Base frame rate (`r_frame_rate`); differs from `avg_fps` for variable frame rate files
+    pub r_fps: Option<f64>,
+    /// Clockwise rotation players apply when displaying, from the display matrix or the
+    /// `rotate` tag: 0, 90, 180 or 270
+    pub rotation: u32,
+    pub color: ColorInfo,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColorInfo {
+    /// `tv` (limited) or `pc` (full)
+    pub range: Option<String>,
+    pub space: Option<String>,
+    pub transfer: Option<String>,
+    pub primaries: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioInfo {
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+}
+
+impl MediaInfo {
+    /// Parse the output of `ffprobe -show_format -show_streams -of json`
+    pub fn from_ffprobe(path: &Path, json: &Value) -> Self {
+        let format = &json["format"];
+        Self {
+            path: path.to_path_buf(),
+            container: text(&format["format_name"]).unwrap_or_default(),
+            duration: number(&format["duration"]),
+            bit_rate: number(&format["bit_rate"]).map(|b| b as u64),
+            size: number(&format["size"]).map(|s| s as u64),
+            streams: json["streams"].as_array().into_iter().flatten().map(StreamInfo::from_ffprobe).collect(),
+        }
+    }
+
+    /// First video stream that is not a cover picture
+    pub fn video(&self) -> Option<(&StreamInfo, &VideoInfo)> {
+        self.streams.iter()
+            .filter(|s| !matches!(s.codec.as_str(), "mjpeg" | "png" | "bmp"))
+            .find_map(|s| s.video.as_ref().map(|v| (s, v)))
+    }
+
+    pub fn audio(&self) -> Option<(&StreamInfo, &AudioInfo)> {
+        self.streams.iter().find_map(|s| s.audio.as_ref().map(|a| (s, a)))
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio().is_some()
+    }
+
+    /// Size of the picture as shown by players, with the rotation applied
+    pub fn display_size(&self) -> Option<(u32, u32)> {
+        let (_, video) = self.video()?;
+        Some(if video.rotation % 180 == 90 { (video.height, video.width) } else { (video.width, video.height) })
+    }
+}
+
+impl StreamInfo {
+    fn from_ffprobe(stream: &Value) -> Self {
+        let kind = match stream["codec_type"].as_str() {
+            Some("video") => StreamKind::Video,
+            Some("audio") => StreamKind::Audio,
+            Some("subtitle") => StreamKind::Subtitle,
+            Some("data") => StreamKind::Data,
+            Some("attachment") => StreamKind::Attachment,
+            _ => StreamKind::Other,
+        };
+        Self {
+            index: stream["index"].as_u64().unwrap_or(0) as usize,
+            kind,
+            codec: text(&stream["codec_name"]).unwrap_or_default(),
+            profile: text(&stream["profile"]),
+            bit_rate: number(&stream["bit_rate"]).map(|b| b as u64),
+            duration: number(&stream["duration"]),
+            language: text(&stream["tags"]["language"]),
+            video: (kind == StreamKind::Video).then(|| VideoInfo {
+                width: stream["width"].as_u64().unwrap_or(0) as u32,
+                height: stream["height"].as_u64().unwrap_or(0) as u32,
+                pix_fmt: text(&stream["pix_fmt"]),
+                avg_fps: rate(&stream["avg_frame_rate"]),
+                r_fps: rate(&stream["r_frame_rate"]),
+                rotation: rotation(stream),
+                color: ColorInfo {
+                    range: text(&stream["color_range"]),
+                    space: text(&stream["color_space"]),
+                    transfer: text(&stream["color_transfer"]),
+                    primaries: text(&stream["color_primaries"]),
+                },
+            }),
+            audio: (kind == StreamKind::Audio).then(|| AudioInfo {
+                sample_rate: number(&stream["sample_rate"]).map(|r| r as u32),
+                channels: stream["channels"].as_u64().map(|c| c as u32),
+                channel_layout: text(&stream["channel_layout"]),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for MediaInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.path.display(), self.container)?;
+        if let Some(duration) = self.duration {
+            write!(f, "\n  duration: {:.2}s", duration)?;
+        }
+        if let Some(bit_rate) = self.bit_rate {
+            write!(f, "\n  bitrate: {} kb/s", bit_rate / 1000)?;
+        }
+        for stream in &self.streams {
+            write!(f, "\n  #{} {:?}: {}", stream.index, stream.kind, stream.codec)?;
+            if let Some(video) = &stream.video {
+                write!(f, " {}x{}", video.width, video.height)?;
+                if let Some(pix_fmt) = &video.pix_fmt {
+                    write!(f, " {}", pix_fmt)?;
+                }
+                if let Some(fps) = video.avg_fps {
+                    write!(f, " @ {:.3} fps", fps)?;
+                }
+                if video.r_fps.zip(video.avg_fps).is_some_and(|(r, avg)| (r - avg).abs() > 0.01) {
+                    write!(f, " (r {:.3})", video.r_fps.unwrap_or_default())?;
+                }
+                if video.rotation != 0 {
+                    write!(f, " rotated {}°", video.rotation)?;
+                }
+                let color = &video.color;
+                if *color != ColorInfo::default() {
+                    let part = |p: &Option<String>| p.clone().unwrap_or_else(|| "?".to_string());
+                    write!(f, " color {}/{}/{}/{}", part(&color.range), part(&color.space), part(&color.transfer), part(&color.primaries))?;
+                }
+            }
+            if let Some(audio) = &stream.audio {
+                if let Some(rate) = audio.sample_rate {
+                    write!(f, " {} Hz", rate)?;
+                }
+                if let Some(channels) = audio.channels {
+                    write!(f, " {} ch", channels)?;
+                }
+            }
+            if let Some(language) = &stream.language {
+                write!(f, " ({})", language)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Probed files kept at most; the least recently used go first. Watch mode runs for days and
+/// every intermediate file of a chain is probed as well.
+const CACHE_SIZE: usize = 256;
+
+struct CacheEntry {
+    /// Size and modification time of the file when it was probed
+    stamp: Option<(u64, SystemTime)>,
+    info: Arc<MediaInfo>,
+    /// `Cache::clock` at the last use
+    used: u64,
+}
+
+/// Cached info per path, dropped when the file's size or modification time changes
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<PathBuf, CacheEntry>,
+    clock: u64,
+}
+
+impl Cache {
+    fn get(&mut self, path: &Path, stamp: Option<(u64, SystemTime)>) -> Option<Arc<MediaInfo>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(path).filter(|e| e.stamp == stamp)?;
+        entry.used = self.clock;
+        Some(entry.info.clone())
+    }
+
+    fn insert(&mut self, path: &Path, stamp: Option<(u64, SystemTime)>, info: Arc<MediaInfo>) {
+        self.clock += 1;
+        if self.entries.len() >= CACHE_SIZE && !self.entries.contains_key(path) {
+            let oldest = self.entries.iter().min_by_key(|(_, e)| e.used).map(|(p, _)| p.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(path.to_path_buf(), CacheEntry { stamp, info, used: self.clock });
+    }
+
+    fn forget(&mut self, path: &Path) {
+        self.entries.retain(|p, _| !p.starts_with(path));
+    }
+}
+
+fn cache() -> &'static Mutex<Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Probe a media file. Results are cached, so actions, the engine and the UI can ask again
+/// without starting ffprobe each time.
+pub fn probe(path: &Path) -> Result<Arc<MediaInfo>> {
+    let stamp = fs::metadata(path).ok().and_then(|m| Some((m.len(), m.modified().ok()?)));
+    if let Some(info) = cache().lock().unwrap_or_else(|e| e.into_inner()).get(path, stamp) {
+        return Ok(info);
+    }
+
+    let info = Arc::new(MediaInfo::from_ffprobe(path, &FFUtils::probe(path)?));
+    cache().lock().unwrap_or_else(|e| e.into_inner()).insert(path, stamp, info.clone());
+    Ok(info)
+}
+
+/// Drop what is cached for `path` and, for a directory, for every file below it. Called for
+/// removed scratch files and directories, whose entries could never be used again.
+pub fn forget(path: &Path) {
+    cache().lock().unwrap_or_else(|e| e.into_inner()).forget(path);
+}
+
+/// Times of the keyframes of the first video stream, counted from the start of the file like
+/// `-ss`. Read from the packet flags, so nothing is decoded.
+pub fn keyframes(path: &Path) -> Result<Vec<f64>> {
+    let ffprobe_path = FFUtils::get_ffprobe_path();
+    let output = Command::new(&ffprobe_path)
+        .args(["-v", "error", "-select_streams", "v:0", "-show_entries", "packet=pts_time,flags:format=start_time", "-of", "json"])
+        .arg(path)
+        .output()
+        .map_err(|e| anyhow!("Failed to execute ffprobe at {:?}: {}", ffprobe_path, e))?;
+    if !output.status.success() {
+        bail!("ffprobe failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+    let start = number(&json["format"]["start_time"]).unwrap_or(0.0);
+    let mut times: Vec<f64> = json["packets"].as_array().into_iter().flatten()
+        .filter(|p| p["flags"].as_str().is_some_and(|f| f.starts_with('K')))
+        .filter_map(|p| number(&p["pts_time"]))
+        .map(|t| t - start)
+        .collect();
+    times.sort_by(f64::total_cmp);
+    Ok(times)
+}
+
+fn text(value: &Value) -> Option<String> {
+    value.as_str().filter(|s| !s.is_empty() && *s != "unknown").map(String::from)
+}
+
+/// ffprobe writes most numbers as strings
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str()?.parse().ok())
+}
+
+/// `30000/1001` → 29.97; `0/0` means unknown
+fn rate(value: &Value) -> Option<f64> {
+    let (num, den) = value.as_str()?.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+fn rotation(stream: &Value) -> u32 {
+    // The display matrix gives the counter-clockwise angle, the old `rotate` tag the clockwise one
+    let from_matrix = stream["side_data_list"].as_array().into_iter().flatten()
+        .find_map(|d| number(&d["rotation"]))
+        .map(|r| -r);
+    let degrees = from_matrix.or_else(|| number(&stream["tags"]["rotate"])).unwrap_or(0.0);
+    ((degrees.round() as i64).rem_euclid(360) as u32 + 45) / 90 * 90 % 360
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn frame_rates() {
+        assert_eq!(rate(&json!("25/1")), Some(25.0));
+        assert!((rate(&json!("30000/1001")).unwrap() - 29.97).abs() < 0.001);
+        assert_eq!(rate(&json!("0/0")), None);
+        assert_eq!(rate(&json!("25")), None);
+        assert_eq!(rate(&json!(null)), None);
+    }
+
+    #[test]
+    fn rotation_from_display_matrix_or_tag() {
+        assert_eq!(rotation(&json!({})), 0);
+        assert_eq!(rotation(&json!({ "side_data_list": [{ "rotation": -90 }] })), 90);
+        assert_eq!(rotation(&json!({ "side_data_list": [{ "rotation": 90 }] })), 270);
+        assert_eq!(rotation(&json!({ "side_data_list": [{ "rotation": 180.0 }] })), 180);
+        assert_eq!(rotation(&json!({ "tags": { "rotate": "90" } })), 90);
+        assert_eq!(rotation(&json!({ "tags": { "rotate": "-88" } })), 270);
+        // The matrix wins over a stale tag
+        assert_eq!(rotation(&json!({ "side_data_list": [{ "rotation": -90 }], "tags": { "rotate": "180" } })), 90);
+    }
+
+    #[test]
+    fn parses_streams_and_display_size() {
+        let info = MediaInfo::from_ffprobe(Path::new("a.mp4"), &json!({
+            "format": { "format_name": "mov,mp4", "duration": "10.5", "bit_rate": "8000000" },
+            "streams": [
+                { "index": 0, "codec_type": "video", "codec_name": "mjpeg", "width": 64, "height": 64 },
+                { "index": 1, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+                  "pix_fmt": "yuv420p", "avg_frame_rate": "30/1", "side_data_list": [{ "rotation": -90 }] },
+                { "index": 2, "codec_type": "audio", "codec_name": "aac", "sample_rate": "48000", "channels": 2,
+                  "tags": { "language": "und" } },
+            ],
+        }));
+        assert_eq!(info.duration, Some(10.5));
+        assert_eq!(info.video().map(|(s, _)| s.index), Some(1));
+        assert_eq!(info.display_size(), Some((1080, 1920)));
+        assert_eq!(info.audio().and_then(|(_, a)| a.sample_rate), Some(48000));
+        assert!(info.has_audio());
+    }
+
+    #[test]
+    fn cache_drops_the_least_recently_used_and_forgotten_dirs() {
+        let info = Arc::new(MediaInfo::from_ffprobe(Path::new("x"), &json!({})));
+        let mut cache = Cache::default();
+        let path = |i: usize| PathBuf::from(format!("/work/{}.mp4", i));
+        for i in 0..CACHE_SIZE {
+            cache.insert(&path(i), None, info.clone());
+        }
+        assert!(cache.get(&path(0), None).is_some());
+        cache.insert(Path::new("/in/new.mp4"), None, info.clone());
+        assert_eq!(cache.entries.len(), CACHE_SIZE);
+        assert!(cache.get(&path(0), None).is_some(), "recently used entry kept");
+        assert!(cache.get(&path(1), None).is_none(), "least recently used entry dropped");
+
+        cache.forget(Path::new("/work"));
+        assert_eq!(cache.entries.len(), 1);
+        assert!(cache.get(Path::new("/in/new.mp4"), None).is_some());
+        assert!(cache.get(Path::new("/in/new.mp4"), Some((1, SystemTime::UNIX_EPOCH))).is_none(), "changed file");
+    }
+}
@@ -0,0 +1,84 @@
+use std::cell::RefCell;
+
+/// Position of a running ffmpeg process, parsed from its `-progress pipe:1` output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FfmpegProgress {
+    /// Seconds of output written so far
+    pub out_time: f64,
+    /// Expected length of the output in seconds, when it could be probed
+    pub duration: Option<f64>,
+    /// Encoding speed as a multiple of realtime
+    pub speed: Option<f64>,
+    /// Set on the last report of the process
+    pub finished: bool,
+}
+
+impl FfmpegProgress {
+    /// Share of the output written, between 0 and 1
+    pub fn fraction(&self) -> Option<f64> {
+        if self.finished {
+            return Some(1.0);
+        }
+        self.duration
+            .filter(|d| *d > 0.0)
+            .map(|d| (self.out_time / d).clamp(0.0, 1.0))
+    }
+
+    /// Seconds left at the current speed
+    pub fn eta_secs(&self) -> Option<f64> {
+        if self.finished {
+            return Some(0.0);
+        }
+        let remaining = (self.duration? - self.out_time).max(0.0);
+        self.speed.filter(|s| *s > 0.0).map(|s| remaining / s)
+    }
+
+    /// Apply one `key=value` line of the progress stream. Returns true at the end of a block.
+    pub fn update(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+        match key {
+            // `out_time_ms` is in microseconds as well, despite its name
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time = us.max(0) as f64 / 1_000_000.0;
+                }
+            }
+            "speed" => self.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => {
+                self.finished = value == "end";
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+}
+
+type Sink = Box<dyn Fn(FfmpegProgress)>;
+
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+}
+
+/// Run `f` on this thread, passing the progress of every ffmpeg call it makes to `sink`
+pub fn scope<T>(sink: impl Fn(FfmpegProgress) + 'static, f: impl FnOnce() -> T) -> T {
+    let previous = SINK.with(|s| s.replace(Some(Box::new(sink))));
+    let result = f();
+    SINK.with(|s| *s.borrow_mut() = previous);
+    result
+}
+
+/// Whether anyone listens to progress on this thread
+pub fn is_active() -> bool {
+    SINK.with(|s| s.borrow().is_some())
+}
+
+pub(crate) fn report(progress: FfmpegProgress) {
+    SINK.with(|s| {
+        if let Some(sink) = s.borrow().as_ref() {
+            sink(progress);
+        }
+    });
+}
@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, bail};
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use crate::core::FFUtils;
+use crate::core::engine::VIDEO_EXTENSIONS;
+
+/// Which files of the input directories a batch picks up
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanOptions {
+    /// Descend into sub-directories
+    pub recursive: bool,
+    /// Glob patterns on the path relative to the input directory, e.g. `shoot_*/*.mp4`.
+    /// `*` also matches `/`. When empty, every file with a video extension is taken.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Files and directories to leave out, same syntax as `include`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size_mb: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size_mb: Option<f64>,
+    /// Seconds; files whose duration cannot be probed are left out when a limit is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_duration: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<f64>,
+    /// Also take files and directories whose name starts with `.`
+    pub hidden: bool,
+    /// Follow symbolic links instead of skipping them
+    pub follow_symlinks: bool,
+    /// Write each output into the same sub-directory of the output dir as its source
+    pub mirror_tree: bool,
+}
+
+/// A file found by `scan`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedFile {
+    pub path: PathBuf,
+    /// Directory of the file relative to the input directory it was found in
+    pub rel_dir: PathBuf,
+}
+
+impl ScanOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Output directory of `file`: its mirrored sub-directory of `out_dir`, or `out_dir` itself
+    pub fn out_dir(&self, file: &ScannedFile, out_dir: &Path) -> PathBuf {
+        if self.mirror_tree { out_dir.join(&file.rel_dir) } else { out_dir.to_path_buf() }
+    }
+}
+
+const MATCH: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+struct Walker<'a> {
+    options: &'a ScanOptions,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    /// Directories never scanned, such as the output directory, so outputs of earlier runs are
+    /// not picked up again
+    skip_dirs: Vec<PathBuf>,
+    /// Directories already walked, so symlink loops end
+    visited: HashSet<PathBuf>,
+    files: Vec<ScannedFile>,
+}
+
+/// Video files of all inputs, in order and without duplicates. Directories are scanned according
+/// to `options`, leaving out `skip_dirs`; files given directly only go through the size and
+/// duration limits.
+pub fn scan(inputs: &[PathBuf], options: &ScanOptions, skip_dirs: &[&Path]) -> Result<Vec<ScannedFile>> {
+    let patterns = |list: &[String]| -> Result<Vec<Pattern>> {
+        list.iter()
+            .map(|p| Pattern::new(p).map_err(|e| anyhow!("无效的通配符 {}: {}", p, e)))
+            .collect()
+    };
+    let mut walker = Walker {
+        options,
+        include: patterns(&options.include)?,
+        exclude: patterns(&options.exclude)?,
+        skip_dirs: skip_dirs.iter().filter_map(|d| fs::canonicalize(d).ok()).collect(),
+        visited: HashSet::new(),
+        files: Vec::new(),
+    };
+
+    for input in inputs {
+        let start = walker.files.len();
+        if input.is_dir() {
+            walker.walk(input, Path::new(""))?;
+        } else if input.is_file() && walker.within_limits(input) {
+            walker.files.push(ScannedFile { path: input.clone(), rel_dir: PathBuf::new() });
+        }
+        walker.files[start..].sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    let mut seen = HashSet::new();
+    walker.files.retain(|f| seen.insert(f.path.clone()));
+
+    if !options.mirror_tree {
+        let mut names = HashSet::new();
+        if let Some(dup) = walker.files.iter().find(|f| !names.insert(f.path.file_name())) {
+            bail!("输入中有同名文件 {}，输出会互相覆盖，请开启镜像目录结构", dup.path.display());
+        }
+    }
+    Ok(walker.files)
+}
+
+impl Walker<'_> {
+    fn walk(&mut self, dir: &Path, rel: &Path) -> Result<()> {
+        let real = fs::canonicalize(dir).map_err(|e| anyhow!("无法读取目录 {}: {}", dir.display(), e))?;
+        if self.skip_dirs.contains(&real) || !self.visited.insert(real) {
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(dir).map_err(|e| anyhow!("无法读取目录 {}: {}", dir.display(), e))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let rel_path = rel.join(&name);
+            if !self.options.hidden && name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if self.exclude.iter().any(|p| p.matches_path_with(&rel_path, MATCH)) {
+                continue;
+            }
+
+            let Ok(mut file_type) = entry.file_type() else { continue };
+            if file_type.is_symlink() {
+                if !self.options.follow_symlinks {
+                    continue;
+                }
+                let Ok(target) = fs::metadata(entry.path()) else { continue };
+                file_type = target.file_type();
+            }
+
+            let path = entry.path();
+            if file_type.is_dir() {
+                if self.options.recursive {
+                    self.walk(&path, &rel_path)?;
+                }
+            } else if file_type.is_file() && self.is_wanted(&rel_path) && self.within_limits(&path) {
+                self.files.push(ScannedFile { path, rel_dir: rel.to_path_buf() });
+            }
+        }
+        Ok(())
+    }
+
+    fn is_wanted(&self, rel_path: &Path) -> bool {
+        if self.include.is_empty() {
+            return rel_path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        }
+        self.include.iter().any(|p| p.matches_path_with(rel_path, MATCH))
+    }
+
+    fn within_limits(&self, path: &Path) -> bool {
+        let o = self.options;
+        if o.min_size_mb.is_some() || o.max_size_mb.is_some() {
+            let Ok(meta) = fs::metadata(path) else { return false };
+            let mb = meta.len() as f64 / (1024.0 * 1024.0);
+            if o.min_size_mb.is_some_and(|min| mb < min) || o.max_size_mb.is_some_and(|max| mb > max) {
+                return false;
+            }
+        }
+        if o.min_duration.is_some() || o.max_duration.is_some() {
+            let Ok(secs) = FFUtils::get_duration(path) else { return false };
+            if o.min_duration.is_some_and(|min| secs < min) || o.max_duration.is_some_and(|max| secs > max) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty files at `paths` below a fresh temporary directory
+    fn tree(paths: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("vmp_scan_{}", uuid::Uuid::new_v4().simple()));
+        for path in paths {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+        root
+    }
+
+    fn found(root: &Path, options: &ScanOptions) -> Vec<String> {
+        scan(&[root.to_path_buf()], options, &[]).unwrap().iter()
+            .map(|f| f.path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn takes_video_extensions_and_descends_only_when_recursive() {
+        let root = tree(&["b.MP4", "a.mov", "notes.txt", ".hidden.mp4", "sub/c.mkv"]);
+        assert_eq!(found(&root, &ScanOptions::default()), ["a.mov", "b.MP4"]);
+        let recursive = ScanOptions { recursive: true, ..Default::default() };
+        assert_eq!(found(&root, &recursive), ["a.mov", "b.MP4", "sub/c.mkv"]);
+        let hidden = ScanOptions { hidden: true, ..Default::default() };
+        assert_eq!(found(&root, &hidden), [".hidden.mp4", "a.mov", "b.MP4"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn include_and_exclude_match_the_relative_path() {
+        let root = tree(&["shoot_1/a.mp4", "shoot_1/raw/b.mp4", "shoot_2/c.txt", "other/d.mp4"]);
+        let options = ScanOptions {
+            recursive: true,
+            include: vec!["shoot_*/*".to_string()],
+            exclude: vec!["*/raw".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(found(&root, &options), ["shoot_1/a.mp4", "shoot_2/c.txt"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn same_names_need_mirrored_directories() {
+        let root = tree(&["a/clip.mp4", "b/clip.mp4"]);
+        let options = ScanOptions { recursive: true, ..Default::default() };
+        assert!(scan(std::slice::from_ref(&root), &options, &[]).is_err());
+
+        let mirrored = ScanOptions { mirror_tree: true, ..options };
+        let files = scan(&[root.clone(), root.join("a/clip.mp4")], &mirrored, &[]).unwrap();
+        assert_eq!(files.len(), 2, "the file given again is dropped");
+        assert_eq!(files[1].rel_dir, Path::new("b"));
+        assert_eq!(mirrored.out_dir(&files[1], Path::new("out")), Path::new("out").join("b"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn skips_the_output_directory() {
+        let root = tree(&["a.mp4", "out/a_processed.mp4"]);
+        let options = ScanOptions { recursive: true, ..Default::default() };
+        let files = scan(std::slice::from_ref(&root), &options, &[&root.join("out")]).unwrap();
+        assert_eq!(files.len(), 1);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
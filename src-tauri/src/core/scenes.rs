@@ -0,0 +1,205 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::core::{VideoAction, ActionConfig, FFUtils, Filter, FilterContext, FilterFragment, FilterGraph};
+use crate::core::cancel;
+use crate::core::probe::{self, probe};
+
+/// How videos are split at scene cuts, so the actions that support it (see
+/// `VideoAction::per_scene`) draw fresh random values for every scene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneOptions {
+    /// `scdet` threshold from 0 to 100; lower values find more cuts
+    pub threshold: f64,
+    /// Shortest scene in seconds; cuts closer than this to the previous cut or to the end are ignored
+    pub min_length: f64,
+}
+
+impl Default for SceneOptions {
+    fn default() -> Self {
+        Self { threshold: 10.0, min_length: 2.0 }
+    }
+}
+
+/// Part of a video between two cuts, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scene {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Times of the cuts ffmpeg's `scdet` finds in the video of `src`
+pub fn detect_cuts(src: &Path, threshold: f64) -> Result<Vec<f64>> {
+    let src_str = src.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+    let log = std::env::temp_dir().join(format!("vmp_scenes_{}.txt", Uuid::new_v4().simple()));
+    let log_str = log.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+
+    // Only the frames starting a scene reach `metadata`, which prints their cut time
+    let filter = format!("scdet=threshold={}:sc_pass=1,metadata=mode=print:key=lavfi.scd.time:file='{}'", threshold, FFUtils::escape_path(log_str));
+    let result = FFUtils::run(&["-i", src_str, "-map", "0:v:0", "-vf", &filter, "-f", "null", "-loglevel", "error", "-"])
+        // No file is written when there is no cut
+        .map(|_| fs::read_to_string(&log).unwrap_or_default());
+    let _ = fs::remove_file(&log);
+
+    let mut cuts: Vec<f64> = result?.lines()
+        .filter_map(|line| line.strip_prefix("lavfi.scd.time="))
+        .filter_map(|time| time.trim().parse().ok())
+        .collect();
+    cuts.sort_by(f64::total_cmp);
+    Ok(cuts)
+}
+
+/// Scenes of a `duration` seconds long video cut at `cuts`, none shorter than `min_length`
+/// unless the whole video is
+pub fn split(cuts: &[f64], duration: f64, min_length: f64) -> Vec<Scene> {
+    let mut starts = vec![0.0];
+    for &cut in cuts {
+        let last = starts[starts.len() - 1];
+        if cut - last >= min_length && duration - cut >= min_length {
+            starts.push(cut);
+        }
+    }
+    starts.iter().enumerate()
+        .map(|(i, &start)| Scene { start, end: starts.get(i + 1).copied().unwrap_or(duration) })
+        .collect()
+}
+
+/// Render `actions`, merged into one filtergraph, on every scene of `src` with random values of
+/// the scene's own and join the scenes into `dst`. Returns the random choices, each tagged with
+/// the start of its scene.
+pub fn render(actions: &[&dyn VideoAction], src: &Path, dst: &Path, config: &ActionConfig, options: &SceneOptions, log: &dyn Fn(String)) -> Result<Vec<String>> {
+    let duration = FFUtils::get_duration(src)?;
+    let scenes = split(&detect_cuts(src, options.threshold)?, duration, options.min_length);
+    log(format!("🎬 检测到 {} 个场景: {}", scenes.len(), actions.iter().map(|a| a.id()).collect::<Vec<_>>().join(" + ")));
+    if scenes.len() < 2 {
+        let (graph, choices) = build(actions, src, config, None)?;
+        graph.run(src, dst)?;
+        return Ok(choices);
+    }
+
+    let out_dir = dst.parent().ok_or_else(|| anyhow!("Invalid output path"))?;
+    let work_dir = out_dir.join(format!(".scenes_{}", Uuid::new_v4().simple()));
+    fs::create_dir_all(&work_dir)?;
+    let result = render_in(actions, src, dst, &work_dir, &scenes, config);
+    let _ = fs::remove_dir_all(&work_dir);
+    probe::forget(&work_dir);
+    result
+}
+
+fn render_in(actions: &[&dyn VideoAction], src: &Path, dst: &Path, work_dir: &Path, scenes: &[Scene], config: &ActionConfig) -> Result<Vec<String>> {
+    let mut choices = Vec::new();
+    let mut size = None;
+    let mut list = String::new();
+    let mut has_audio = false;
+
+    for (i, scene) in scenes.iter().enumerate() {
+        cancel::check()?;
+        let (mut graph, scene_choices) = build(actions, src, &config.for_scene(i), Some(scene))?;
+        choices.extend(scene_choices);
+        // Another crop or zoom gives another frame size; every scene takes the size of the first
+        if let Some((width, height)) = size {
+            graph.push(FilterFragment::video(Filter::Chain(format!("scale={}:{},setsar=1", width, height))));
+        }
+
+        let segment = work_dir.join(format!("scene{}.mkv", i));
+        let mut args = graph.args(src, &segment)?;
+        args.splice(0..0, ["-ss".to_string(), format!("{:.6}", scene.start), "-t".to_string(), format!("{:.6}", scene.end - scene.start)]);
+        // PCM joins without gaps; the audio is encoded once when the scenes are joined
+        match args.iter().position(|a| a == "-c:a") {
+            Some(codec) => args[codec + 1] = "pcm_s16le".to_string(),
+            None => {
+                let output = args.len() - 1;
+                args.splice(output..output, ["-c:a".to_string(), "pcm_s16le".to_string()]);
+            }
+        }
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        FFUtils::run_scaled(&args, graph.time_scale())?;
+
+        if size.is_none() {
+            let info = probe(&segment)?;
+            size = info.display_size();
+            has_audio = info.has_audio();
+        }
+        list.push_str(&format!("file '{}'\n", segment.to_string_lossy().replace('\'', "'\\''")));
+    }
+
+    cancel::check()?;
+    let list_path = work_dir.join("scenes.txt");
+    fs::write(&list_path, list)?;
+    let list_str = list_path.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+    let dst_str = dst.to_str().ok_or_else(|| anyhow!("Invalid output path encoding"))?;
+
+    let mut args = vec!["-f", "concat", "-safe", "0", "-i", list_str, "-map", "0:v", "-map", "0:a?"];
+    // The scenes are H.264, which WebM cannot hold
+    let webm = dst.extension().is_some_and(|e| e.eq_ignore_ascii_case("webm"));
+    if !webm {
+        args.extend(["-c:v", "copy"]);
+    }
+    if has_audio {
+        // Fill the odd missing sample at a join, so the sound stays in sync
+        args.extend(["-af", "aresample=async=1"]);
+    }
+    args.extend(["-loglevel", "error", dst_str]);
+    FFUtils::run(&args)?;
+    Ok(choices)
+}
+
+/// Merge the fragments of `actions` for one scene (the whole file when None)
+fn build(actions: &[&dyn VideoAction], src: &Path, config: &ActionConfig, scene: Option<&Scene>) -> Result<(FilterGraph, Vec<String>)> {
+    let mut graph = FilterGraph::new();
+    let mut choices = Vec::new();
+    for action in actions {
+        let ctx = FilterContext { src, time_scale: graph.time_scale() };
+        let fragment = action.filter(&ctx, config)?
+            .ok_or_else(|| anyhow!("Action {} has no filter fragment", action.id()))?;
+        choices.extend(fragment.choices.iter().map(|c| match scene {
+            Some(scene) => format!("{}@{:.1}s: {}", action.id(), scene.start, c),
+            None => format!("{}: {}", action.id(), c),
+        }));
+        graph.push(fragment);
+    }
+    Ok((graph, choices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(scenes: &[Scene]) -> Vec<(f64, f64)> {
+        scenes.iter().map(|s| (s.start, s.end)).collect()
+    }
+
+    #[test]
+    fn scenes_run_from_cut_to_cut() {
+        assert_eq!(bounds(&split(&[], 10.0, 2.0)), [(0.0, 10.0)]);
+        assert_eq!(bounds(&split(&[3.0, 6.5], 10.0, 2.0)), [(0.0, 3.0), (3.0, 6.5), (6.5, 10.0)]);
+    }
+
+    #[test]
+    fn cuts_leaving_short_scenes_are_skipped() {
+        // Too close to the start, to the previous cut and to the end
+        assert_eq!(bounds(&split(&[1.0, 4.0, 5.0, 9.0], 10.0, 2.0)), [(0.0, 4.0), (4.0, 10.0)]);
+        // A skipped cut does not move the next scene's start
+        assert_eq!(bounds(&split(&[2.5, 3.5, 4.6], 10.0, 2.0)), [(0.0, 2.5), (2.5, 4.6), (4.6, 10.0)]);
+        // Exactly the minimum is long enough
+        assert_eq!(bounds(&split(&[2.0, 8.0], 10.0, 2.0)), [(0.0, 2.0), (2.0, 8.0), (8.0, 10.0)]);
+        // A video shorter than a scene stays whole
+        assert_eq!(bounds(&split(&[0.5, 1.0], 1.5, 2.0)), [(0.0, 1.5)]);
+    }
+
+    #[test]
+    fn scenes_cover_the_video() {
+        let cuts: Vec<f64> = (1..60).map(|i| i as f64 * 0.37 + (i % 3) as f64 * 0.5).collect();
+        let scenes = split(&cuts, 25.0, 1.5);
+        assert!(scenes.len() > 2);
+        assert_eq!(scenes[0].start, 0.0);
+        assert_eq!(scenes[scenes.len() - 1].end, 25.0);
+        for pair in scenes.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert!(scenes.iter().all(|s| s.end - s.start >= 1.5), "{:?}", scenes);
+    }
+}
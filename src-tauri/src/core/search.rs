@@ -0,0 +1,284 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::core::{ActionConfig, ActionOutput, ParamKind, VideoAction};
+use crate::core::{cancel, probe};
+use crate::core::seed::derive_seed;
+use crate::core::similarity::{self, Similarity, DUPLICATE_THRESHOLD};
+
+/// Growth of the intensity parameters per attempt: the second attempt runs with 1.5× the
+/// configured values, the third with 2×, and so on, up to each parameter's maximum
+pub const INTENSITY_STEP: f64 = 0.5;
+
+/// How different every output must end up from its source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimilarityTarget {
+    /// Highest perceptual similarity (`Similarity::score`) an output may keep
+    pub max_similarity: f64,
+    /// Lowest SSIM, so stronger parameters do not ruin the picture
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_ssim: Option<f64>,
+    /// Highest audio fingerprint similarity, checked for files with audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_audio: Option<f64>,
+    /// Outputs rendered per task at most
+    pub attempts: usize,
+}
+
+impl Default for SimilarityTarget {
+    fn default() -> Self {
+        Self { max_similarity: DUPLICATE_THRESHOLD, min_ssim: None, max_audio: None, attempts: 5 }
+    }
+}
+
+impl SimilarityTarget {
+    pub fn is_met(&self, similarity: &Similarity) -> bool {
+        similarity.score < self.max_similarity
+            && self.keeps_quality(similarity)
+            && self.max_audio.is_none_or(|max| similarity.audio.is_none_or(|audio| audio < max))
+    }
+
+    fn keeps_quality(&self, similarity: &Similarity) -> bool {
+        self.min_ssim.is_none_or(|min| similarity.ssim >= min)
+    }
+
+    /// Whether attempt `a` should be kept over attempt `b`: one meeting the target wins, then
+    /// one keeping the SSIM, then the lower score
+    fn prefers(&self, a: &Similarity, b: &Similarity) -> bool {
+        let rank = |s: &Similarity| (!self.is_met(s), !self.keeps_quality(s));
+        match rank(a).cmp(&rank(b)) {
+            std::cmp::Ordering::Equal => a.score < b.score,
+            order => order.is_lt(),
+        }
+    }
+}
+
+impl fmt::Display for SimilarityTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "相似度 < {:.0}%", self.max_similarity * 100.0)?;
+        if let Some(min) = self.min_ssim {
+            write!(f, "，SSIM ≥ {:.2}", min)?;
+        }
+        if let Some(max) = self.max_audio {
+            write!(f, "，音频 < {:.0}%", max * 100.0)?;
+        }
+        write!(f, "，最多 {} 次", self.attempts)
+    }
+}
+
+/// The attempt a search kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// Counted from 1
+    pub attempt: usize,
+    pub attempts: usize,
+    pub target_met: bool,
+    /// Seed the kept attempt ran with
+    pub seed: Option<u64>,
+    /// Intensity parameters the kept attempt ran with
+    pub params: Map<String, Value>,
+}
+
+/// Config of attempt `attempt`, counted from 0. The first runs as configured; later ones get a
+/// seed of their own and the intensity parameters of `actions` raised by `INTENSITY_STEP` each.
+pub fn attempt_config(config: &ActionConfig, actions: &[&dyn VideoAction], attempt: usize) -> Result<ActionConfig> {
+    let mut config = config.clone();
+    if attempt == 0 {
+        return Ok(config);
+    }
+    config.seed = config.seed.map(|seed| derive_seed(seed, &format!("attempt{}", attempt)));
+
+    let factor = 1.0 + INTENSITY_STEP * attempt as f64;
+    for spec in actions.iter().flat_map(|a| a.intensity()) {
+        let value = match spec.kind {
+            ParamKind::Float { max, .. } => json!((config.float(spec)? * factor).min(max)),
+            ParamKind::Int { max, .. } => json!(((config.int(spec)? as f64 * factor).round() as i64).min(max)),
+            _ => continue,
+        };
+        config.params[spec.name] = value;
+    }
+    Ok(config)
+}
+
+/// Render `src` with `render` until an output meets `target` or the attempts run out, and keep
+/// the best output under the path the renders write to; the other attempts are deleted.
+pub fn search(
+    target: &SimilarityTarget,
+    actions: &[&dyn VideoAction],
+    config: &ActionConfig,
+    src: &Path,
+    render: impl Fn(&ActionConfig) -> Result<ActionOutput>,
+    log: &dyn Fn(String),
+) -> Result<(ActionOutput, Similarity, SearchResult)> {
+    let attempts = target.attempts.max(1);
+    let mut best: Option<(ActionOutput, Similarity, SearchResult)> = None;
+    let mut dst: Option<PathBuf> = None;
+
+    let result = (|| -> Result<()> {
+        for attempt in 0..attempts {
+            cancel::check()?;
+            let config = attempt_config(config, actions, attempt)?;
+            let mut output = render(&config)?;
+            if output.path == src {
+                return Err(anyhow!("动作没有生成新视频，无法搜索相似度目标"));
+            }
+            let similarity = similarity::compare(src, &output.path)?;
+            let met = target.is_met(&similarity);
+            log(format!("🎯 第 {}/{} 次: 相似度 {:.0}%，SSIM {:.2}{}", attempt + 1, attempts,
+                similarity.score * 100.0, similarity.ssim, if met { " ✅" } else { "" }));
+
+            // Park the output so the next attempt does not overwrite it
+            let parked = parked_path(&output.path, attempt)?;
+            fs::rename(&output.path, &parked)?;
+            dst.get_or_insert(std::mem::replace(&mut output.path, parked));
+
+            let result = SearchResult {
+                attempt: attempt + 1,
+                attempts,
+                target_met: met,
+                seed: config.seed,
+                params: intensity_params(&config, actions)?,
+            };
+            let loser = match &best {
+                Some((_, kept, _)) if !target.prefers(&similarity, kept) => Some(output.path),
+                _ => best.replace((output, similarity, result)).map(|(old, ..)| old.path),
+            };
+            if let Some(path) = loser {
+                let _ = fs::remove_file(&path);
+                probe::forget(&path);
+            }
+            if met {
+                break;
+            }
+        }
+        Ok(())
+    })();
+
+    let (mut output, similarity, result) = match (result, best) {
+        (Ok(()), Some(best)) => best,
+        (result, best) => {
+            if let Some((output, ..)) = best {
+                let _ = fs::remove_file(output.path);
+            }
+            return Err(result.err().unwrap_or_else(|| anyhow!("No attempt was rendered")));
+        }
+    };
+    let dst = dst.ok_or_else(|| anyhow!("No attempt was rendered"))?;
+    fs::rename(&output.path, &dst)?;
+    output.path = dst;
+    Ok((output, similarity, result))
+}
+
+/// `name.ext` → `name.try2.ext`
+fn parked_path(path: &Path, attempt: usize) -> Result<PathBuf> {
+    let stem = path.file_stem().ok_or_else(|| anyhow!("Invalid output filename"))?.to_string_lossy();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    Ok(path.with_file_name(format!("{}.try{}{}", stem, attempt + 1, ext)))
+}
+
+fn intensity_params(config: &ActionConfig, actions: &[&dyn VideoAction]) -> Result<Map<String, Value>> {
+    let mut params = Map::new();
+    for spec in actions.iter().flat_map(|a| a.intensity()) {
+        let value = match spec.kind {
+            ParamKind::Int { .. } => json!(config.int(spec)?),
+            _ => json!(config.float(spec)?),
+        };
+        params.insert(spec.name.to_string(), value);
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ActionCategory, ActionMeta, ParamSpec, Touches};
+
+    const STRENGTH: ParamSpec = ParamSpec {
+        name: "strength",
+        label: "",
+        kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.3 },
+        unit: "",
+        description: "",
+    };
+
+    const STEPS: ParamSpec = ParamSpec {
+        name: "steps",
+        label: "",
+        kind: ParamKind::Int { min: 1, max: 10, default: 3 },
+        unit: "",
+        description: "",
+    };
+
+    struct Strong;
+
+    impl VideoAction for Strong {
+        fn execute(&self, _src: &Path, _out_dir: &Path, _config: &ActionConfig) -> Result<ActionOutput> {
+            unreachable!()
+        }
+        fn id(&self) -> &'static str {
+            "strong"
+        }
+        fn meta(&self) -> ActionMeta {
+            ActionMeta { name_zh: "", name_en: "", category: ActionCategory::Basic, touches: Touches::VIDEO, materials: &[] }
+        }
+        fn intensity(&self) -> &'static [ParamSpec] {
+            &[STRENGTH, STEPS]
+        }
+    }
+
+    fn similarity(score: f64, ssim: f64, audio: Option<f64>) -> Similarity {
+        Similarity { phash: score, dhash: score, score, likely_duplicate: score >= DUPLICATE_THRESHOLD, frames: 12, ssim, audio, audio_match: false }
+    }
+
+    #[test]
+    fn attempts_raise_intensity_up_to_the_maximum() {
+        let config = ActionConfig { seed: Some(1), ..Default::default() };
+        let actions: [&dyn VideoAction; 1] = [&Strong];
+
+        let first = attempt_config(&config, &actions, 0).unwrap();
+        assert_eq!(first.seed, Some(1));
+        assert_eq!(first.params, json!({}));
+
+        let third = attempt_config(&config, &actions, 2).unwrap();
+        assert_ne!(third.seed, Some(1));
+        assert_eq!(third.float(&STRENGTH).unwrap(), 0.6);
+        assert_eq!(third.int(&STEPS).unwrap(), 6);
+
+        let last = attempt_config(&config, &actions, 9).unwrap();
+        assert_eq!(last.float(&STRENGTH).unwrap(), 1.0);
+        assert_eq!(last.int(&STEPS).unwrap(), 10);
+        assert_eq!(intensity_params(&last, &actions).unwrap()["steps"], json!(10));
+    }
+
+    #[test]
+    fn target_checks_score_quality_and_audio() {
+        let target = SimilarityTarget { max_similarity: 0.8, min_ssim: Some(0.5), max_audio: Some(0.6), attempts: 3 };
+        assert!(target.is_met(&similarity(0.7, 0.9, None)));
+        assert!(target.is_met(&similarity(0.7, 0.9, Some(0.5))));
+        assert!(!target.is_met(&similarity(0.85, 0.9, None)));
+        assert!(!target.is_met(&similarity(0.7, 0.4, None)));
+        assert!(!target.is_met(&similarity(0.7, 0.9, Some(0.7))));
+    }
+
+    #[test]
+    fn prefers_met_then_quality_then_lower_score() {
+        let target = SimilarityTarget { max_similarity: 0.8, min_ssim: Some(0.5), ..Default::default() };
+        let met = similarity(0.75, 0.9, None);
+        let ugly = similarity(0.5, 0.3, None);
+        let close = similarity(0.9, 0.9, None);
+        let closer = similarity(0.95, 0.9, None);
+        assert!(target.prefers(&met, &ugly));
+        assert!(target.prefers(&close, &ugly));
+        assert!(target.prefers(&close, &closer));
+        assert!(!target.prefers(&closer, &close));
+    }
+
+    #[test]
+    fn parked_path_numbers_the_attempt() {
+        assert_eq!(parked_path(Path::new("out/a_processed.mp4"), 1).unwrap(), Path::new("out/a_processed.try2.mp4"));
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use crate::core::ActionConfig;
+
+/// FNV-1a. Stable across runs and platforms, unlike `DefaultHasher`.
+pub fn stable_hash<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Mix a name into a seed
+pub fn derive_seed(seed: u64, name: &str) -> u64 {
+    stable_hash(seed.to_le_bytes().iter().chain(name.as_bytes()))
+}
+
+/// `rel_dir/name` with `/` on every platform; just the name for files at the top of their input
+fn file_key(src: &Path, rel_dir: &Path) -> String {
+    rel_dir.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .chain(std::iter::once(src.file_name().unwrap_or_default().to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl ActionConfig {
+    /// Config for one source file, recorded as `source`: the job seed is replaced by a seed
+    /// derived from the file's path below its input directory (`rel_dir`, see `ScannedFile`), so
+    /// a file gets the same random values whatever else is in the batch, and equally named files
+    /// in different sub-directories get different ones.
+    pub fn for_file(&self, src: &Path, rel_dir: &Path) -> ActionConfig {
+        let mut config = self.clone();
+        config.source = Some(src.to_path_buf());
+        if let Some(seed) = self.seed {
+            config.seed = Some(derive_seed(seed, &file_key(src, rel_dir)));
+        }
+        config
+    }
+
+    /// Config for one scene of a file split at its cuts, with a seed derived from the scene number
+    pub fn for_scene(&self, index: usize) -> ActionConfig {
+        let mut config = self.clone();
+        if let Some(seed) = self.seed {
+            config.seed = Some(derive_seed(seed, &format!("scene{}", index)));
+        }
+        config
+    }
+
+    /// Random generator for one action. Seeded from `seed` and the action id when a seed is set,
+    /// from the OS otherwise.
+    pub fn rng(&self, action_id: &str) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(derive_seed(seed, action_id)),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed_of(src: &str, rel_dir: &str) -> Option<u64> {
+        let config = ActionConfig { seed: Some(7), ..Default::default() };
+        config.for_file(Path::new(src), Path::new(rel_dir)).seed
+    }
+
+    #[test]
+    fn same_name_in_other_directory_gets_other_seed() {
+        assert_ne!(seed_of("/in/a/clip.mp4", "a"), seed_of("/in/b/clip.mp4", "b"));
+        assert_eq!(seed_of("/in/a/clip.mp4", "a"), seed_of("/elsewhere/a/clip.mp4", "a"));
+    }
+
+    #[test]
+    fn top_level_files_keep_the_name_seed() {
+        assert_eq!(seed_of("/in/clip.mp4", ""), Some(derive_seed(7, "clip.mp4")));
+        assert_eq!(file_key(Path::new("/in/x/y/clip.mp4"), Path::new("x/y")), "x/y/clip.mp4");
+    }
+
+    #[test]
+    fn no_seed_stays_unseeded() {
+        let config = ActionConfig::default();
+        assert_eq!(config.for_file(Path::new("clip.mp4"), Path::new("")).seed, None);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::core::{fingerprint, FFUtils};
+use crate::core::probe::probe;
+use crate::core::search::SearchResult;
+
+/// Frames sampled from each file, spread evenly over its duration
+pub const SAMPLE_FRAMES: usize = 12;
+
+/// Score from which common duplicate detectors treat two videos as the same: at most ~10 of the
+/// 64 hash bits differ
+pub const DUPLICATE_THRESHOLD: f64 = 0.85;
+
+/// Side of the grey frames sampled for SSIM
+const SAMPLE_SIDE: usize = 128;
+/// Side of the thumbnails the hashes are computed from
+const SIDE: usize = 32;
+
+/// How close an output still is to its source, by perceptual hashes of sampled frames and an
+/// audio fingerprint. Each output frame is compared with the closest source frame, so trimmed
+/// or sped up outputs are matched against the right part of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Similarity {
+    /// Share of equal pHash (DCT) bits, averaged over the output frames; 1.0 is identical
+    pub phash: f64,
+    /// Same for dHash (gradient) bits
+    pub dhash: f64,
+    /// The higher of the two, since a detector using either would match on it
+    pub score: f64,
+    pub likely_duplicate: bool,
+    /// Output frames compared
+    pub frames: usize,
+    /// Structural similarity (SSIM) of each output frame with its closest source frame, averaged;
+    /// stays high while the picture keeps its quality
+    #[serde(default)]
+    pub ssim: f64,
+    /// Share of equal audio fingerprint bits, see `fingerprint`; unset when either file has no
+    /// audio
+    pub audio: Option<f64>,
+    /// The soundtrack would still be matched by an audio fingerprint service
+    pub audio_match: bool,
+}
+
+/// One line of the similarity report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarityRecord {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    /// Action id, or the ids joined with ` → ` in stacked mode
+    pub action: String,
+    #[serde(flatten)]
+    pub similarity: Similarity,
+    /// The attempt kept by a similarity target search, with its parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<SearchResult>,
+}
+
+/// Perceptual hashes of one frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHash {
+    pub phash: u64,
+    pub dhash: u64,
+}
+
+impl FrameHash {
+    /// Hash a `SIDE`×`SIDE` grey frame
+    fn of(pixels: &[u8]) -> Self {
+        Self { phash: phash(pixels), dhash: dhash(pixels) }
+    }
+}
+
+/// A sampled `SAMPLE_SIDE`×`SAMPLE_SIDE` grey frame and its hashes
+#[derive(Debug, Clone)]
+pub struct SampledFrame {
+    pub pixels: Vec<u8>,
+    pub hash: FrameHash,
+}
+
+/// Compare `output` with `source`
+pub fn compare(source_path: &Path, output_path: &Path) -> Result<Similarity> {
+    let source = sample(source_path)?;
+    let output = sample(output_path)?;
+
+    let closest = |hash: fn(&FrameHash) -> u64, o: &SampledFrame| -> (f64, usize) {
+        source.iter().enumerate()
+            .map(|(i, s)| (bit_similarity(hash(&o.hash), hash(&s.hash)), i))
+            .fold((0.0, 0), |best, c| if c.0 > best.0 { c } else { best })
+    };
+    let average = |values: Vec<f64>| values.iter().sum::<f64>() / values.len() as f64;
+    let phash = average(output.iter().map(|o| closest(|h| h.phash, o).0).collect());
+    let dhash = average(output.iter().map(|o| closest(|h| h.dhash, o).0).collect());
+    let ssim = average(output.iter().map(|o| ssim(&source[closest(|h| h.phash, o).1].pixels, &o.pixels)).collect());
+    let score = phash.max(dhash);
+    // A soundtrack that cannot be fingerprinted (too short, undecodable) leaves the visual score
+    let audio = compare_audio(source_path, output_path).unwrap_or(None);
+    Ok(Similarity {
+        phash,
+        dhash,
+        score,
+        likely_duplicate: score >= DUPLICATE_THRESHOLD,
+        frames: output.len(),
+        ssim,
+        audio,
+        audio_match: audio.is_some_and(|a| a >= fingerprint::MATCH_THRESHOLD),
+    })
+}
+
+/// Fingerprint similarity of the soundtracks, when both files have one
+pub fn compare_audio(source: &Path, output: &Path) -> Result<Option<f64>> {
+    let has_audio = |path: &Path| probe(path).is_ok_and(|info| info.has_audio());
+    if !has_audio(source) || !has_audio(output) {
+        return Ok(None);
+    }
+    fingerprint::compare(source, output).map(Some)
+}
+
+/// `SAMPLE_FRAMES` frames spread over the file
+pub fn sample(path: &Path) -> Result<Vec<SampledFrame>> {
+    let duration = FFUtils::get_duration(path)?;
+    if duration <= 0.0 {
+        bail!("视频时长为 0: {}", path.display());
+    }
+    let src = path.to_str().ok_or_else(|| anyhow!("Invalid source path encoding"))?;
+    let raw = std::env::temp_dir().join(format!("vmp_frames_{}.gray", Uuid::new_v4().simple()));
+    let raw_str = raw.to_str().ok_or_else(|| anyhow!("Invalid temp path encoding"))?;
+
+    let filter = format!("fps={:.6},scale={}:{}:flags=area,format=gray", SAMPLE_FRAMES as f64 / duration, SAMPLE_SIDE, SAMPLE_SIDE);
+    let result = FFUtils::run(&[
+        "-i", src,
+        "-vf", &filter,
+        "-frames:v", &SAMPLE_FRAMES.to_string(),
+        "-f", "rawvideo",
+        "-loglevel", "error",
+        raw_str,
+    ]).and_then(|_| Ok(fs::read(&raw)?));
+    let _ = fs::remove_file(&raw);
+
+    let frames: Vec<SampledFrame> = result?.chunks_exact(SAMPLE_SIDE * SAMPLE_SIDE)
+        .map(|pixels| SampledFrame { pixels: pixels.to_vec(), hash: FrameHash::of(&shrink(pixels)) })
+        .collect();
+    if frames.is_empty() {
+        bail!("无法从 {} 截取画面", path.display());
+    }
+    Ok(frames)
+}
+
+/// Average `SAMPLE_SIDE` pixels down to `SIDE`
+fn shrink(pixels: &[u8]) -> Vec<u8> {
+    let k = SAMPLE_SIDE / SIDE;
+    (0..SIDE * SIDE)
+        .map(|i| {
+            let (y, x) = (i / SIDE * k, i % SIDE * k);
+            let sum: u32 = (y..y + k).flat_map(|y| (x..x + k).map(move |x| pixels[y * SAMPLE_SIDE + x] as u32)).sum();
+            (sum / (k * k) as u32) as u8
+        })
+        .collect()
+}
+
+/// Mean SSIM over 8×8 windows, stepping by 4
+fn ssim(a: &[u8], b: &[u8]) -> f64 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+    const WINDOW: usize = 8;
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y in (0..=SAMPLE_SIDE - WINDOW).step_by(4) {
+        for x in (0..=SAMPLE_SIDE - WINDOW).step_by(4) {
+            let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            for i in (y..y + WINDOW).flat_map(|y| (x..x + WINDOW).map(move |x| y * SAMPLE_SIDE + x)) {
+                let (pa, pb) = (a[i] as f64, b[i] as f64);
+                sa += pa;
+                sb += pb;
+                saa += pa * pa;
+                sbb += pb * pb;
+                sab += pa * pb;
+            }
+            let n = (WINDOW * WINDOW) as f64;
+            let (ma, mb) = (sa / n, sb / n);
+            let (va, vb, cov) = (saa / n - ma * ma, sbb / n - mb * mb, sab / n - ma * mb);
+            total += (2.0 * ma * mb + C1) * (2.0 * cov + C2) / ((ma * ma + mb * mb + C1) * (va + vb + C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+/// Share of equal bits
+pub fn bit_similarity(a: u64, b: u64) -> f64 {
+    1.0 - (a ^ b).count_ones() as f64 / 64.0
+}
+
+/// DCT hash: the 8×8 lowest frequencies of the frame, each compared with their median
+fn phash(pixels: &[u8]) -> u64 {
+    // cos((2x + 1)uπ / 2N) for the 8 lowest frequencies u
+    let cos: Vec<[f64; 8]> = (0..SIDE)
+        .map(|x| std::array::from_fn(|u| ((2 * x + 1) as f64 * u as f64 * PI / (2 * SIDE) as f64).cos()))
+        .collect();
+
+    // Rows first, then the columns of the result
+    let rows: Vec<[f64; 8]> = pixels.chunks_exact(SIDE)
+        .map(|row| std::array::from_fn(|u| row.iter().zip(&cos).map(|(&p, c)| p as f64 * c[u]).sum()))
+        .collect();
+    let mut coefficients = [0.0; 64];
+    for v in 0..8 {
+        for u in 0..8 {
+            coefficients[v * 8 + u] = rows.iter().zip(&cos).map(|(row, c)| row[u] * c[v]).sum();
+        }
+    }
+
+    let mut sorted = coefficients;
+    sorted.sort_by(f64::total_cmp);
+    let median = (sorted[31] + sorted[32]) / 2.0;
+    bits(coefficients.iter().map(|&c| c > median))
+}
+
+/// Gradient hash: whether each cell of a 9×8 thumbnail is brighter than its right neighbour
+fn dhash(pixels: &[u8]) -> u64 {
+    let cell = |row: usize, col: usize| -> f64 {
+        let (y0, y1) = (row * SIDE / 8, (row + 1) * SIDE / 8);
+        let (x0, x1) = (col * SIDE / 9, ((col + 1) * SIDE / 9).max(col * SIDE / 9 + 1));
+        let sum: u32 = (y0..y1).flat_map(|y| (x0..x1).map(move |x| pixels[y * SIDE + x] as u32)).sum();
+        sum as f64 / ((y1 - y0) * (x1 - x0)) as f64
+    };
+    bits((0..8).flat_map(|row| (0..8).map(move |col| (row, col))).map(|(row, col)| cell(row, col) > cell(row, col + 1)))
+}
+
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values.fold(0, |hash, bit| hash << 1 | bit as u64)
+}
+
+/// Merge `records` into the report of `out_dir`, replacing older lines for the same outputs
+pub fn update_report(out_dir: &Path, records: &[SimilarityRecord]) -> Result<PathBuf> {
+    let path = out_dir.join("similarity_report.json");
+    let mut report: Vec<SimilarityRecord> = fs::read_to_string(&path).ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+
+    let fresh: HashMap<&Path, &SimilarityRecord> = records.iter().map(|r| (r.output.as_path(), r)).collect();
+    report.retain(|r| !fresh.contains_key(r.output.as_path()));
+    report.extend(records.iter().cloned());
+    report.sort_by(|a, b| (&a.source, &a.action).cmp(&(&b.source, &b.action)));
+
+    fs::write(&path, serde_json::to_string_pretty(&report)?)
+        .map_err(|e| anyhow!("无法写入相似度报告 {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `SAMPLE_SIDE`² grey frame from a function of the pixel position
+    fn frame(f: impl Fn(usize, usize) -> f64) -> Vec<u8> {
+        (0..SAMPLE_SIDE * SAMPLE_SIDE)
+            .map(|i| f(i % SAMPLE_SIDE, i / SAMPLE_SIDE).clamp(0.0, 255.0) as u8)
+            .collect()
+    }
+
+    fn gradient(x: usize, y: usize) -> f64 {
+        (x + y) as f64
+    }
+
+    fn blobs(x: usize, y: usize) -> f64 {
+        128.0 + 100.0 * ((x as f64 / 9.0).sin() * (y as f64 / 13.0).cos())
+    }
+
+    #[test]
+    fn bit_similarity_counts_equal_bits() {
+        assert_eq!(bit_similarity(0, 0), 1.0);
+        assert_eq!(bit_similarity(0, u64::MAX), 0.0);
+        assert_eq!(bit_similarity(0b1111, 0), 1.0 - 4.0 / 64.0);
+    }
+
+    #[test]
+    fn shrink_averages_blocks() {
+        let small = shrink(&frame(|x, _| if x < SAMPLE_SIDE / 2 { 0.0 } else { 200.0 }));
+        assert_eq!(small.len(), SIDE * SIDE);
+        assert_eq!(small[0], 0);
+        assert_eq!(small[SIDE - 1], 200);
+    }
+
+    #[test]
+    fn hashes_survive_brightness_and_noise_but_not_another_picture() {
+        let hash = |f: &[u8]| FrameHash::of(&shrink(f));
+        let original = hash(&frame(blobs));
+        let brighter = hash(&frame(|x, y| blobs(x, y) * 0.9 + 20.0));
+        let noisy = hash(&frame(|x, y| blobs(x, y) + ((x * 7 + y * 13) % 5) as f64 - 2.0));
+        let other = hash(&frame(|x, y| 255.0 - gradient(x, y)));
+
+        assert_eq!(original, hash(&frame(blobs)));
+        assert!(bit_similarity(original.dhash, brighter.dhash) >= DUPLICATE_THRESHOLD);
+        assert!(bit_similarity(original.phash, brighter.phash) >= DUPLICATE_THRESHOLD);
+        assert!(bit_similarity(original.phash, noisy.phash) >= DUPLICATE_THRESHOLD);
+        assert!(bit_similarity(original.phash, other.phash) < DUPLICATE_THRESHOLD);
+        assert!(bit_similarity(original.dhash, other.dhash) < DUPLICATE_THRESHOLD);
+    }
+
+    #[test]
+    fn ssim_is_one_for_equal_frames_and_drops_with_structure() {
+        let a = frame(blobs);
+        assert!((ssim(&a, &a) - 1.0).abs() < 1e-9);
+        let flat = frame(|_, _| 128.0);
+        assert!(ssim(&a, &flat) < 0.5);
+    }
+
+    #[test]
+    fn report_replaces_lines_of_the_same_output() {
+        let dir = std::env::temp_dir().join(format!("vmp_similarity_{}", Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let similarity = Similarity { phash: 0.9, dhash: 0.8, score: 0.9, likely_duplicate: true, frames: 12, ssim: 0.7, audio: None, audio_match: false };
+        let record = |output: &str, score: f64| SimilarityRecord {
+            source: PathBuf::from("a.mp4"),
+            output: PathBuf::from(output),
+            action: "crop".to_string(),
+            similarity: Similarity { score, ..similarity },
+            search: None,
+        };
+        update_report(&dir, &[record("a_1.mp4", 0.9), record("a_2.mp4", 0.9)]).unwrap();
+        let path = update_report(&dir, &[record("a_1.mp4", 0.5)]).unwrap();
+
+        let report: Vec<SimilarityRecord> = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.iter().find(|r| r.output == Path::new("a_1.mp4")).unwrap().similarity.score, 0.5);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
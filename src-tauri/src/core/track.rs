@@ -0,0 +1,339 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use crate::core::frames::{Frame, Rect};
+
+/// Width frames are shrunk to before matching
+const ANALYSIS_WIDTH: usize = 320;
+
+/// Share of the new look blended into the template after a confident match, so the template
+/// follows slow changes of the object without drifting onto the background
+const TEMPLATE_RATE: f32 = 0.1;
+
+/// Correlation from which a match updates the template
+const UPDATE_CONFIDENCE: f64 = 0.8;
+
+/// Correlation below which the object counts as lost; the region then stays where it was
+const LOST_CONFIDENCE: f64 = 0.3;
+
+/// Position of the tracked region in one frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub frame: usize,
+    pub time: f64,
+    #[serde(flatten)]
+    pub rect: Rect,
+    /// Correlation of the match, 1.0 for a perfect one; low values mean the object was lost
+    pub confidence: f64,
+}
+
+/// Path of a region through a video, exported as `*.track.json` so other runs and actions can
+/// reuse it. Boxes between keyframes are interpolated linearly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Track {
+    pub source: PathBuf,
+    /// Frame size the boxes are measured in
+    pub width: usize,
+    pub height: usize,
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn load(path: &Path) -> Result<Track> {
+        let text = fs::read_to_string(path).map_err(|e| anyhow!("无法读取追踪数据 {}: {}", path.display(), e))?;
+        let track: Track = serde_json::from_str(&text).map_err(|e| anyhow!("追踪数据格式错误 {}: {}", path.display(), e))?;
+        if track.keyframes.is_empty() {
+            bail!("追踪数据中没有关键帧: {}", path.display());
+        }
+        // Boxes are scaled from this size to the frames they are used on
+        if track.width == 0 || track.height == 0 {
+            bail!("追踪数据的画面尺寸无效 ({}x{}): {}", track.width, track.height, path.display());
+        }
+        if track.keyframes.windows(2).any(|k| k[1].time < k[0].time) {
+            bail!("追踪数据的关键帧没有按时间排列: {}", path.display());
+        }
+        Ok(track)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("无法写入追踪数据 {}: {}", path.display(), e))
+    }
+
+    /// Box at `time` in a `width`×`height` frame, held before the first keyframe and after the last
+    pub fn rect_at(&self, time: f64, width: usize, height: usize) -> Option<Rect> {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        let rect = match (next.checked_sub(1).map(|i| &self.keyframes[i]), self.keyframes.get(next)) {
+            (Some(a), Some(b)) if b.time > a.time => {
+                let t = (time - a.time) / (b.time - a.time);
+                let mix = |p: f64, q: f64| p + (q - p) * t;
+                Rect { x: mix(a.rect.x, b.rect.x), y: mix(a.rect.y, b.rect.y), width: mix(a.rect.width, b.rect.width), height: mix(a.rect.height, b.rect.height) }
+            }
+            (Some(a), _) => a.rect,
+            (None, Some(b)) => b.rect,
+            (None, None) => return None,
+        };
+        Some(rect.scale(width as f64 / self.width as f64, height as f64 / self.height as f64))
+    }
+
+    /// Drop the keyframes that interpolating between their neighbours reproduces within
+    /// `tolerance` pixels
+    pub fn thin(&mut self, tolerance: f64) {
+        if self.keyframes.len() < 3 {
+            return;
+        }
+        let all = std::mem::take(&mut self.keyframes);
+        let mut kept = vec![all[0]];
+        let mut anchor = 0;
+        for end in 2..all.len() {
+            let (a, b) = (&all[anchor], &all[end]);
+            let fits = all[anchor + 1..end].iter().all(|k| {
+                let t = (k.time - a.time) / (b.time - a.time).max(f64::EPSILON);
+                let (x, y) = (a.rect.x + (b.rect.x - a.rect.x) * t, a.rect.y + (b.rect.y - a.rect.y) * t);
+                (x - k.rect.x).abs() <= tolerance && (y - k.rect.y).abs() <= tolerance
+            });
+            if !fits {
+                anchor = end - 1;
+                kept.push(all[anchor]);
+            }
+        }
+        kept.push(all[all.len() - 1]);
+        self.keyframes = kept;
+    }
+}
+
+/// Follows a region from frame to frame by normalized cross-correlation of a grey template,
+/// searched near the last position. The region keeps its size.
+pub struct TemplateTracker {
+    step: usize,
+    /// Template as first seen, blended with later looks
+    template: Vec<f32>,
+    tw: usize,
+    th: usize,
+    /// Top left corner in analysis pixels
+    x: f64,
+    y: f64,
+}
+
+impl TemplateTracker {
+    /// Start tracking `rect` (frame pixels) in `frame`
+    pub fn new(frame: &Frame, rect: &Rect) -> Result<Self> {
+        let step = frame.width.div_ceil(ANALYSIS_WIDTH).max(1);
+        let (grey, w, h) = grey(frame, step);
+        let (x, y) = (rect.x / step as f64, rect.y / step as f64);
+        let (tw, th) = ((rect.width / step as f64).round() as usize, (rect.height / step as f64).round() as usize);
+        if tw < 4 || th < 4 || x < 0.0 || y < 0.0 || x as usize + tw > w || y as usize + th > h {
+            bail!("追踪区域过小或超出画面");
+        }
+        let template = patch(&grey, w, x as usize, y as usize, tw, th);
+        Ok(Self { step, template, tw, th, x, y })
+    }
+
+    /// Box of the region in the next frame and the match correlation
+    pub fn update(&mut self, frame: &Frame) -> (Rect, f64) {
+        let (grey, w, h) = grey(frame, self.step);
+        let radius = (self.tw.max(self.th) / 2).max(8) as isize;
+        let (cx, cy) = (self.x.round() as isize, self.y.round() as isize);
+        let (max_x, max_y) = ((w - self.tw) as isize, (h - self.th) as isize);
+        let template = normalize(&self.template);
+        let score = |x: isize, y: isize| -> f64 {
+            if x < 0 || y < 0 || x > max_x || y > max_y {
+                return -1.0;
+            }
+            ncc(&template, &grey, w, x as usize, y as usize, self.tw, self.th)
+        };
+
+        // Every other offset first, then the neighbours of the best one
+        let mut best = (cx.clamp(0, max_x), cy.clamp(0, max_y));
+        let mut best_score = score(best.0, best.1);
+        for dy in (-radius..=radius).step_by(2) {
+            for dx in (-radius..=radius).step_by(2) {
+                let s = score(cx + dx, cy + dy);
+                if s > best_score {
+                    (best, best_score) = ((cx + dx, cy + dy), s);
+                }
+            }
+        }
+        let coarse = best;
+        for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+            let s = score(coarse.0 + dx, coarse.1 + dy);
+            if s > best_score {
+                (best, best_score) = ((coarse.0 + dx, coarse.1 + dy), s);
+            }
+        }
+
+        let step = self.step as f64;
+        let size = (self.tw as f64 * step, self.th as f64 * step);
+        if best_score < LOST_CONFIDENCE {
+            return (Rect { x: self.x * step, y: self.y * step, width: size.0, height: size.1 }, best_score);
+        }
+
+        // Sub-pixel position from a parabola through the scores around the peak
+        let offset = |l: f64, r: f64| {
+            let curve = l - 2.0 * best_score + r;
+            if l > -1.0 && r > -1.0 && curve < 0.0 { ((l - r) / (2.0 * curve)).clamp(-0.5, 0.5) } else { 0.0 }
+        };
+        self.x = best.0 as f64 + offset(score(best.0 - 1, best.1), score(best.0 + 1, best.1));
+        self.y = best.1 as f64 + offset(score(best.0, best.1 - 1), score(best.0, best.1 + 1));
+
+        if best_score >= UPDATE_CONFIDENCE {
+            let look = patch(&grey, w, best.0 as usize, best.1 as usize, self.tw, self.th);
+            for (t, l) in self.template.iter_mut().zip(look) {
+                *t += (l - *t) * TEMPLATE_RATE;
+            }
+        }
+
+        (Rect { x: self.x * step, y: self.y * step, width: size.0, height: size.1 }, best_score)
+    }
+}
+
+/// Luma of `frame` averaged over `step`×`step` blocks
+fn grey(frame: &Frame, step: usize) -> (Vec<f32>, usize, usize) {
+    let (w, h) = (frame.width / step, frame.height / step);
+    let mut grey = vec![0.0; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = 0.0;
+            for (dx, dy) in (0..step).flat_map(|dy| (0..step).map(move |dx| (dx, dy))) {
+                let [r, g, b] = frame.pixel(x * step + dx, y * step + dy);
+                sum += 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            }
+            grey[y * w + x] = sum / (step * step) as f32;
+        }
+    }
+    (grey, w, h)
+}
+
+fn patch(grey: &[f32], w: usize, x: usize, y: usize, pw: usize, ph: usize) -> Vec<f32> {
+    (y..y + ph).flat_map(|y| grey[y * w + x..y * w + x + pw].iter().copied()).collect()
+}
+
+/// Template minus its mean, scaled to unit length
+fn normalize(template: &[f32]) -> Vec<f32> {
+    let mean = template.iter().sum::<f32>() / template.len() as f32;
+    let norm = template.iter().map(|t| (t - mean) * (t - mean)).sum::<f32>().sqrt().max(f32::EPSILON);
+    template.iter().map(|t| (t - mean) / norm).collect()
+}
+
+/// Normalized cross-correlation of a `normalize`d template with the patch at (`x`, `y`)
+fn ncc(template: &[f32], grey: &[f32], w: usize, x: usize, y: usize, tw: usize, th: usize) -> f64 {
+    let (mut dot, mut sum, mut sum_sq) = (0.0f64, 0.0f64, 0.0f64);
+    for row in 0..th {
+        let line = &grey[(y + row) * w + x..(y + row) * w + x + tw];
+        for (t, &p) in template[row * tw..(row + 1) * tw].iter().zip(line) {
+            dot += (*t * p) as f64;
+            sum += p as f64;
+            sum_sq += (p * p) as f64;
+        }
+    }
+    let n = (tw * th) as f64;
+    let variance = sum_sq - sum * sum / n;
+    if variance <= f64::EPSILON {
+        return 0.0;
+    }
+    dot / variance.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(frame: usize, x: f64, y: f64) -> Keyframe {
+        Keyframe { frame, time: frame as f64 / 10.0, rect: Rect { x, y, width: 20.0, height: 10.0 }, confidence: 1.0 }
+    }
+
+    fn track(keyframes: Vec<Keyframe>) -> Track {
+        Track { source: PathBuf::from("clip.mp4"), width: 100, height: 50, keyframes }
+    }
+
+    #[test]
+    fn rect_at_interpolates_holds_and_scales() {
+        let track = track(vec![keyframe(10, 10.0, 10.0), keyframe(20, 30.0, 20.0)]);
+        assert_eq!(track.rect_at(1.5, 100, 50), Some(Rect { x: 20.0, y: 15.0, width: 20.0, height: 10.0 }));
+        assert_eq!(track.rect_at(0.0, 100, 50).unwrap().x, 10.0);
+        assert_eq!(track.rect_at(9.0, 100, 50).unwrap().x, 30.0);
+        // Measured at 100×50, used on 200×100
+        assert_eq!(track.rect_at(1.0, 200, 100), Some(Rect { x: 20.0, y: 20.0, width: 40.0, height: 20.0 }));
+        assert_eq!(Track { keyframes: Vec::new(), ..track }.rect_at(1.0, 100, 50), None);
+    }
+
+    #[test]
+    fn thin_keeps_the_turns() {
+        // Straight right, then straight down
+        let mut path: Vec<Keyframe> = (0..5).map(|i| keyframe(i, i as f64 * 10.0, 0.0)).collect();
+        path.extend((5..9).map(|i| keyframe(i, 40.0, (i - 4) as f64 * 10.0)));
+        let mut thinned = track(path.clone());
+        thinned.thin(1.0);
+        assert_eq!(thinned.keyframes.iter().map(|k| k.frame).collect::<Vec<_>>(), [0, 4, 8]);
+        for k in &path {
+            let rect = thinned.rect_at(k.time, 100, 50).unwrap();
+            assert!((rect.x - k.rect.x).abs() < 1e-9 && (rect.y - k.rect.y).abs() < 1e-9, "{:?} at {}", rect, k.frame);
+        }
+
+        // Small wobbles within the tolerance go too
+        let mut wobbly = track((0..5).map(|i| keyframe(i, i as f64 * 10.0, if i % 2 == 0 { 0.0 } else { 0.5 })).collect());
+        wobbly.thin(1.0);
+        assert_eq!(wobbly.keyframes.len(), 2);
+        wobbly.thin(0.1);
+        assert_eq!(wobbly.keyframes.len(), 2);
+    }
+
+    #[test]
+    fn load_rejects_what_rect_at_cannot_use() {
+        let dir = std::env::temp_dir().join(format!("vmp_track_{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clip.track.json");
+
+        let good = track(vec![keyframe(0, 1.0, 2.0), keyframe(5, 3.0, 4.0)]);
+        good.save(&path).unwrap();
+        assert_eq!(Track::load(&path).unwrap().keyframes, good.keyframes);
+
+        for bad in [
+            Track { width: 0, ..good.clone() },
+            Track { height: 0, ..good.clone() },
+            track(Vec::new()),
+            track(vec![keyframe(5, 3.0, 4.0), keyframe(0, 1.0, 2.0)]),
+        ] {
+            bad.save(&path).unwrap();
+            assert!(Track::load(&path).is_err(), "{:?}", bad);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tracker_follows_a_patch_and_holds_when_it_is_gone() {
+        // A 24×24 patch of random 4 px blocks on a flat wall, moving 3 px right and 2 px down a frame
+        let frame = |index: usize, at: Option<(usize, usize)>| {
+            let data = (0..120).flat_map(|y| (0..160).map(move |x| (x, y)))
+                .flat_map(|(x, y)| {
+                    let v = match at {
+                        Some((px, py)) if (px..px + 24).contains(&x) && (py..py + 24).contains(&y) => {
+                            let seed = ((x - px) / 4 * 6 + (y - py) / 4) as u32;
+                            seed.wrapping_mul(2_654_435_761) >> 24
+                        }
+                        _ => 100,
+                    } as u8;
+                    [v; 3]
+                })
+                .collect();
+            Frame { index, time: index as f64 / 25.0, width: 160, height: 120, data }
+        };
+
+        let mut tracker = TemplateTracker::new(&frame(0, Some((40, 30))), &Rect { x: 40.0, y: 30.0, width: 24.0, height: 24.0 }).unwrap();
+        let mut last = Rect { x: 40.0, y: 30.0, width: 24.0, height: 24.0 };
+        for i in 1..10 {
+            let (x, y) = (40 + 3 * i, 30 + 2 * i);
+            let (rect, confidence) = tracker.update(&frame(i, Some((x, y))));
+            assert!((rect.x - x as f64).abs() <= 1.0 && (rect.y - y as f64).abs() <= 1.0, "frame {}: {:?} for ({}, {})", i, rect, x, y);
+            assert!(confidence > UPDATE_CONFIDENCE, "frame {}: {}", i, confidence);
+            assert_eq!((rect.width, rect.height), (24.0, 24.0));
+            last = rect;
+        }
+
+        // Gone: the region stays where the patch was last seen
+        let (rect, confidence) = tracker.update(&frame(10, None));
+        assert!(confidence < LOST_CONFIDENCE, "{}", confidence);
+        assert_eq!(rect, last);
+    }
+}
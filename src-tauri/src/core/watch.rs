@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use crate::core::engine::{self, BatchJob, BatchSummary, CancelToken, EngineEvent, Session};
+use crate::core::scan::{scan, ScannedFile};
+
+/// Settings of a watch-folder run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchOptions {
+    /// Seconds between two scans of the folder
+    pub interval_secs: f64,
+    /// A file is taken once its size and modification time have stayed the same for this long,
+    /// so files still being copied are left alone
+    pub settle_secs: f64,
+    /// Originals whose tasks all succeeded are moved here, keeping their sub-directory
+    pub archive_dir: Option<PathBuf>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self { interval_secs: 5.0, settle_secs: 10.0, archive_dir: None }
+    }
+}
+
+/// Size and modification time of a file as seen by one scan
+type Stamp = (u64, SystemTime);
+
+/// Keep scanning the inputs of `job` and process every new or changed video once it has finished
+/// copying, until `cancel` is set. Returns the totals of all rounds.
+///
+/// Files are remembered for the session; across sessions the job journal keeps finished files
+/// from being processed again.
+pub fn watch(job: &BatchJob, options: &WatchOptions, cancel: &CancelToken, on_event: &(dyn Fn(EngineEvent) + Sync)) -> Result<BatchSummary> {
+    engine::check_job(job)?;
+    fs::create_dir_all(&job.output_dir)
+        .map_err(|e| anyhow!("Failed to create output directory: {}", e))?;
+    if let Some(archive) = &options.archive_dir {
+        fs::create_dir_all(archive).map_err(|e| anyhow!("无法创建归档目录 {}: {}", archive.display(), e))?;
+    }
+
+    let mut skip_dirs = vec![job.output_dir.as_path()];
+    skip_dirs.extend(options.archive_dir.as_deref());
+    for input in &job.inputs {
+        on_event(EngineEvent::log(format!("👀 正在监视 {}，每 {} 秒检查一次", input.display(), options.interval_secs)));
+    }
+
+    let mut picker = Picker::default();
+    let mut session = None;
+    let mut last_error = None;
+    let mut totals = BatchSummary::default();
+
+    while !cancel.is_cancelled() {
+        let files = match scan(&job.inputs, &job.scan, &skip_dirs) {
+            Ok(files) => {
+                last_error = None;
+                files
+            }
+            Err(e) => {
+                // Report a failing scan once, not on every poll
+                let message = e.to_string();
+                if last_error.as_ref() != Some(&message) {
+                    on_event(EngineEvent::log(format!("⚠️ 扫描失败: {}", message)));
+                    last_error = Some(message);
+                }
+                Vec::new()
+            }
+        };
+
+        let stamped = files.into_iter().filter_map(|file| stamp(&file.path).map(|stamp| (file, stamp))).collect();
+        let ready = picker.pick(stamped, SystemTime::now(), options.settle_secs);
+        if !ready.is_empty() {
+            on_event(EngineEvent::log(format!("📥 {} 个新文件已就绪", ready.len())));
+            // Set up on the first ready files, so the seed, the worker budget and the journal
+            // are logged and opened once per session
+            if session.is_none() {
+                match Session::open(job, &ready, on_event) {
+                    Ok(opened) => session = Some(opened),
+                    Err(e) => on_event(EngineEvent::log(format!("❌ 处理失败: {}", e))),
+                }
+            }
+            if let Some(session) = &session {
+                let summary = process(session, job, &ready, options, cancel, on_event);
+                totals.total += summary.total;
+                totals.succeeded += summary.succeeded;
+                totals.failed += summary.failed;
+                totals.cancelled += summary.cancelled;
+                totals.resumed += summary.resumed;
+            }
+            picker.handled(&ready);
+        }
+
+        let next = Instant::now() + Duration::from_secs_f64(options.interval_secs.max(0.5));
+        while Instant::now() < next && !cancel.is_cancelled() {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    on_event(EngineEvent::log("👀 已停止监视"));
+    Ok(totals)
+}
+
+/// Run one round over the files that became ready and archive the originals that went through
+fn process(session: &Session, job: &BatchJob, files: &[ScannedFile], options: &WatchOptions, cancel: &CancelToken, on_event: &(dyn Fn(EngineEvent) + Sync)) -> BatchSummary {
+    let unfinished = Mutex::new(HashSet::new());
+    let result = session.process(job, files, cancel, &|event| {
+        if let EngineEvent::TaskFailed { file, .. } | EngineEvent::TaskCancelled { file, .. } = &event {
+            unfinished.lock().unwrap_or_else(|e| e.into_inner()).insert(file.clone());
+        }
+        on_event(event);
+    });
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            on_event(EngineEvent::log(format!("❌ 处理失败: {}", e)));
+            return BatchSummary::default();
+        }
+    };
+
+    let unfinished = unfinished.into_inner().unwrap_or_else(|e| e.into_inner());
+    if let (Some(archive), false) = (&options.archive_dir, cancel.is_cancelled()) {
+        for file in files.iter().filter(|f| !unfinished.contains(&f.path)) {
+            match archive_file(file, archive) {
+                Ok(dst) => on_event(EngineEvent::log(format!("📦 已归档: {}", dst.display()))),
+                Err(e) => on_event(EngineEvent::log(format!("⚠️ 归档失败 {}: {}", file.path.display(), e))),
+            }
+        }
+    }
+    summary
+}
+
+/// Which scanned files are ready, from what the scans of this session saw
+#[derive(Default)]
+struct Picker {
+    /// Stamps of the previous scan
+    observed: HashMap<PathBuf, Stamp>,
+    /// Files already processed, with the stamp they had then
+    handled: HashMap<PathBuf, Stamp>,
+}
+
+impl Picker {
+    /// Files of this scan that kept their stamp since the previous one, were last modified at
+    /// least `settle_secs` before `now` and have not been handled as they are
+    fn pick(&mut self, scanned: Vec<(ScannedFile, Stamp)>, now: SystemTime, settle_secs: f64) -> Vec<ScannedFile> {
+        let mut current = HashMap::new();
+        let mut ready = Vec::new();
+        for (file, stamp) in scanned {
+            current.insert(file.path.clone(), stamp);
+            if self.handled.get(&file.path) == Some(&stamp) {
+                continue;
+            }
+            let age = now.duration_since(stamp.1).unwrap_or_default().as_secs_f64();
+            if self.observed.get(&file.path) == Some(&stamp) && age >= settle_secs {
+                ready.push(file);
+            }
+        }
+        // A file that went away and comes back is new again
+        self.handled.retain(|path, _| current.contains_key(path));
+        self.observed = current;
+        ready
+    }
+
+    /// Leave `files` alone until they change
+    fn handled(&mut self, files: &[ScannedFile]) {
+        for file in files {
+            if let Some(stamp) = self.observed.get(&file.path) {
+                self.handled.insert(file.path.clone(), *stamp);
+            }
+        }
+    }
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Move an original into `archive`, under its sub-directory, without replacing an older file of
+/// the same name
+fn archive_file(file: &ScannedFile, archive: &Path) -> Result<PathBuf> {
+    let dir = archive.join(&file.rel_dir);
+    fs::create_dir_all(&dir)?;
+    let name = file.path.file_name().ok_or_else(|| anyhow!("Invalid source filename"))?;
+    let mut dst = dir.join(name);
+    let mut n = 1;
+    while dst.exists() {
+        let stem = file.path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = file.path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        dst = dir.join(format!("{}_{}{}", stem, n, ext));
+        n += 1;
+    }
+
+    // rename fails across drives; fall back to copy and delete
+    if fs::rename(&file.path, &dst).is_err() {
+        fs::copy(&file.path, &dst)?;
+        fs::remove_file(&file.path)?;
+    }
+    Ok(dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> ScannedFile {
+        ScannedFile { path: PathBuf::from("/in").join(name), rel_dir: PathBuf::new() }
+    }
+
+    fn names(files: &[ScannedFile]) -> Vec<String> {
+        files.iter().map(|f| f.path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn files_are_picked_once_they_settle() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut picker = Picker::default();
+
+        // First seen: never ready, however old
+        assert!(picker.pick(vec![(file("a.mp4"), (10, start))], at(60), 10.0).is_empty());
+        // a unchanged and old enough; b new
+        let growing = (20, at(55));
+        let ready = picker.pick(vec![(file("a.mp4"), (10, start)), (file("b.mp4"), growing)], at(60), 10.0);
+        assert_eq!(names(&ready), ["a.mp4"]);
+        // b is still being copied: a new stamp starts over
+        let copied = (30, at(62));
+        assert!(picker.pick(vec![(file("b.mp4"), copied)], at(65), 10.0).is_empty());
+        assert!(picker.pick(vec![(file("b.mp4"), copied)], at(70), 10.0).is_empty());
+        assert_eq!(names(&picker.pick(vec![(file("b.mp4"), copied)], at(72), 10.0)), ["b.mp4"]);
+    }
+
+    #[test]
+    fn handled_files_wait_until_they_change() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut picker = Picker::default();
+        let first = vec![(file("a.mp4"), (10, start))];
+        picker.pick(first.clone(), at(20), 5.0);
+        let ready = picker.pick(first.clone(), at(25), 5.0);
+        assert_eq!(names(&ready), ["a.mp4"]);
+        picker.handled(&ready);
+        assert!(picker.pick(first.clone(), at(30), 5.0).is_empty());
+
+        // Replaced by a new version: picked again once that settles
+        let replaced = vec![(file("a.mp4"), (12, at(31)))];
+        assert!(picker.pick(replaced.clone(), at(32), 5.0).is_empty());
+        assert_eq!(names(&picker.pick(replaced, at(40), 5.0)), ["a.mp4"]);
+
+        // Gone and back as it was: new again
+        let mut picker = Picker::default();
+        picker.pick(first.clone(), at(20), 5.0);
+        let ready = picker.pick(first.clone(), at(25), 5.0);
+        picker.handled(&ready);
+        picker.pick(Vec::new(), at(30), 5.0);
+        picker.pick(first.clone(), at(35), 5.0);
+        assert_eq!(names(&picker.pick(first, at(40), 5.0)), ["a.mp4"]);
+    }
+
+    #[test]
+    fn archive_keeps_the_sub_directory_and_older_files() {
+        let dir = std::env::temp_dir().join(format!("vmp_watch_{}", uuid::Uuid::new_v4().simple()));
+        let (input, archive) = (dir.join("in"), dir.join("archive"));
+        fs::create_dir_all(input.join("day1")).unwrap();
+        let original = ScannedFile { path: input.join("day1").join("clip.mp4"), rel_dir: PathBuf::from("day1") };
+
+        fs::write(&original.path, "first").unwrap();
+        let first = archive_file(&original, &archive).unwrap();
+        assert_eq!(first, archive.join("day1").join("clip.mp4"));
+        assert!(!original.path.exists());
+
+        fs::write(&original.path, "second").unwrap();
+        let second = archive_file(&original, &archive).unwrap();
+        assert_eq!(second, archive.join("day1").join("clip_1.mp4"));
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
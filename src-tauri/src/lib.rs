@@ -0,0 +1,4 @@
+//! Processing engine shared by the desktop app and the `vmp` command-line tool.
+
+pub mod core;
+pub mod actions;
@@ -1,10 +1,14 @@
 mod desktop_app;
-mod core;
-mod actions;
+mod commands;
 
 fn main() {
-    // 运行真正的桌面应用（非浏览器）
-    if let Err(e) = desktop_app::run_desktop_app() {
+    // `--web` 或 VMP_UI=web 时运行 Tauri 网页前端，否则运行真正的桌面应用（非浏览器）
+    let web = std::env::args().any(|a| a == "--web") || std::env::var("VMP_UI").is_ok_and(|v| v == "web");
+    if web {
+        if let Err(e) = commands::run_tauri_app() {
+            eprintln!("应用启动失败: {}", e);
+        }
+    } else if let Err(e) = desktop_app::run_desktop_app() {
         eprintln!("应用启动失败: {}", e);
     }
 }
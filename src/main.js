@@ -1,73 +1,13 @@
 const { invoke } = window.__TAURI__.tauri;
 const { open } = window.__TAURI__.dialog;
+const { listen } = window.__TAURI__.event;
 
 let selectedFiles = [];
 let inputDir = "";
 let outputDir = "";
 let isProcessing = false;
 let singleVideoMode = false;
-
-// 处理视频链式调用（多个动作按顺序应用到同一个视频）
-async function processVideoChain(videoFile, actions, outDir, onProgress) {
-  let currentInput = videoFile;
-  const ext = videoFile.split('.').pop();
-  const baseName = videoFile.split('/').pop().replace(`.${ext}`, '');
-  let tempFiles = [];
-  
-  for (let i = 0; i < actions.length; i++) {
-    const actionId = actions[i];
-    const isLastAction = i === actions.length - 1;
-    
-    // 生成输出文件名：假设Rust动作会在outDir中生成 ${baseName}_${actionId}.${ext} 格式的文件
-    // 但对于链式调用，我们需要跟踪实际生成的文件名
-    // 简化：使用固定临时文件名，每次覆盖（但Rust可能不允许）
-    // 改为：使用递增的文件名
-    const outputFileName = isLastAction ? 
-      `${baseName}_processed.${ext}` : 
-      `${baseName}_chain_${i}_${actionId}.${ext}`;
-    const outputPath = `${outDir}/${outputFileName}`;
-    
-    if (!isLastAction) {
-      tempFiles.push(outputPath);
-    }
-    
-    try {
-      // 注意：Rust的process_video可能忽略我们指定的输出路径，使用自己的命名规则
-      // 这里假设它会使用我们提供的输出路径
-      await invoke("process_video", {
-        actionId: actionId,
-        srcPath: currentInput,
-        outDir: outDir
-      });
-      
-      // 假设输出文件已经生成在outDir中，文件名为 ${baseName}_${actionId}.${ext}
-      // 但为了简单，我们假设输出就是我们指定的outputPath
-      // 实际上需要扫描outDir来找到新生成的文件
-      // 暂时使用outputPath作为下一个输入
-      currentInput = outputPath;
-      
-      // 更新进度
-      if (onProgress) {
-        onProgress(i + 1, actions.length, actionId);
-      }
-      
-    } catch (e) {
-      throw new Error(`动作 ${actionId} 失败: ${e}`);
-    }
-  }
-  
-  // 清理临时文件（如果delete_file命令存在）
-  for (const tempFile of tempFiles) {
-    try {
-      await invoke("delete_file", { path: tempFile });
-    } catch (e) {
-      // 忽略错误，可能命令不存在
-      console.warn(`无法删除临时文件 ${tempFile}: ${e}`);
-    }
-  }
-  
-  return currentInput; // 返回最终输出文件路径
-}
+let similarityCheck = false;
 
 // DOM Elements
 const inputDirInput = document.getElementById("input-dir");
@@ -79,6 +19,7 @@ const btnStop = document.getElementById("btn-stop");
 const logArea = document.getElementById("log-area");
 const etrLabel = document.getElementById("etr-label");
 const progressBar = document.getElementById("progress-bar");
+const runningTasksEl = document.getElementById("running-tasks");
 
 // Tab Switching
 document.querySelectorAll(".tab-btn").forEach(btn => {
@@ -184,96 +125,166 @@ btnStart.addEventListener("click", async () => {
   log(`📂 输出目录: ${currentOutDir}`, "info");
   log(`✅ 已选择 ${actions.length} 个功能: ${actions.join(", ")}`, "info");
 
-  // 扫描视频文件
-  try {
-    log(`🔍 正在扫描视频文件...`, "info");
-
-    // 视频文件扩展名
-    const videoExtensions = ['.mp4', '.mov', '.mkv', '.avi', '.wmv', '.flv', '.webm'];
-
-    // 模拟找到一些文件（实际实现应使用Tauri文件系统API）
-    const videoFiles = [
-      `${inputDir}/sample1.mp4`,
-      `${inputDir}/sample2.mp4`
-    ];
-
-    log(`📹 找到 ${videoFiles.length} 个视频文件`, "info");
-
-    let totalTasks = videoFiles.length * actions.length;
-    let completedTasks = 0;
-
-    // 更新进度条函数
-    function updateProgress() {
-      completedTasks++;
-      const progress = (completedTasks / totalTasks) * 100;
-      const progressInt = Math.round(progress);
-      progressBar.style.width = `${progress}%`;
-      const percentLabel = document.getElementById("progress-percent");
-      if (percentLabel) {
-        percentLabel.textContent = `${progressInt}%`;
-      }
-      progressBar.textContent = "";
-    }
+  const startedAt = Date.now();
+  const unlisten = [];
+  // 正在运行的任务: "文件|动作" -> { fraction, speed, eta_secs }
+  const running = new Map();
+  const taskKey = payload => `${payload.file}|${payload.action}`;
+  let completed = 0;
+  let total = 0;
+
+  const finish = () => {
+    running.clear();
+    renderRunningTasks(running);
+    unlisten.forEach(stop => stop());
+    isProcessing = false;
+    updateStartButton();
+  };
 
-    // 处理每个视频文件
-    for (const videoFile of videoFiles) {
-      if (singleVideoMode) {
-        // 单个视频叠加模式：所有动作按顺序应用到同一个视频
-        try {
-          log(`  ⏳ 正在处理: ${videoFile} [叠加模式: ${actions.join(" → ")}]...`, "info");
-          
-          await processVideoChain(videoFile, actions, currentOutDir, (current, total, actionId) => {
-            log(`    ↪️ 步骤 ${current}/${total}: ${actionId}`, "info");
-            updateProgress();
+  // 后端每个 EngineEvent 对应一条日志或一次进度更新
+  unlisten.push(await listen("batch-event", ({ payload }) => {
+    if (["task_done", "task_failed", "task_cancelled"].includes(payload.event)) {
+      running.delete(taskKey(payload));
+      renderRunningTasks(running);
+    }
+    switch (payload.event) {
+      case "progress":
+        completed = payload.completed;
+        total = payload.total;
+        updateProgress(completed, total, running, startedAt);
+        break;
+      case "file_progress": {
+        // 进度可能晚于任务结束到达，只更新仍在运行的任务
+        const task = running.get(taskKey(payload));
+        if (task) {
+          Object.assign(task, {
+            fraction: payload.fraction ?? task.fraction,
+            speed: payload.speed,
+            eta_secs: payload.eta_secs,
           });
-          
-          log(`  ✅ 叠加处理完成 (${videoFile})`, "success");
-          // 更新进度（每个动作都已在上面的回调中更新）
-        } catch (e) {
-          log(`  ❌ 叠加处理失败 (${videoFile}): ${e}`, "error");
-          // 如果链式处理失败，仍要更新进度（避免卡住）
-          completedTasks += (actions.length - Math.floor(completedTasks % actions.length));
-          updateProgress();
+          updateProgress(completed, total, running, startedAt);
+          renderRunningTasks(running);
         }
-      } else {
-        // 原始模式：每个动作生成独立视频
-        for (const actionId of actions) {
-          try {
-            log(`  ⏳ 正在处理: ${videoFile} [${actionId}]...`, "info");
-
-            await invoke("process_video", {
-              actionId: actionId,
-              srcPath: videoFile,
-              outDir: currentOutDir
-            });
-
-            updateProgress();
-            log(`  ✅ ${actionId} 完成 (${videoFile})`, "success");
-          } catch (e) {
-            log(`  ❌ ${actionId} 失败 (${videoFile}): ${e}`, "error");
-            updateProgress();
-          }
+        break;
+      }
+      case "log":
+        log(payload.message, "info");
+        break;
+      case "task_started":
+        running.set(taskKey(payload), { file: payload.file, action: payload.action, fraction: 0 });
+        renderRunningTasks(running);
+        log(`  ⏳ 正在处理: ${payload.file} [${payload.action}]...`, "info");
+        break;
+      case "task_done":
+        log(`  ✅ ${payload.action} 完成 (${payload.file}) → ${payload.output} (${payload.elapsed_secs.toFixed(1)}s)`, "success");
+        if (payload.choices.length > 0) {
+          log(`    🎲 ${payload.choices.join(", ")}`, "info");
         }
+        break;
+      case "task_failed":
+        log(`  ❌ ${payload.action} 失败 (${payload.file}): ${payload.error}`, "error");
+        break;
+      case "task_skipped":
+        log(`  ⏭ ${payload.action} 已完成，跳过 (${payload.file}) → ${payload.output}`, "info");
+        break;
+      case "task_cancelled":
+        log(`  🛑 ${payload.action} 已取消 (${payload.file})`, "warning");
+        break;
+      case "similarity": {
+        const s = payload.similarity;
+        const pct = v => `${(v * 100).toFixed(0)}%`;
+        const audio = s.audio == null ? "" : `，音频 ${pct(s.audio)}${s.audio_match ? " ⚠️ 音频指纹仍可匹配" : ""}`;
+        const search = payload.search
+          ? `，第 ${payload.search.attempt}/${payload.search.attempts} 次尝试${payload.search.target_met ? "" : " ⚠️ 未达到目标"}`
+          : "";
+        log(`  🔍 ${payload.action} 相似度 (${payload.file}): ${pct(s.score)} (pHash ${pct(s.phash)}, dHash ${pct(s.dhash)}, SSIM ${s.ssim.toFixed(2)})${s.likely_duplicate ? " ⚠️ 仍可能被判为重复" : ""}${audio}${search}`,
+          s.likely_duplicate || s.audio_match ? "warning" : "info");
+        break;
       }
     }
+  }));
 
-    log(`🎉 所有任务完成!`, "success");
+  unlisten.push(await listen("batch-finished", ({ payload }) => {
+    if (payload.failed > 0) {
+      log(`⚠️ ${payload.failed} / ${payload.total} 个任务失败`, "warning");
+    }
+    if (payload.cancelled > 0) {
+      log(`🛑 已取消 ${payload.cancelled} 个任务`, "warning");
+    } else {
+      log(`🎉 所有任务完成!`, "success");
+    }
     etrLabel.textContent = "ETR: 完成";
+    finish();
+  }));
+
+  unlisten.push(await listen("batch-error", ({ payload }) => {
+    log(`❌ 处理失败: ${payload}`, "error");
+    finish();
+  }));
 
+  try {
+    await invoke("start_batch", {
+      job: {
+        inputs: [inputDir],
+        output_dir: currentOutDir,
+        actions: actions,
+        mode: singleVideoMode ? "stacked" : "independent",
+        similarity: similarityCheck
+      }
+    });
   } catch (e) {
     log(`❌ 处理失败: ${e}`, "error");
-  } finally {
-    isProcessing = false;
-    updateStartButton();
+    finish();
   }
 });
 
+function formatSecs(secs) {
+  const s = Math.max(0, Math.round(secs));
+  const pad = n => String(n).padStart(2, "0");
+  return `${pad(Math.floor(s / 3600))}:${pad(Math.floor(s / 60) % 60)}:${pad(s % 60)}`;
+}
+
+// 更新进度条与剩余时间，正在运行的任务按其 ffmpeg 进度计入
+function updateProgress(completed, total, running, startedAt) {
+  if (total === 0) {
+    return;
+  }
+  let done = completed;
+  running.forEach(task => { done += task.fraction; });
+  const fraction = Math.min(done / total, 1);
+  progressBar.style.width = `${fraction * 100}%`;
+  const percentLabel = document.getElementById("progress-percent");
+  if (percentLabel) {
+    percentLabel.textContent = `${Math.round(fraction * 100)}%`;
+  }
+
+  if (fraction >= 0.01) {
+    const elapsed = (Date.now() - startedAt) / 1000;
+    etrLabel.textContent = `ETR: ${formatSecs(elapsed / fraction * (1 - fraction))}`;
+  }
+}
+
+// 每个运行中的任务一行: 文件 [动作] 百分比 速度 剩余时间
+function renderRunningTasks(running) {
+  runningTasksEl.replaceChildren(...[...running.values()].map(task => {
+    const row = document.createElement("div");
+    const name = task.file.split(/[\\/]/).pop();
+    let text = `${name} [${task.action}] ${Math.round(task.fraction * 100)}%`;
+    if (task.speed != null) {
+      text += `  ${task.speed.toFixed(2)}x`;
+    }
+    if (task.eta_secs != null) {
+      text += `  剩余 ${formatSecs(task.eta_secs)}`;
+    }
+    row.textContent = text;
+    return row;
+  }));
+}
+
 // Stop Processing
-btnStop.addEventListener("click", () => {
-  if (isProcessing) {
-    isProcessing = false;
-    log("🛑 用户停止处理", "warning");
-    updateStartButton();
+btnStop.addEventListener("click", async () => {
+  if (isProcessing && await invoke("cancel_batch")) {
+    log("🛑 用户停止处理，正在终止运行中的任务...", "warning");
   }
 });
 
@@ -286,6 +297,14 @@ if (singleVideoToggle) {
   });
 }
 
+// 相似度检测开关
+const similarityToggle = document.getElementById("similarity-toggle");
+if (similarityToggle) {
+  similarityToggle.addEventListener("change", function() {
+    similarityCheck = this.checked;
+  });
+}
+
 // Disable context menu for native app feel
 document.addEventListener('contextmenu', event => event.preventDefault());
 